@@ -1,15 +1,29 @@
 use crate::format::normalize_text;
 use crate::model::{MenuGroup, TodayMenu};
+use anyhow::bail;
 use html_escape::decode_html_entities;
 use scraper::{Html, Selector};
 
+/// Containers of the consent tools seen in front of antell.fi.
+const CONSENT_SELECTORS: &str =
+    "#CybotCookiebotDialog, #onetrust-consent-sdk, #cmplz-cookiebanner-container, .cookie-consent";
+const CONSENT_KEYWORDS: [&str; 5] = [
+    "hyväksy evästeet",
+    "evästeasetukset",
+    "accept cookies",
+    "accept all cookies",
+    "cookie consent",
+];
+
 fn element_text(element: &scraper::element_ref::ElementRef) -> String {
     let raw = element.text().collect::<Vec<_>>().join(" ");
     let decoded = decode_html_entities(&raw);
     normalize_text(decoded.as_ref())
 }
 
-pub fn parse_antell_html(html: &str, today_key: &str) -> TodayMenu {
+/// Parses the print view of an Antell lunch list. A page with no menu sections but a cookie
+/// consent wall is an error, so the wall isn't shown (or cached) as an empty menu.
+pub fn parse_antell_html(html: &str, today_key: &str) -> anyhow::Result<TodayMenu> {
     let document = Html::parse_document(html);
     let section_sel = Selector::parse("section.menu-section").unwrap();
     let title_sel = Selector::parse("h2.menu-title").unwrap();
@@ -18,6 +32,10 @@ pub fn parse_antell_html(html: &str, today_key: &str) -> TodayMenu {
 
     let mut menus = Vec::new();
 
+    if document.select(&section_sel).next().is_none() && is_consent_page(&document) {
+        bail!("Menu blocked by consent page");
+    }
+
     for section in document.select(&section_sel) {
         let items: Vec<String> = section
             .select(&item_sel)
@@ -47,12 +65,63 @@ pub fn parse_antell_html(html: &str, today_key: &str) -> TodayMenu {
             name,
             price,
             components: items,
+            energy: Vec::new(),
+            prices: Vec::new(),
         });
     }
 
-    TodayMenu {
+    Ok(TodayMenu {
         date_iso: today_key.to_string(),
         lunch_time: String::new(),
         menus,
+        closed: false,
+    })
+}
+
+/// A consent container whose text asks about cookies. The keywords alone don't count: the
+/// same words sit in the ordinary site footer of a page that simply has no menu today.
+fn is_consent_page(document: &Html) -> bool {
+    let consent_sel = Selector::parse(CONSENT_SELECTORS).unwrap();
+    document.select(&consent_sel).any(|container| {
+        let text = element_text(&container).to_lowercase();
+        CONSENT_KEYWORDS
+            .iter()
+            .any(|keyword| text.contains(keyword))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_antell_html;
+
+    #[test]
+    fn consent_wall_is_an_error_not_an_empty_menu() {
+        let wall = r#"<html><body>
+            <div id="CybotCookiebotDialog"><h2>Tämä sivusto käyttää evästeitä</h2>
+            <button>Hyväksy evästeet</button></div>
+            </body></html>"#;
+        let err = parse_antell_html(wall, "2026-10-16").unwrap_err();
+        assert_eq!(err.to_string(), "Menu blocked by consent page");
+
+        let footer = r#"<html><body><p>Ei listaa tälle päivälle.</p>
+            <footer><a href="/evasteet">Evästeasetukset</a></footer></body></html>"#;
+        assert!(parse_antell_html(footer, "2026-10-16")
+            .unwrap()
+            .menus
+            .is_empty());
+
+        let empty = "<html><body><p>Ei listaa tälle päivälle.</p></body></html>";
+        assert!(parse_antell_html(empty, "2026-10-16")
+            .unwrap()
+            .menus
+            .is_empty());
+
+        let menu = r#"<html><body>
+            <div class="cookie-consent">Hyväksy evästeet</div>
+            <section class="menu-section"><h2 class="menu-title">Lounas</h2>
+            <ul class="menu-list"><li>Lohikeitto</li></ul></section>
+            </body></html>"#;
+        let parsed = parse_antell_html(menu, "2026-10-16").unwrap();
+        assert_eq!(parsed.menus[0].components, vec!["Lohikeitto".to_string()]);
     }
 }
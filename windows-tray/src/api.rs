@@ -1,20 +1,51 @@
 use crate::antell;
-use crate::format::{normalize_optional, normalize_text};
-use crate::model::{ApiResponse, ApiSetMenu, MenuGroup, TodayMenu};
+use crate::cache;
+use crate::format::{normalize_optional, normalize_text, parse_compass_price_entries, text_for};
+use crate::log::log_line;
+use crate::model::{ApiResponse, ApiSetMenu, MenuGroup, TodayMenu, WeekMenu};
 use crate::restaurant::{restaurant_for_code, Provider, Restaurant};
-use crate::settings::Settings;
+use crate::settings::{menu_language, Settings};
+use crate::util::{date_key, local_date_key, local_now};
 use anyhow::{anyhow, Context};
 use html_escape::decode_html_entities;
 use regex::Regex;
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
 use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashSet;
-use time::{Month, OffsetDateTime};
+use time::{Date, Duration, Month};
+use windows::Win32::Networking::WinInet::{InternetGetConnectedState, INTERNET_CONNECTION};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FetchErrorKind {
+    #[default]
+    None,
+    Network,
+    Timeout,
+    HttpStatus(u16),
+    Parse,
+    Config,
+}
+
+impl FetchErrorKind {
+    pub fn is_network(self) -> bool {
+        matches!(self, FetchErrorKind::Network | FetchErrorKind::Timeout)
+    }
+
+    /// Config errors (missing ids, unknown restaurant) won't fix themselves on retry.
+    pub fn is_retryable(self) -> bool {
+        !matches!(self, FetchErrorKind::None | FetchErrorKind::Config)
+    }
+}
 
 pub struct FetchOutput {
     pub ok: bool,
     pub error_message: String,
+    pub error_kind: FetchErrorKind,
     pub today_menu: Option<TodayMenu>,
+    pub week_menu: WeekMenu,
     pub restaurant_name: String,
     pub restaurant_url: String,
     pub provider: Provider,
@@ -22,110 +53,176 @@ pub struct FetchOutput {
     pub payload_date: String,
 }
 
-pub fn fetch_today(settings: &Settings) -> FetchOutput {
-    let restaurant = restaurant_for_code(
-        &settings.restaurant_code,
-        settings.enable_antell_restaurants,
-    );
+pub fn fetch_week(settings: &Settings) -> FetchOutput {
+    match build_client(settings) {
+        Ok(client) => fetch_week_with_client(settings, &client),
+        Err(err) => {
+            let restaurant = restaurant_for_code(&settings.restaurant_code, settings);
+            failure_output(
+                &restaurant,
+                restaurant.provider,
+                err.to_string(),
+                error_kind(&err),
+                String::new(),
+            )
+        }
+    }
+}
+
+/// Like `fetch_week`, but reuses `client` so several restaurants can share its connections.
+pub fn fetch_week_with_client(settings: &Settings, client: &Client) -> FetchOutput {
+    let settings = &with_menu_language(settings);
+    let restaurant = restaurant_for_code(&settings.restaurant_code, settings);
     match restaurant.provider {
-        Provider::Compass => fetch_compass(settings, restaurant),
-        Provider::CompassRss => fetch_compass_rss(settings, restaurant),
-        Provider::Antell => fetch_antell(restaurant),
-        Provider::HuomenJson => fetch_huomen(settings, restaurant),
+        Provider::Compass => fetch_compass(settings, client, restaurant),
+        Provider::CompassRss => fetch_compass_rss(settings, client, restaurant),
+        Provider::Antell => fetch_antell(settings, client, restaurant),
+        Provider::HuomenJson => fetch_huomen(settings, client, restaurant),
+        Provider::SodexoJson => fetch_sodexo(settings, client, restaurant),
+        Provider::UnicaJson => fetch_unica(settings, client, restaurant),
     }
 }
 
-fn fetch_compass(settings: &Settings, restaurant: Restaurant) -> FetchOutput {
-    let url = format!(
-        "https://www.compass-group.fi/menuapi/feed/json?costNumber={}&language={}",
-        restaurant.code, settings.language
-    );
-    let client = match Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
+/// `settings` with `language` set to the restaurant's `language_overrides` entry, so the
+/// request, the parsers and the cache keys below all use it.
+fn with_menu_language(settings: &Settings) -> Cow<'_, Settings> {
+    let language = menu_language(settings, &settings.restaurant_code);
+    if language == settings.language {
+        return Cow::Borrowed(settings);
+    }
+    Cow::Owned(Settings {
+        language: language.to_string(),
+        ..settings.clone()
+    })
+}
+
+/// Cheap local check that needs no DNS or socket, so a resume from sleep doesn't wait out
+/// the fetch timeout before showing the cached menu.
+pub fn network_available() -> bool {
+    let mut flags = INTERNET_CONNECTION(0);
+    unsafe { InternetGetConnectedState(&mut flags, 0).is_ok() }
+}
+
+/// Failure reported instead of fetching while the machine is offline.
+pub fn offline_output(settings: &Settings) -> FetchOutput {
+    let restaurant = restaurant_for_code(&settings.restaurant_code, settings);
+    failure_output(
+        &restaurant,
+        restaurant.provider,
+        "No network connection".to_string(),
+        FetchErrorKind::Network,
+        String::new(),
+    )
+}
+
+const USER_AGENT: &str = concat!(
+    "compass-lunch-plasmoid/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/veetir/compass-lunch-plasmoid)"
+);
+
+pub fn build_client(settings: &Settings) -> reqwest::Result<Client> {
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(std::time::Duration::from_secs(
+            settings.fetch_timeout_secs as u64,
+        ))
         .build()
-    {
-        Ok(c) => c,
-        Err(err) => {
-            return FetchOutput {
-                ok: false,
-                error_message: err.to_string(),
-                today_menu: None,
-                restaurant_name: String::new(),
-                restaurant_url: String::new(),
-                provider: Provider::Compass,
-                raw_json: String::new(),
-                payload_date: String::new(),
-            };
-        }
+}
+
+/// Fetches the Compass JSON feed. When it answers with an HTTP error or a payload that won't
+/// parse and the restaurant has `fallback_rss`, the RSS feed is tried instead; its output keeps
+/// `Provider::CompassRss` so the payload is cached and re-parsed as RSS; cache readers find it
+/// through `cache::cached_provider`.
+fn fetch_compass(settings: &Settings, client: &Client, restaurant: Restaurant) -> FetchOutput {
+    let output = fetch_compass_json(settings, client, &restaurant);
+    let Some(rss_cost_number) = restaurant.fallback_rss.clone() else {
+        return output;
     };
+    if !wants_rss_fallback(&output) {
+        return output;
+    }
 
-    let response = client.get(&url).send();
-    let mut raw_json = String::new();
-    let api: ApiResponse = match response {
-        Ok(resp) => match resp.text() {
-            Ok(text) => {
-                raw_json = text.clone();
-                match serde_json::from_str(&text) {
-                    Ok(parsed) => parsed,
-                    Err(err) => {
-                        return FetchOutput {
-                            ok: false,
-                            error_message: err.to_string(),
-                            today_menu: None,
-                            restaurant_name: String::new(),
-                            restaurant_url: String::new(),
-                            provider: Provider::Compass,
-                            raw_json,
-                            payload_date: String::new(),
-                        };
-                    }
-                }
-            }
-            Err(err) => {
-                return FetchOutput {
-                    ok: false,
-                    error_message: err.to_string(),
-                    today_menu: None,
-                    restaurant_name: String::new(),
-                    restaurant_url: String::new(),
-                    provider: Provider::Compass,
-                    raw_json,
-                    payload_date: String::new(),
-                };
-            }
-        },
-        Err(err) => {
-            return FetchOutput {
-                ok: false,
-                error_message: err.to_string(),
-                today_menu: None,
-                restaurant_name: String::new(),
-                restaurant_url: String::new(),
-                provider: Provider::Compass,
-                raw_json,
-                payload_date: String::new(),
-            };
-        }
+    log_line(&format!(
+        "compass json failed code={} err={}; trying rss {}",
+        restaurant.code, output.error_message, rss_cost_number
+    ));
+    if output.error_kind == FetchErrorKind::Parse {
+        cache::clear_cache_meta(Provider::Compass, &restaurant.code, &settings.language);
+    }
+    let code = restaurant.code.clone();
+    let rss_restaurant = Restaurant {
+        provider: Provider::CompassRss,
+        cost_number: Some(rss_cost_number),
+        ..restaurant
     };
+    let rss_output = fetch_compass_rss(settings, client, rss_restaurant);
+    if rss_output.ok {
+        log_line(&format!("compass code={} served by rss fallback", code));
+    } else {
+        log_line(&format!(
+            "compass rss fallback failed code={} err={}",
+            code, rss_output.error_message
+        ));
+    }
+    rss_fallback_result(output, rss_output)
+}
+
+/// The server answered but the JSON feed is unusable: an error status or a payload that won't
+/// parse. Network failures would hit the RSS feed on the same host just the same.
+fn wants_rss_fallback(output: &FetchOutput) -> bool {
+    !output.ok
+        && matches!(
+            output.error_kind,
+            FetchErrorKind::Parse | FetchErrorKind::HttpStatus(_)
+        )
+}
 
-    parse_response(api, raw_json)
+/// The RSS output when it worked, else the JSON failure, which names the primary feed.
+fn rss_fallback_result(json_output: FetchOutput, rss_output: FetchOutput) -> FetchOutput {
+    if rss_output.ok {
+        rss_output
+    } else {
+        json_output
+    }
 }
 
-fn fetch_compass_rss(settings: &Settings, restaurant: Restaurant) -> FetchOutput {
-    let rss_cost_number = match restaurant.rss_cost_number {
+fn fetch_compass_json(
+    settings: &Settings,
+    client: &Client,
+    restaurant: &Restaurant,
+) -> FetchOutput {
+    let cost_number = restaurant
+        .cost_number
+        .as_deref()
+        .unwrap_or(&restaurant.code);
+    let url = format!(
+        "https://www.compass-group.fi/menuapi/feed/json?costNumber={}&language={}",
+        cost_number, settings.language
+    );
+
+    let provider = Provider::Compass;
+    let text = match fetch_text(client, &url, restaurant, provider, settings) {
+        Ok(text) => text,
+        Err(output) => return *output,
+    };
+    match serde_json::from_str::<ApiResponse>(&text) {
+        Ok(api) => parse_response(api, text),
+        Err(err) => failure_output(
+            restaurant,
+            provider,
+            err.to_string(),
+            error_kind(&err),
+            text,
+        ),
+    }
+}
+
+fn fetch_compass_rss(settings: &Settings, client: &Client, restaurant: Restaurant) -> FetchOutput {
+    let provider = Provider::CompassRss;
+    let rss_cost_number = match restaurant.cost_number.as_deref() {
         Some(value) if !value.trim().is_empty() => value.trim(),
-        _ => {
-            return FetchOutput {
-                ok: false,
-                error_message: "Missing RSS cost number".to_string(),
-                today_menu: None,
-                restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                provider: Provider::CompassRss,
-                raw_json: String::new(),
-                payload_date: String::new(),
-            };
-        }
+        _ => return missing_config(&restaurant, provider, "Missing RSS cost number"),
     };
 
     let url = format!(
@@ -133,67 +230,17 @@ fn fetch_compass_rss(settings: &Settings, restaurant: Restaurant) -> FetchOutput
         rss_cost_number, settings.language
     );
 
-    let client = match Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-    {
-        Ok(c) => c,
-        Err(err) => {
-            return FetchOutput {
-                ok: false,
-                error_message: err.to_string(),
-                today_menu: None,
-                restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                provider: Provider::CompassRss,
-                raw_json: String::new(),
-                payload_date: String::new(),
-            };
-        }
-    };
-
-    match client.get(&url).send() {
-        Ok(resp) => match resp.text() {
-            Ok(text) => parse_compass_rss_payload(&text, restaurant, &settings.language),
-            Err(err) => FetchOutput {
-                ok: false,
-                error_message: err.to_string(),
-                today_menu: None,
-                restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                provider: Provider::CompassRss,
-                raw_json: String::new(),
-                payload_date: String::new(),
-            },
-        },
-        Err(err) => FetchOutput {
-            ok: false,
-            error_message: err.to_string(),
-            today_menu: None,
-            restaurant_name: restaurant.name.to_string(),
-            restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-            provider: Provider::CompassRss,
-            raw_json: String::new(),
-            payload_date: String::new(),
-        },
+    match fetch_text(client, &url, &restaurant, provider, settings) {
+        Ok(text) => parse_compass_rss_payload(&text, &restaurant, &settings.language),
+        Err(output) => *output,
     }
 }
 
-fn fetch_huomen(settings: &Settings, restaurant: Restaurant) -> FetchOutput {
-    let huomen_api_base = match restaurant.huomen_api_base {
+fn fetch_huomen(settings: &Settings, client: &Client, restaurant: Restaurant) -> FetchOutput {
+    let provider = Provider::HuomenJson;
+    let huomen_api_base = match restaurant.huomen_api_base.as_deref() {
         Some(value) if !value.trim().is_empty() => value.trim(),
-        _ => {
-            return FetchOutput {
-                ok: false,
-                error_message: "Missing Huomen API base URL".to_string(),
-                today_menu: None,
-                restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                provider: Provider::HuomenJson,
-                raw_json: String::new(),
-                payload_date: String::new(),
-            };
-        }
+        _ => return missing_config(&restaurant, provider, "Missing Huomen API base URL"),
     };
 
     let separator = if huomen_api_base.contains('?') {
@@ -206,68 +253,151 @@ fn fetch_huomen(settings: &Settings, restaurant: Restaurant) -> FetchOutput {
         huomen_api_base, separator, settings.language
     );
 
-    let client = match Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-    {
-        Ok(c) => c,
-        Err(err) => {
-            return FetchOutput {
-                ok: false,
-                error_message: err.to_string(),
-                today_menu: None,
-                restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                provider: Provider::HuomenJson,
-                raw_json: String::new(),
-                payload_date: String::new(),
-            };
-        }
+    let text = match fetch_text(client, &url, &restaurant, provider, settings) {
+        Ok(text) => text,
+        Err(output) => return *output,
     };
+    parse_or_failure(
+        parse_huomen_payload(&text, &restaurant, &settings.language),
+        &restaurant,
+        provider,
+        text,
+    )
+}
 
-    match client.get(&url).send() {
-        Ok(resp) => match resp.text() {
-            Ok(text) => match parse_huomen_payload(&text, restaurant, &settings.language) {
-                Ok(output) => output,
-                Err(err) => FetchOutput {
-                    ok: false,
-                    error_message: err.to_string(),
-                    today_menu: None,
-                    restaurant_name: restaurant.name.to_string(),
-                    restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                    provider: Provider::HuomenJson,
-                    raw_json: text,
-                    payload_date: String::new(),
-                },
-            },
-            Err(err) => FetchOutput {
-                ok: false,
-                error_message: err.to_string(),
-                today_menu: None,
-                restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                provider: Provider::HuomenJson,
-                raw_json: String::new(),
-                payload_date: String::new(),
-            },
-        },
-        Err(err) => FetchOutput {
-            ok: false,
-            error_message: err.to_string(),
-            today_menu: None,
-            restaurant_name: restaurant.name.to_string(),
-            restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-            provider: Provider::HuomenJson,
-            raw_json: String::new(),
-            payload_date: String::new(),
-        },
+fn fetch_sodexo(settings: &Settings, client: &Client, restaurant: Restaurant) -> FetchOutput {
+    let provider = Provider::SodexoJson;
+    let meal_id = match restaurant.sodexo_meal_id.as_deref() {
+        Some(value) if !value.trim().is_empty() => value.trim(),
+        _ => return missing_config(&restaurant, provider, "Missing Sodexo meal id"),
+    };
+
+    let today_key = local_date_key();
+    let url = format!(
+        "https://www.sodexo.fi/ruokalistat/output/daily_json/{}/{}",
+        meal_id, today_key
+    );
+
+    let text = match fetch_text(client, &url, &restaurant, provider, settings) {
+        Ok(text) => text,
+        Err(output) => return *output,
+    };
+    parse_or_failure(
+        parse_sodexo_payload(&text, &restaurant, &settings.language, &today_key),
+        &restaurant,
+        provider,
+        text,
+    )
+}
+
+fn fetch_unica(settings: &Settings, client: &Client, restaurant: Restaurant) -> FetchOutput {
+    let provider = Provider::UnicaJson;
+    let restaurant_id = match restaurant.unica_restaurant_id.as_deref() {
+        Some(value) if !value.trim().is_empty() => value.trim(),
+        _ => return missing_config(&restaurant, provider, "Missing Unica restaurant id"),
+    };
+
+    let url = format!(
+        "https://messi.hyyravintolat.fi/publicapi/restaurant/{}",
+        restaurant_id
+    );
+
+    let text = match fetch_text(client, &url, &restaurant, provider, settings) {
+        Ok(text) => text,
+        Err(output) => return *output,
+    };
+    parse_or_failure(
+        parse_unica_payload(&text, &restaurant, &settings.language, &local_date_key()),
+        &restaurant,
+        provider,
+        text,
+    )
+}
+
+/// Conditional GET of `url` for `restaurant`: the checked body (or the cached payload on 304),
+/// or the failed output for `provider` when the request, status or Content-Type is wrong.
+fn fetch_text(
+    client: &Client,
+    url: &str,
+    restaurant: &Restaurant,
+    provider: Provider,
+    settings: &Settings,
+) -> Result<String, Box<FetchOutput>> {
+    let fail = |message: String, kind: FetchErrorKind| {
+        Box::new(failure_output(
+            restaurant,
+            provider,
+            message,
+            kind,
+            String::new(),
+        ))
+    };
+    let language = &settings.language;
+    let response = conditional_get(client, url, restaurant, language)
+        .map_err(|err| fail(err.to_string(), error_kind(&err)))?;
+    response_text(
+        response,
+        ContentKind::for_provider(provider),
+        restaurant,
+        language,
+    )
+    .map_err(|err| fail(err.to_string(), error_kind(err.as_ref())))
+}
+
+/// A provider parser's result, with a parse error turned into a failed output that keeps the
+/// payload.
+fn parse_or_failure(
+    parsed: anyhow::Result<FetchOutput>,
+    restaurant: &Restaurant,
+    provider: Provider,
+    text: String,
+) -> FetchOutput {
+    parsed.unwrap_or_else(|err| {
+        failure_output(
+            restaurant,
+            provider,
+            err.to_string(),
+            error_kind(err.as_ref()),
+            text,
+        )
+    })
+}
+
+fn missing_config(restaurant: &Restaurant, provider: Provider, message: &str) -> FetchOutput {
+    failure_output(
+        restaurant,
+        provider,
+        message.to_string(),
+        FetchErrorKind::Config,
+        String::new(),
+    )
+}
+
+fn failure_output(
+    restaurant: &Restaurant,
+    provider: Provider,
+    error_message: String,
+    error_kind: FetchErrorKind,
+    raw_json: String,
+) -> FetchOutput {
+    FetchOutput {
+        ok: false,
+        error_message,
+        error_kind,
+        today_menu: None,
+        week_menu: WeekMenu::default(),
+        restaurant_name: restaurant.name.to_string(),
+        restaurant_url: restaurant.url.as_deref().unwrap_or_default().to_string(),
+        provider,
+        raw_json,
+        payload_date: String::new(),
     }
 }
 
 pub fn parse_cached_payload(
     raw_payload: &str,
     provider: Provider,
-    restaurant: Restaurant,
+    restaurant: &Restaurant,
     language: &str,
 ) -> anyhow::Result<FetchOutput> {
     match provider {
@@ -278,20 +408,30 @@ pub fn parse_cached_payload(
         }
         Provider::CompassRss => Ok(parse_compass_rss_payload(raw_payload, restaurant, language)),
         Provider::Antell => {
-            let today_key = local_today_key();
-            let today_menu = antell::parse_antell_html(raw_payload, &today_key);
+            let (_, menu_date) = local_antell_menu_day();
+            let today_menu = antell::parse_antell_html(raw_payload, &menu_date)?;
             Ok(FetchOutput {
                 ok: true,
                 error_message: String::new(),
+                error_kind: FetchErrorKind::None,
+                week_menu: WeekMenu {
+                    days: vec![today_menu.clone()],
+                },
                 today_menu: Some(today_menu),
                 restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
+                restaurant_url: restaurant.url.as_deref().unwrap_or_default().to_string(),
                 provider,
                 raw_json: raw_payload.to_string(),
                 payload_date: String::new(),
             })
         }
         Provider::HuomenJson => parse_huomen_payload(raw_payload, restaurant, language),
+        Provider::SodexoJson => {
+            parse_sodexo_payload(raw_payload, restaurant, language, &local_date_key())
+        }
+        Provider::UnicaJson => {
+            parse_unica_payload(raw_payload, restaurant, language, &local_date_key())
+        }
     }
 }
 
@@ -301,7 +441,9 @@ fn parse_response(api: ApiResponse, raw_json: String) -> FetchOutput {
         return FetchOutput {
             ok: false,
             error_message: error_text,
+            error_kind: FetchErrorKind::Config,
             today_menu: None,
+            week_menu: WeekMenu::default(),
             restaurant_name: normalize_optional(api.restaurant_name.as_deref()),
             restaurant_url: normalize_optional(api.restaurant_url.as_deref()),
             provider: Provider::Compass,
@@ -310,9 +452,10 @@ fn parse_response(api: ApiResponse, raw_json: String) -> FetchOutput {
         };
     }
 
-    let today_key = local_today_key();
+    let today_key = local_date_key();
     let menus_for_days = api.menus_for_days.unwrap_or_default();
     let mut today_menu: Option<TodayMenu> = None;
+    let mut week_days = Vec::new();
     let mut fallback_payload_date = String::new();
     let mut payload_date = String::new();
 
@@ -322,24 +465,26 @@ fn parse_response(api: ApiResponse, raw_json: String) -> FetchOutput {
             .next()
             .unwrap_or("")
             .to_string();
-        if !date_key.is_empty()
-            && (fallback_payload_date.is_empty() || date_key > fallback_payload_date)
-        {
+        if date_key.is_empty() {
+            continue;
+        }
+        if fallback_payload_date.is_empty() || date_key > fallback_payload_date {
             fallback_payload_date = date_key.clone();
         }
-        if date_key == today_key {
-            let lunch_time = normalize_optional(day.lunch_time.as_deref());
-            let set_menus = day.set_menus.unwrap_or_default();
-            let menus = normalize_menus(set_menus);
-            today_menu = Some(TodayMenu {
-                date_iso: today_key.clone(),
-                lunch_time,
-                menus,
-            });
+        let set_menus = day.set_menus.unwrap_or_default();
+        let menu = TodayMenu {
+            date_iso: date_key.clone(),
+            lunch_time: normalize_optional(day.lunch_time.as_deref()),
+            menus: normalize_menus(set_menus),
+            closed: false,
+        };
+        if date_key == today_key && today_menu.is_none() {
+            today_menu = Some(menu.clone());
             payload_date = today_key.clone();
-            break;
         }
+        week_days.push(menu);
     }
+    week_days.sort_by(|a, b| a.date_iso.cmp(&b.date_iso));
 
     if payload_date.is_empty() {
         payload_date = fallback_payload_date;
@@ -348,7 +493,9 @@ fn parse_response(api: ApiResponse, raw_json: String) -> FetchOutput {
     FetchOutput {
         ok: true,
         error_message: String::new(),
+        error_kind: FetchErrorKind::None,
         today_menu,
+        week_menu: WeekMenu { days: week_days },
         restaurant_name: normalize_optional(api.restaurant_name.as_deref()),
         restaurant_url: normalize_optional(api.restaurant_url.as_deref()),
         provider: Provider::Compass,
@@ -366,104 +513,70 @@ fn normalize_menus(set_menus: Vec<ApiSetMenu>) -> Vec<MenuGroup> {
     }
     menus_with_idx
         .into_iter()
-        .map(|(_, menu)| MenuGroup {
-            name: normalize_optional(menu.name.as_deref()),
-            price: normalize_optional(menu.price.as_deref()),
-            components: menu
-                .components
-                .unwrap_or_default()
-                .into_iter()
-                .map(|c| normalize_text(&c))
-                .filter(|c| !c.is_empty())
-                .collect(),
+        .map(|(_, menu)| {
+            let price = normalize_optional(menu.price.as_deref());
+            MenuGroup {
+                name: normalize_optional(menu.name.as_deref()),
+                prices: parse_compass_price_entries(&price),
+                price,
+                components: menu
+                    .components
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|c| normalize_text(&c))
+                    .filter(|c| !c.is_empty())
+                    .collect(),
+                energy: Vec::new(),
+            }
         })
         .collect()
 }
 
-fn fetch_antell(restaurant: Restaurant) -> FetchOutput {
-    let today_key = local_today_key();
-    let slug = match restaurant.antell_slug {
+fn fetch_antell(settings: &Settings, client: &Client, restaurant: Restaurant) -> FetchOutput {
+    let provider = Provider::Antell;
+    let today_key = local_date_key();
+    let (day_token, menu_date) = local_antell_menu_day();
+    let slug = match restaurant.antell_slug.as_deref() {
         Some(s) => s,
-        None => {
-            return FetchOutput {
-                ok: false,
-                error_message: "Missing Antell slug".to_string(),
-                today_menu: None,
-                restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                provider: Provider::Antell,
-                raw_json: String::new(),
-                payload_date: String::new(),
-            };
-        }
+        None => return missing_config(&restaurant, provider, "Missing Antell slug"),
     };
     let url = format!(
         "https://antell.fi/lounas/kuopio/{}/?print_lunch_day={}&print_lunch_list_day=1",
-        slug,
-        weekday_token()
+        slug, day_token
     );
-    let client = match Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-    {
-        Ok(c) => c,
-        Err(err) => {
-            return FetchOutput {
-                ok: false,
-                error_message: err.to_string(),
-                today_menu: None,
-                restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                provider: Provider::Antell,
-                raw_json: String::new(),
-                payload_date: String::new(),
-            };
-        }
-    };
 
-    let response = client.get(&url).send();
-    match response {
-        Ok(resp) => match resp.text() {
-            Ok(text) => {
-                let today_menu = antell::parse_antell_html(&text, &today_key);
-                FetchOutput {
-                    ok: true,
-                    error_message: String::new(),
-                    today_menu: Some(today_menu),
-                    restaurant_name: restaurant.name.to_string(),
-                    restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                    provider: Provider::Antell,
-                    raw_json: text,
-                    payload_date: today_key,
-                }
-            }
-            Err(err) => FetchOutput {
-                ok: false,
-                error_message: err.to_string(),
-                today_menu: None,
-                restaurant_name: restaurant.name.to_string(),
-                restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-                provider: Provider::Antell,
-                raw_json: String::new(),
-                payload_date: String::new(),
+    let text = match fetch_text(client, &url, &restaurant, provider, settings) {
+        Ok(text) => text,
+        Err(output) => return *output,
+    };
+    match antell::parse_antell_html(&text, &menu_date) {
+        Ok(today_menu) => FetchOutput {
+            ok: true,
+            error_message: String::new(),
+            error_kind: FetchErrorKind::None,
+            week_menu: WeekMenu {
+                days: vec![today_menu.clone()],
             },
-        },
-        Err(err) => FetchOutput {
-            ok: false,
-            error_message: err.to_string(),
-            today_menu: None,
+            today_menu: Some(today_menu),
             restaurant_name: restaurant.name.to_string(),
-            restaurant_url: restaurant.url.unwrap_or_default().to_string(),
-            provider: Provider::Antell,
-            raw_json: String::new(),
-            payload_date: String::new(),
+            restaurant_url: restaurant.url.as_deref().unwrap_or_default().to_string(),
+            provider,
+            raw_json: text,
+            payload_date: today_key,
         },
+        Err(err) => failure_output(
+            &restaurant,
+            provider,
+            err.to_string(),
+            error_kind(err.as_ref()),
+            String::new(),
+        ),
     }
 }
 
 fn parse_compass_rss_payload(
     xml_text: &str,
-    restaurant: Restaurant,
+    restaurant: &Restaurant,
     language: &str,
 ) -> FetchOutput {
     let payload_text = String::from(xml_text);
@@ -486,7 +599,7 @@ fn parse_compass_rss_payload(
         menu_date_iso = parse_rss_menu_date_iso(&item_guid);
     }
 
-    let today = local_today_key();
+    let today = local_date_key();
     let is_date_today = !menu_date_iso.is_empty() && menu_date_iso == today;
     let components = parse_rss_components(&description_raw);
 
@@ -498,23 +611,30 @@ fn parse_compass_rss_payload(
     let restaurant_url = if !item_link.is_empty() {
         item_link
     } else {
-        restaurant.url.unwrap_or_default().to_string()
+        restaurant.url.as_deref().unwrap_or_default().to_string()
     };
 
+    let feed_menu = TodayMenu {
+        date_iso: menu_date_iso.clone(),
+        lunch_time: String::new(),
+        menus: vec![MenuGroup {
+            name: text_for(language, "lunch"),
+            price: String::new(),
+            components,
+            energy: Vec::new(),
+            prices: Vec::new(),
+        }],
+        closed: false,
+    };
+    let week_menu = WeekMenu {
+        days: if menu_date_iso.is_empty() {
+            Vec::new()
+        } else {
+            vec![feed_menu.clone()]
+        },
+    };
     let today_menu = if is_date_today {
-        Some(TodayMenu {
-            date_iso: today,
-            lunch_time: String::new(),
-            menus: vec![MenuGroup {
-                name: if language == "fi" {
-                    "Lounas".to_string()
-                } else {
-                    "Lunch".to_string()
-                },
-                price: String::new(),
-                components,
-            }],
-        })
+        Some(feed_menu.clone())
     } else {
         None
     };
@@ -522,7 +642,9 @@ fn parse_compass_rss_payload(
     FetchOutput {
         ok: true,
         error_message: String::new(),
+        error_kind: FetchErrorKind::None,
         today_menu,
+        week_menu,
         restaurant_name,
         restaurant_url,
         provider: Provider::CompassRss,
@@ -533,7 +655,7 @@ fn parse_compass_rss_payload(
 
 fn parse_huomen_payload(
     json_text: &str,
-    restaurant: Restaurant,
+    restaurant: &Restaurant,
     language: &str,
 ) -> anyhow::Result<FetchOutput> {
     let parsed: Value = serde_json::from_str(json_text).context("parse Huomen JSON")?;
@@ -556,8 +678,9 @@ fn parse_huomen_payload(
         .and_then(Value::as_array)
         .ok_or_else(|| anyhow!("Missing week.days in Huomen payload"))?;
 
-    let expected_iso = local_today_key();
-    let mut day_match: Option<&Value> = None;
+    let expected_iso = local_date_key();
+    let mut today_menu: Option<TodayMenu> = None;
+    let mut week_days = Vec::new();
     let mut fallback_payload_date = String::new();
 
     for day in days {
@@ -566,34 +689,21 @@ fn parse_huomen_payload(
                 .and_then(Value::as_str)
                 .unwrap_or_default(),
         );
-        if !date.is_empty() && (fallback_payload_date.is_empty() || date > fallback_payload_date) {
+        if date.is_empty() {
+            continue;
+        }
+        if fallback_payload_date.is_empty() || date > fallback_payload_date {
             fallback_payload_date = date.clone();
         }
-        if date == expected_iso {
-            day_match = Some(day);
-            break;
+        let menu = huomen_day_menu(day, &date, language);
+        if date == expected_iso && today_menu.is_none() {
+            today_menu = Some(menu.clone());
         }
+        week_days.push(menu);
     }
+    week_days.sort_by(|a, b| a.date_iso.cmp(&b.date_iso));
 
-    let mut lunch_lines = Vec::new();
-    if let Some(day) = day_match {
-        let is_closed = day
-            .get("isClosed")
-            .and_then(Value::as_bool)
-            .unwrap_or(false);
-        if !is_closed {
-            if let Some(lunches) = day.get("lunches").and_then(Value::as_array) {
-                for lunch in lunches {
-                    let line = huomen_lunch_line(lunch, language);
-                    if !line.is_empty() {
-                        lunch_lines.push(line);
-                    }
-                }
-            }
-        }
-    }
-
-    let provider_date_valid = day_match.is_some();
+    let provider_date_valid = today_menu.is_some();
     let menu_date_iso = if provider_date_valid {
         expected_iso.clone()
     } else {
@@ -609,30 +719,14 @@ fn parse_huomen_payload(
         }
     };
 
-    let restaurant_url = restaurant.url.unwrap_or_default().to_string();
-
-    let today_menu = if provider_date_valid {
-        Some(TodayMenu {
-            date_iso: expected_iso,
-            lunch_time: String::new(),
-            menus: vec![MenuGroup {
-                name: if language == "fi" {
-                    "Lounas".to_string()
-                } else {
-                    "Lunch".to_string()
-                },
-                price: String::new(),
-                components: lunch_lines,
-            }],
-        })
-    } else {
-        None
-    };
+    let restaurant_url = restaurant.url.as_deref().unwrap_or_default().to_string();
 
     Ok(FetchOutput {
         ok: true,
         error_message: String::new(),
+        error_kind: FetchErrorKind::None,
         today_menu,
+        week_menu: WeekMenu { days: week_days },
         restaurant_name,
         restaurant_url,
         provider: Provider::HuomenJson,
@@ -645,6 +739,315 @@ fn parse_huomen_payload(
     })
 }
 
+fn parse_sodexo_payload(
+    json_text: &str,
+    restaurant: &Restaurant,
+    language: &str,
+    date_key: &str,
+) -> anyhow::Result<FetchOutput> {
+    let parsed: Value = serde_json::from_str(json_text).context("parse Sodexo JSON")?;
+
+    let mut courses: Vec<(usize, &Value)> = match parsed.get("courses") {
+        Some(Value::Object(map)) => map
+            .iter()
+            .map(|(key, course)| (key.parse::<usize>().unwrap_or(usize::MAX), course))
+            .collect(),
+        Some(Value::Array(list)) => list.iter().enumerate().collect(),
+        _ => Vec::new(),
+    };
+    courses.sort_by_key(|(order, _)| *order);
+
+    let menus: Vec<MenuGroup> = courses
+        .into_iter()
+        .filter_map(|(_, course)| sodexo_course_group(course, language))
+        .collect();
+
+    let restaurant_name = {
+        let value = normalize_text(
+            parsed
+                .pointer("/meta/ref_title")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        );
+        if value.is_empty() {
+            restaurant.name.to_string()
+        } else {
+            value
+        }
+    };
+    let restaurant_url = {
+        let value = normalize_text(
+            parsed
+                .pointer("/meta/ref_url")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        );
+        if value.is_empty() {
+            restaurant.url.as_deref().unwrap_or_default().to_string()
+        } else {
+            value
+        }
+    };
+
+    let today_menu = TodayMenu {
+        date_iso: date_key.to_string(),
+        lunch_time: String::new(),
+        menus,
+        closed: false,
+    };
+
+    Ok(FetchOutput {
+        ok: true,
+        error_message: String::new(),
+        error_kind: FetchErrorKind::None,
+        week_menu: WeekMenu {
+            days: vec![today_menu.clone()],
+        },
+        today_menu: Some(today_menu),
+        restaurant_name,
+        restaurant_url,
+        provider: Provider::SodexoJson,
+        raw_json: json_text.to_string(),
+        payload_date: date_key.to_string(),
+    })
+}
+
+fn sodexo_course_group(course: &Value, language: &str) -> Option<MenuGroup> {
+    let field =
+        |key: &str| normalize_text(course.get(key).and_then(Value::as_str).unwrap_or_default());
+    let (primary, secondary) = if language == "fi" {
+        ("title_fi", "title_en")
+    } else {
+        ("title_en", "title_fi")
+    };
+    let mut title = field(primary);
+    if title.is_empty() {
+        title = field(secondary);
+    }
+    if title.is_empty() {
+        return None;
+    }
+
+    let properties = field("properties");
+    let component = if properties.is_empty() {
+        title
+    } else {
+        format!("{} ({})", title, properties)
+    };
+    let category = field("category");
+    Some(MenuGroup {
+        name: if category.is_empty() {
+            text_for(language, "lunch")
+        } else {
+            category
+        },
+        price: field("price"),
+        components: vec![component],
+        energy: vec![energy_text(course, language)],
+        prices: Vec::new(),
+    })
+}
+
+/// Unica (`publicapi/restaurant/<id>`) lists days as `{"date": "Ma 16.10.", "data": [...]}`,
+/// one entry per dish with its diet labels in `meta["0"]`.
+fn parse_unica_payload(
+    json_text: &str,
+    restaurant: &Restaurant,
+    language: &str,
+    today_key: &str,
+) -> anyhow::Result<FetchOutput> {
+    let parsed: Value = serde_json::from_str(json_text).context("parse Unica JSON")?;
+    if let Some(status) = parsed.get("status").and_then(Value::as_str) {
+        if !status.eq_ignore_ascii_case("ok") {
+            return Err(anyhow!("Unica API returned status {}", status));
+        }
+    }
+
+    let days = parsed
+        .get("data")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("Missing data in Unica payload"))?;
+
+    let mut today_menu: Option<TodayMenu> = None;
+    let mut week_days = Vec::new();
+    for day in days {
+        let label = normalize_text(day.get("date").and_then(Value::as_str).unwrap_or_default());
+        let Some(date_iso) = unica_date_iso(&label, today_key) else {
+            continue;
+        };
+        let menus: Vec<MenuGroup> = day
+            .get("data")
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| unica_item_group(item, language))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let menu = TodayMenu {
+            date_iso,
+            lunch_time: String::new(),
+            menus,
+            closed: false,
+        };
+        if menu.date_iso == today_key && today_menu.is_none() {
+            today_menu = Some(menu.clone());
+        }
+        week_days.push(menu);
+    }
+    week_days.sort_by(|a, b| a.date_iso.cmp(&b.date_iso));
+
+    let payload_date = if today_menu.is_some() {
+        today_key.to_string()
+    } else {
+        String::new()
+    };
+    let restaurant_name = {
+        let value = normalize_text(
+            parsed
+                .pointer("/information/restaurant")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        );
+        if value.is_empty() {
+            restaurant.name.to_string()
+        } else {
+            value
+        }
+    };
+
+    Ok(FetchOutput {
+        ok: true,
+        error_message: String::new(),
+        error_kind: FetchErrorKind::None,
+        today_menu,
+        week_menu: WeekMenu { days: week_days },
+        restaurant_name,
+        restaurant_url: restaurant.url.as_deref().unwrap_or_default().to_string(),
+        provider: Provider::UnicaJson,
+        raw_json: json_text.to_string(),
+        payload_date,
+    })
+}
+
+/// "Ma 16.10." has no year; take the one that puts the date nearest to `today_key`.
+fn unica_date_iso(label: &str, today_key: &str) -> Option<String> {
+    let digits = label.trim_end_matches('.').rsplit(' ').next()?;
+    let (day, month) = digits.split_once('.')?;
+    let day = day.trim().parse::<u8>().ok()?;
+    let month = Month::try_from(month.trim().parse::<u8>().ok()?).ok()?;
+    let mut today = today_key
+        .splitn(3, '-')
+        .map(|part| part.parse::<i32>().ok());
+    let mut year = today.next()??;
+    let month_gap = month as i32 - today.next()??;
+    if month_gap > 6 {
+        year -= 1;
+    } else if month_gap < -6 {
+        year += 1;
+    }
+    Date::from_calendar_date(year, month, day)
+        .ok()
+        .map(date_key)
+}
+
+fn unica_item_group(item: &Value, language: &str) -> Option<MenuGroup> {
+    let field =
+        |key: &str| normalize_text(item.get(key).and_then(Value::as_str).unwrap_or_default());
+    let mut title = match language {
+        "en" => field("name_en"),
+        "sv" => field("name_sv"),
+        _ => String::new(),
+    };
+    if title.is_empty() {
+        title = field("name");
+    }
+    if title.is_empty() {
+        return None;
+    }
+
+    let labels: Vec<String> = item
+        .pointer("/meta/0")
+        .and_then(Value::as_array)
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(Value::as_str)
+                .map(normalize_text)
+                .filter(|label| !label.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let component = if labels.is_empty() {
+        title
+    } else {
+        format!("{} ({})", title, labels.join(", "))
+    };
+
+    let price_name = normalize_text(
+        item.pointer("/price/name")
+            .and_then(Value::as_str)
+            .unwrap_or_default(),
+    );
+    let price = ["student", "staff"]
+        .iter()
+        .filter_map(|group| item.pointer(&format!("/price/value/{}", group)))
+        .filter_map(Value::as_str)
+        .map(normalize_text)
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join(" / ");
+    Some(MenuGroup {
+        name: if price_name.is_empty() {
+            text_for(language, "lunch")
+        } else {
+            price_name
+        },
+        price,
+        components: vec![component],
+        energy: vec![energy_text(item, language)],
+        prices: Vec::new(),
+    })
+}
+
+fn huomen_day_menu(day: &Value, date: &str, language: &str) -> TodayMenu {
+    let closed = day
+        .get("isClosed")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let mut lunch_lines = Vec::new();
+    let mut energy = Vec::new();
+    if !closed {
+        if let Some(lunches) = day.get("lunches").and_then(Value::as_array) {
+            for lunch in lunches {
+                let line = huomen_lunch_line(lunch, language);
+                if !line.is_empty() {
+                    lunch_lines.push(line);
+                    energy.push(energy_text(lunch, language));
+                }
+            }
+        }
+    }
+    let menus = if closed {
+        Vec::new()
+    } else {
+        vec![MenuGroup {
+            name: text_for(language, "lunch"),
+            price: String::new(),
+            components: lunch_lines,
+            energy,
+            prices: Vec::new(),
+        }]
+    };
+    TodayMenu {
+        date_iso: date.to_string(),
+        lunch_time: String::new(),
+        menus,
+        closed,
+    }
+}
+
 fn parse_rss_tag_raw(xml_text: &str, tag_name: &str) -> String {
     let pattern = format!(
         r"(?is)<{}(?:\s+[^>]*)?>([\s\S]*?)</{}>",
@@ -942,7 +1345,29 @@ fn normalize_huomen_allergen_token(token: &str) -> String {
     clean
 }
 
+/// Reads an energy value from a Huomen lunch or Sodexo course, either at the top level or
+/// under `nutrition`. Bare numbers are taken as kcal.
+fn energy_text(item: &Value, language: &str) -> String {
+    let nutrition = item.get("nutrition");
+    for key in ["energy", "kcal", "energyKcal", "calories"] {
+        let value = item.get(key).or_else(|| nutrition.and_then(|n| n.get(key)));
+        let text = localized_field(value, language);
+        if text.is_empty() {
+            continue;
+        }
+        return match text.replace(',', ".").parse::<f64>() {
+            Ok(kcal) => format!("{} kcal", kcal.round() as i64),
+            Err(_) => text,
+        };
+    }
+    String::new()
+}
+
 fn huomen_lunch_line(lunch: &Value, language: &str) -> String {
+    // Some locations list lunches as plain strings instead of title/allergen objects.
+    if let Value::String(title) = lunch {
+        return normalize_text(title);
+    }
     let title = localized_field(lunch.get("title"), language);
     if title.is_empty() {
         return String::new();
@@ -982,9 +1407,157 @@ fn huomen_lunch_line(lunch: &Value, language: &str) -> String {
     normalize_text(&line)
 }
 
-fn weekday_token() -> &'static str {
-    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
-    match now.weekday() {
+#[derive(Debug, Clone, Copy)]
+enum ContentKind {
+    Json,
+    Xml,
+    Html,
+}
+
+impl ContentKind {
+    fn for_provider(provider: Provider) -> Self {
+        match provider {
+            Provider::CompassRss => ContentKind::Xml,
+            Provider::Antell => ContentKind::Html,
+            Provider::Compass
+            | Provider::HuomenJson
+            | Provider::SodexoJson
+            | Provider::UnicaJson => ContentKind::Json,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ContentKind::Json => "JSON",
+            ContentKind::Xml => "XML",
+            ContentKind::Html => "HTML",
+        }
+    }
+}
+
+/// Rejects responses whose Content-Type clearly is not what the provider parser expects.
+/// A missing header is let through so the parser can still try.
+fn check_content_type(expected: ContentKind, content_type: Option<&str>) -> anyhow::Result<()> {
+    let Some(content_type) = content_type else {
+        return Ok(());
+    };
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let matches = match expected {
+        ContentKind::Json => mime.contains("json"),
+        ContentKind::Xml => mime.contains("xml"),
+        ContentKind::Html => mime.contains("html"),
+    };
+    if matches || mime.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Expected {} but got {}", expected.label(), mime))
+    }
+}
+
+/// Sends the cached payload's validators so an unchanged feed can answer 304 Not Modified.
+fn conditional_get(
+    client: &Client,
+    url: &str,
+    restaurant: &Restaurant,
+    language: &str,
+) -> reqwest::Result<Response> {
+    let mut request = client.get(url);
+    if cache::read_cache(restaurant.provider, &restaurant.code, language).is_some() {
+        let meta = cache::read_cache_meta(restaurant.provider, &restaurant.code, language);
+        if let Some(etag) = meta.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = meta.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    request.send()
+}
+
+/// Body of a successful response, or the cached payload when the server answered 304.
+fn response_text(
+    resp: Response,
+    expected: ContentKind,
+    restaurant: &Restaurant,
+    language: &str,
+) -> anyhow::Result<String> {
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return cache::read_cache(restaurant.provider, &restaurant.code, language)
+            .ok_or_else(|| anyhow!("Got 304 Not Modified without a cached payload"));
+    }
+    let resp = resp.error_for_status()?;
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let content_type = header(CONTENT_TYPE);
+    check_content_type(expected, content_type.as_deref())?;
+    let meta = cache::CacheMeta {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+    let text = resp.text()?;
+    if let Err(err) =
+        cache::write_cache_meta(restaurant.provider, &restaurant.code, language, &meta)
+    {
+        log_line(&format!(
+            "cache meta write failed code={} err={}",
+            restaurant.code, err
+        ));
+    }
+    Ok(text)
+}
+
+/// Classifies a fetch failure by the first `reqwest::Error` in its source chain; anything
+/// else failed while reading the payload.
+fn error_kind(err: &(dyn std::error::Error + 'static)) -> FetchErrorKind {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(req) = err.downcast_ref::<reqwest::Error>() {
+            return if req.is_timeout() {
+                FetchErrorKind::Timeout
+            } else if let Some(status) = req.status() {
+                FetchErrorKind::HttpStatus(status.as_u16())
+            } else if req.is_builder() {
+                FetchErrorKind::Config
+            } else if req.is_decode() {
+                FetchErrorKind::Parse
+            } else {
+                FetchErrorKind::Network
+            };
+        }
+        current = err.source();
+    }
+    FetchErrorKind::Parse
+}
+
+/// Antell lists weekdays only, so on weekends ask for the preceding Friday's list instead.
+/// Returns the `print_lunch_day` token and the date the list belongs to.
+fn antell_menu_day(today: Date) -> (&'static str, Date) {
+    let days_back = match today.weekday() {
+        time::Weekday::Saturday => 1,
+        time::Weekday::Sunday => 2,
+        _ => 0,
+    };
+    let date = today - Duration::days(days_back);
+    (weekday_token(date.weekday()), date)
+}
+
+fn local_antell_menu_day() -> (&'static str, String) {
+    let now = local_now();
+    let (token, date) = antell_menu_day(now.date());
+    (token, date_key(date))
+}
+
+fn weekday_token(weekday: time::Weekday) -> &'static str {
+    match weekday {
         time::Weekday::Monday => "monday",
         time::Weekday::Tuesday => "tuesday",
         time::Weekday::Wednesday => "wednesday",
@@ -995,13 +1568,246 @@ fn weekday_token() -> &'static str {
     }
 }
 
-fn local_today_key() -> String {
-    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
-    let date = now.date();
-    format!(
-        "{:04}-{:02}-{:02}",
-        date.year(),
-        date.month() as u8,
-        date.day()
-    )
+#[cfg(test)]
+mod tests {
+    use super::{
+        antell_menu_day, check_content_type, error_kind, failure_output, huomen_day_menu,
+        parse_huomen_payload, parse_sodexo_payload, parse_unica_payload, rss_fallback_result,
+        wants_rss_fallback, ContentKind, FetchErrorKind,
+    };
+    use crate::format::{is_closed_today, split_component_suffix};
+    use crate::restaurant::{Provider, Restaurant};
+    use anyhow::Context;
+    use std::borrow::Cow;
+
+    const SODEXO_RESTAURANT: Restaurant = Restaurant {
+        code: Cow::Borrowed("sodexo-test"),
+        name: Cow::Borrowed("Sodexo Test"),
+        provider: Provider::SodexoJson,
+        antell_slug: None,
+        cost_number: None,
+        fallback_rss: None,
+        huomen_api_base: None,
+        sodexo_meal_id: Some(Cow::Borrowed("1")),
+        unica_restaurant_id: None,
+        url: None,
+    };
+
+    const UNICA_RESTAURANT: Restaurant = Restaurant {
+        code: Cow::Borrowed("unica-test"),
+        name: Cow::Borrowed("Unica Test"),
+        provider: Provider::UnicaJson,
+        antell_slug: None,
+        cost_number: None,
+        fallback_rss: None,
+        huomen_api_base: None,
+        sodexo_meal_id: None,
+        unica_restaurant_id: Some(Cow::Borrowed("1")),
+        url: None,
+    };
+
+    #[test]
+    fn fetch_errors_are_classified_by_source() {
+        let bad_url = reqwest::blocking::Client::new()
+            .get("not a url")
+            .build()
+            .unwrap_err();
+        assert_eq!(error_kind(&bad_url), FetchErrorKind::Config);
+
+        let parse = serde_json::from_str::<serde_json::Value>("{")
+            .map_err(anyhow::Error::from)
+            .context("parse JSON")
+            .unwrap_err();
+        assert_eq!(error_kind(parse.as_ref()), FetchErrorKind::Parse);
+
+        assert!(FetchErrorKind::Timeout.is_network());
+        assert!(!FetchErrorKind::HttpStatus(503).is_network());
+        assert!(!FetchErrorKind::Config.is_retryable());
+    }
+
+    #[test]
+    fn parses_sodexo_courses_in_order_with_allergen_suffix() {
+        let payload = r#"{
+            "meta": {"ref_title": "Sodexo Kuopio", "ref_url": "https://www.sodexo.fi/kuopio"},
+            "courses": {
+                "10": {"title_fi": "Jälkiruoka", "title_en": "Dessert", "category": "Dessert"},
+                "2": {"title_fi": "Kanacurry", "title_en": "Chicken curry",
+                      "category": "Lunch", "price": "2,95 / 7,50", "properties": "G, L"}
+            }
+        }"#;
+        let output = parse_sodexo_payload(payload, &SODEXO_RESTAURANT, "en", "2025-05-06").unwrap();
+        assert!(output.ok);
+        assert_eq!(output.restaurant_name, "Sodexo Kuopio");
+        assert_eq!(output.payload_date, "2025-05-06");
+        let menu = output.today_menu.unwrap();
+        assert_eq!(menu.menus.len(), 2);
+        assert_eq!(menu.menus[0].name, "Lunch");
+        assert_eq!(menu.menus[0].price, "2,95 / 7,50");
+        let (main, suffix) = split_component_suffix(&menu.menus[0].components[0], "en");
+        assert_eq!(main, "Chicken curry");
+        assert_eq!(suffix, "(G, L)");
+        assert_eq!(menu.menus[1].components[0], "Dessert");
+    }
+
+    #[test]
+    fn parses_unica_days_with_diet_labels() {
+        let payload = r#"{
+            "status": "OK",
+            "information": {"restaurant": "Chemicum"},
+            "data": [
+                {"date": "Ma 28.12.", "data": []},
+                {"date": "Ti 5.1.", "data": [
+                    {"name": "Kasvispyörykät", "name_en": "Vegetable balls",
+                     "price": {"name": "Edullisesti", "value": {"student": "2,95", "staff": "5,35"}},
+                     "meta": {"0": ["VEG", "G"], "1": []}},
+                    {"name": "Kanakeitto", "price": {"name": ""}, "meta": {"0": []}}
+                ]}
+            ]
+        }"#;
+        let output = parse_unica_payload(payload, &UNICA_RESTAURANT, "en", "2027-01-05").unwrap();
+        assert_eq!(output.restaurant_name, "Chemicum");
+        assert_eq!(output.payload_date, "2027-01-05");
+        let dates: Vec<_> = output
+            .week_menu
+            .days
+            .iter()
+            .map(|d| d.date_iso.as_str())
+            .collect();
+        assert_eq!(dates, ["2026-12-28", "2027-01-05"]);
+        let menu = output.today_menu.unwrap();
+        assert_eq!(menu.menus[0].name, "Edullisesti");
+        assert_eq!(menu.menus[0].price, "2,95 / 5,35");
+        let (main, suffix) = split_component_suffix(&menu.menus[0].components[0], "en");
+        assert_eq!(main, "Vegetable balls");
+        assert_eq!(suffix, "(Veg, G)");
+        assert_eq!(menu.menus[1].name, "Lunch");
+        assert_eq!(menu.menus[1].components[0], "Kanakeitto");
+
+        let later = parse_unica_payload(payload, &UNICA_RESTAURANT, "fi", "2027-01-07").unwrap();
+        assert!(later.today_menu.is_none());
+        assert!(later.payload_date.is_empty());
+    }
+
+    #[test]
+    fn sodexo_empty_course_list_yields_no_menus() {
+        let payload = r#"{"meta": {}, "courses": []}"#;
+        let output = parse_sodexo_payload(payload, &SODEXO_RESTAURANT, "fi", "2025-05-06").unwrap();
+        assert!(output.today_menu.unwrap().menus.is_empty());
+        assert_eq!(output.restaurant_name, "Sodexo Test");
+    }
+
+    #[test]
+    fn reads_energy_from_sodexo_and_huomen_items() {
+        let payload = r#"{"courses": {
+            "1": {"title_fi": "Keitto", "nutrition": {"energy": "519,6"}},
+            "2": {"title_fi": "Salaatti"}
+        }}"#;
+        let output = parse_sodexo_payload(payload, &SODEXO_RESTAURANT, "fi", "2025-05-06").unwrap();
+        let menus = output.today_menu.unwrap().menus;
+        assert_eq!(menus[0].energy, vec!["520 kcal".to_string()]);
+        assert_eq!(menus[1].energy, vec![String::new()]);
+
+        let day: serde_json::Value =
+            serde_json::from_str(r#"{"lunches": [{"title": {"fi": "Lohikeitto"}, "kcal": 410}]}"#)
+                .unwrap();
+        let menu = huomen_day_menu(&day, "2025-05-06", "fi");
+        assert_eq!(menu.menus[0].energy, vec!["410 kcal".to_string()]);
+    }
+
+    #[test]
+    fn closed_huomen_day_is_closed_today_only_on_its_date() {
+        let restaurant = Restaurant {
+            code: Cow::Borrowed("huomen-test"),
+            name: Cow::Borrowed("Huomen Test"),
+            provider: Provider::HuomenJson,
+            huomen_api_base: Some(Cow::Borrowed("https://example.invalid")),
+            sodexo_meal_id: None,
+            unica_restaurant_id: None,
+            ..SODEXO_RESTAURANT
+        };
+        let today = crate::util::local_date_key();
+        let payload = serde_json::json!({
+            "data": { "week": { "days": [
+                { "dateString": today, "isClosed": true },
+                { "dateString": "2000-01-03", "isClosed": true },
+                { "dateString": "2000-01-04", "lunches": ["Keitto"] },
+            ] } }
+        })
+        .to_string();
+        let output = parse_huomen_payload(&payload, &restaurant, "fi").unwrap();
+        assert!(is_closed_today(output.today_menu.as_ref().unwrap()));
+        let closed: Vec<bool> = output.week_menu.days.iter().map(is_closed_today).collect();
+        assert_eq!(closed, vec![false, false, true]);
+    }
+
+    #[test]
+    fn parses_huomen_lunches_given_as_plain_strings() {
+        let day: serde_json::Value = serde_json::from_str(
+            r#"{"lunches": ["Broileripasta (L, G)", "  ", "Kasvissosekeitto"]}"#,
+        )
+        .unwrap();
+        let menu = huomen_day_menu(&day, "2025-05-06", "fi");
+        assert_eq!(
+            menu.menus[0].components,
+            vec!["Broileripasta (L, G)", "Kasvissosekeitto"]
+        );
+        assert_eq!(menu.menus[0].energy, vec![String::new(), String::new()]);
+    }
+
+    #[test]
+    fn content_type_check_names_the_mismatch() {
+        let err =
+            check_content_type(ContentKind::Json, Some("text/html; charset=utf-8")).unwrap_err();
+        assert_eq!(err.to_string(), "Expected JSON but got text/html");
+        assert!(check_content_type(ContentKind::Json, Some("application/json")).is_ok());
+        assert!(check_content_type(ContentKind::Xml, Some("application/rss+xml")).is_ok());
+        assert!(check_content_type(ContentKind::Html, None).is_ok());
+    }
+
+    #[test]
+    fn antell_weekends_fall_back_to_friday() {
+        let date = |day: u8| time::Date::from_calendar_date(2025, time::Month::May, day).unwrap();
+        assert_eq!(antell_menu_day(date(7)), ("wednesday", date(7)));
+        assert_eq!(antell_menu_day(date(9)), ("friday", date(9)));
+        assert_eq!(antell_menu_day(date(10)), ("friday", date(9)));
+        assert_eq!(antell_menu_day(date(11)), ("friday", date(9)));
+        assert_eq!(antell_menu_day(date(12)), ("monday", date(12)));
+    }
+
+    #[test]
+    fn rss_fallback_only_covers_bad_json_answers() {
+        let failed = |kind| {
+            failure_output(
+                &SODEXO_RESTAURANT,
+                Provider::Compass,
+                "json failed".to_string(),
+                kind,
+                String::new(),
+            )
+        };
+        assert!(wants_rss_fallback(&failed(FetchErrorKind::Parse)));
+        assert!(wants_rss_fallback(&failed(FetchErrorKind::HttpStatus(500))));
+        assert!(!wants_rss_fallback(&failed(FetchErrorKind::Network)));
+        assert!(!wants_rss_fallback(&failed(FetchErrorKind::Timeout)));
+        assert!(!wants_rss_fallback(&failed(FetchErrorKind::Config)));
+
+        let rss_failed = failure_output(
+            &SODEXO_RESTAURANT,
+            Provider::CompassRss,
+            "rss failed".to_string(),
+            FetchErrorKind::Network,
+            String::new(),
+        );
+        let result = rss_fallback_result(failed(FetchErrorKind::HttpStatus(503)), rss_failed);
+        assert_eq!(result.provider, Provider::Compass);
+        assert_eq!(result.error_kind, FetchErrorKind::HttpStatus(503));
+        assert_eq!(result.error_message, "json failed");
+
+        let mut rss_ok = failed(FetchErrorKind::None);
+        rss_ok.ok = true;
+        rss_ok.provider = Provider::CompassRss;
+        let result = rss_fallback_result(failed(FetchErrorKind::Parse), rss_ok);
+        assert!(result.ok);
+        assert_eq!(result.provider, Provider::CompassRss);
+    }
 }
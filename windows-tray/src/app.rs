@@ -1,14 +1,25 @@
-use crate::api::{self, FetchOutput};
-use crate::cache;
+use crate::api::{self, FetchErrorKind, FetchOutput};
+use crate::cache::{self, MenuHistory};
+use crate::format::{
+    is_starred_dish, matches_favorite_keyword, menu_signature, normalize_text, text_for,
+};
+use crate::keys::{self, popup_keybindings, KeyCombo, PopupAction};
+use crate::locale::system_language;
 use crate::log::{log_line, set_enabled as set_log_enabled};
-use crate::model::TodayMenu;
+use crate::model::{TodayMenu, WeekMenu};
 use crate::restaurant::{
-    available_restaurants, provider_key, restaurant_for_code, Provider,
+    available_restaurants, provider_key, restaurant_for_code, Provider, Restaurant,
+};
+use crate::serve::MenuServer;
+use crate::settings::{
+    load_settings, menu_language, normalize_popup_anchor, normalize_theme, reload_settings_file,
+    save_settings, settings_dir, Settings,
 };
-use crate::settings::{load_settings, normalize_theme, save_settings, settings_dir, Settings};
+use crate::util::{date_key, date_key_from_epoch_ms, local_date_key, local_now};
 use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, Mutex};
-use time::OffsetDateTime;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use time::{Date, OffsetDateTime, Weekday};
 use windows::Win32::Foundation::HWND;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -26,14 +37,18 @@ pub struct AppState {
     pub status: FetchStatus,
     pub loading_started_epoch_ms: i64,
     pub error_message: String,
+    pub error_kind: FetchErrorKind,
     pub stale_network_error: bool,
     pub today_menu: Option<TodayMenu>,
+    pub week_menu: WeekMenu,
+    pub selected_day: Option<usize>,
     pub restaurant_name: String,
     pub restaurant_url: String,
     pub raw_payload: String,
     pub provider: Provider,
     pub payload_date: String,
     pub stale_date: bool,
+    pub same_as_previous_day: bool,
 }
 
 #[derive(Default, Clone, Copy)]
@@ -46,7 +61,9 @@ struct WindowHandles {
 struct MemoryMenuEntry {
     ok: bool,
     error_message: String,
+    error_kind: FetchErrorKind,
     today_menu: Option<TodayMenu>,
+    week_menu: WeekMenu,
     restaurant_name: String,
     restaurant_url: String,
     provider: Provider,
@@ -59,11 +76,68 @@ pub struct App {
     state: Arc<Mutex<AppState>>,
     hwnds: Mutex<WindowHandles>,
     hover_point: Mutex<Option<(i32, i32)>>,
+    hover_started: Mutex<Option<Instant>>,
+    hover_left: Mutex<Option<Instant>>,
     context_menu_open: Mutex<bool>,
     in_flight_codes: Mutex<HashSet<String>>,
+    last_manual_refresh: Mutex<HashMap<String, Instant>>,
     retry_step: Mutex<usize>,
     last_prefetch_ms: Mutex<i64>,
+    prefetch_cursor: Mutex<usize>,
+    prefetch_jobs: Mutex<Option<mpsc::Sender<FetchJob>>>,
     memory_menu_cache: Mutex<HashMap<String, MemoryMenuEntry>>,
+    menu_server: Mutex<Option<MenuServer>>,
+    /// `popup_keybindings` for `settings.popup_keys`, built when settings are loaded.
+    popup_keys: Mutex<HashMap<KeyCombo, PopupAction>>,
+}
+
+struct FetchJob {
+    hwnd: HWND,
+    settings: Settings,
+    requested_code: String,
+    requested_language: String,
+}
+
+const PREFETCH_WORKERS: usize = 2;
+const MANUAL_REFRESH_MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+fn spawn_prefetch_workers() -> mpsc::Sender<FetchJob> {
+    let (sender, receiver) = mpsc::channel::<FetchJob>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..PREFETCH_WORKERS {
+        let receiver = Arc::clone(&receiver);
+        std::thread::spawn(move || loop {
+            let job = match receiver.lock().unwrap().recv() {
+                Ok(job) => job,
+                Err(_) => break,
+            };
+            run_fetch_job(job);
+        });
+    }
+    sender
+}
+
+fn run_fetch_job(job: FetchJob) {
+    let result = api::fetch_week(&job.settings);
+    let message = FetchMessage {
+        requested_code: job.requested_code,
+        requested_language: job.requested_language,
+        result,
+    };
+    post_fetch_message(job.hwnd, message);
+}
+
+fn post_fetch_message(hwnd: HWND, message: FetchMessage) {
+    let boxed = Box::new(message);
+    let ptr = Box::into_raw(boxed) as isize;
+    unsafe {
+        let _ = windows::Win32::UI::WindowsAndMessaging::PostMessageW(
+            hwnd,
+            crate::winmsg::WM_APP_FETCH_COMPLETE,
+            windows::Win32::Foundation::WPARAM(0),
+            windows::Win32::Foundation::LPARAM(ptr),
+        );
+    }
 }
 
 pub struct FetchMessage {
@@ -82,35 +156,59 @@ pub enum FetchApplyOutcome {
 
 impl App {
     pub fn new(no_tray: bool) -> Self {
-        let settings = load_settings();
+        let mut settings = load_settings();
         set_log_enabled(settings.enable_logging);
+        let restaurant = restaurant_for_code(&settings.restaurant_code, &settings);
+        settings.restaurant_code = restaurant.code.to_string();
+        let popup_keys = popup_keybindings(&settings.popup_keys);
         let state = AppState {
-            provider: restaurant_for_code(&settings.restaurant_code, settings.enable_antell_restaurants).provider,
+            provider: restaurant.provider,
             settings,
             status: FetchStatus::Idle,
             loading_started_epoch_ms: 0,
             error_message: String::new(),
+            error_kind: FetchErrorKind::None,
             stale_network_error: false,
             today_menu: None,
+            week_menu: WeekMenu::default(),
+            selected_day: None,
             restaurant_name: String::new(),
             restaurant_url: String::new(),
             raw_payload: String::new(),
             payload_date: String::new(),
             stale_date: false,
+            same_as_previous_day: false,
         };
         Self {
             no_tray,
             state: Arc::new(Mutex::new(state)),
             hwnds: Mutex::new(WindowHandles::default()),
             hover_point: Mutex::new(None),
+            hover_started: Mutex::new(None),
+            hover_left: Mutex::new(None),
             context_menu_open: Mutex::new(false),
             in_flight_codes: Mutex::new(HashSet::new()),
+            last_manual_refresh: Mutex::new(HashMap::new()),
             retry_step: Mutex::new(0),
             last_prefetch_ms: Mutex::new(0),
+            prefetch_cursor: Mutex::new(0),
+            prefetch_jobs: Mutex::new(None),
             memory_menu_cache: Mutex::new(HashMap::new()),
+            menu_server: Mutex::new(None),
+            popup_keys: Mutex::new(popup_keys),
         }
     }
 
+    /// The popup action bound to `key` with `modifiers` held.
+    pub fn popup_action(&self, key: u32, modifiers: u32) -> Option<PopupAction> {
+        keys::popup_action(&self.popup_keys.lock().unwrap(), key, modifiers)
+    }
+
+    /// Whether releasing `key` should end a next-day peek.
+    pub fn is_peek_key(&self, key: u32) -> bool {
+        keys::is_peek_key(&self.popup_keys.lock().unwrap(), key)
+    }
+
     pub fn set_hwnds(&self, tray: HWND, popup: HWND) {
         let mut hwnds = self.hwnds.lock().unwrap();
         hwnds.tray = tray;
@@ -129,51 +227,71 @@ impl App {
         self.state.lock().unwrap().clone()
     }
 
+    /// Starts the `serve_port` endpoint when one is set. It reads through `snapshot`, so
+    /// `stop_menu_server` has to run before the app is dropped.
+    pub fn start_menu_server(&'static self) {
+        let Some(port) = self.snapshot().settings.serve_port else {
+            return;
+        };
+        match MenuServer::start(port, self) {
+            Ok(server) => {
+                log_line(&format!("serving menu on 127.0.0.1:{}", port));
+                *self.menu_server.lock().unwrap() = Some(server);
+            }
+            Err(err) => log_line(&format!("menu server on port {} failed: {}", port, err)),
+        }
+    }
+
+    pub fn stop_menu_server(&self) {
+        let server = self.menu_server.lock().unwrap().take();
+        if let Some(server) = server {
+            server.shutdown();
+        }
+    }
+
     pub fn load_cache_for_current(&self) -> bool {
         let (restaurant, language) = {
             let state = self.state.lock().unwrap();
             (
-                restaurant_for_code(
-                    &state.settings.restaurant_code,
-                    state.settings.enable_antell_restaurants,
-                ),
-                state.settings.language.clone(),
+                restaurant_for_code(&state.settings.restaurant_code, &state.settings),
+                menu_language(&state.settings, &state.settings.restaurant_code).to_string(),
             )
         };
-        let cached_date = if restaurant.provider == Provider::Antell {
-            cache::cache_mtime_ms(restaurant.provider, restaurant.code, &language)
+        let provider = cache::cached_provider(&restaurant, &language);
+        let cached_date = if is_dated_by_cache_mtime(provider) {
+            cache::cache_mtime_ms(provider, &restaurant.code, &language)
                 .and_then(date_key_from_epoch_ms)
         } else {
             None
         };
 
         if self.load_memory_for(
-            restaurant.code,
+            &restaurant.code,
             &language,
-            restaurant.provider,
+            provider,
             cached_date.as_deref(),
         ) {
             log_line(&format!(
                 "memory cache hit provider={} code={} language={}",
-                provider_key(restaurant.provider),
+                provider_key(provider),
                 restaurant.code,
                 language
             ));
             return true;
         }
 
-        if let Some(raw) = cache::read_cache(restaurant.provider, restaurant.code, &language) {
-            match api::parse_cached_payload(&raw, restaurant.provider, restaurant, &language) {
+        if let Some(raw) = cache::read_cache(provider, &restaurant.code, &language) {
+            match api::parse_cached_payload(&raw, provider, &restaurant, &language) {
                 Ok(result) => {
                     let mut result = result;
                     if let Some(date_key) = cached_date {
                         result.payload_date = date_key;
                     }
                     self.apply_cached_result(&result);
-                    self.store_memory_from_fetch_output(restaurant.code, &language, &result);
+                    self.store_memory_from_fetch_output(&restaurant.code, &language, &result);
                     log_line(&format!(
                         "cache hit provider={} code={} language={}",
-                        provider_key(restaurant.provider),
+                        provider_key(provider),
                         restaurant.code,
                         language
                     ));
@@ -184,10 +302,11 @@ impl App {
                     state.status = FetchStatus::Error;
                     state.loading_started_epoch_ms = 0;
                     state.error_message = err.to_string();
+                    state.error_kind = FetchErrorKind::Parse;
                     state.stale_network_error = false;
                     log_line(&format!(
                         "cache parse error provider={} code={} language={} err={}",
-                        provider_key(restaurant.provider),
+                        provider_key(provider),
                         restaurant.code,
                         language,
                         err
@@ -198,7 +317,7 @@ impl App {
         }
         log_line(&format!(
             "cache miss provider={} code={} language={}",
-            provider_key(restaurant.provider),
+            provider_key(provider),
             restaurant.code,
             language
         ));
@@ -211,20 +330,27 @@ impl App {
         state.restaurant_name = result.restaurant_name.clone();
         state.restaurant_url = result.restaurant_url.clone();
         state.today_menu = result.today_menu.clone();
+        state.week_menu = result.week_menu.clone();
+        clamp_selected_day(&mut state);
         state.provider = result.provider;
         state.payload_date = result.payload_date.clone();
         update_stale_date(&mut state);
+        let repeat_check = repeat_menu_check(&mut state);
         if result.ok {
             state.status = FetchStatus::Ok;
             state.loading_started_epoch_ms = 0;
             state.error_message.clear();
+            state.error_kind = FetchErrorKind::None;
             state.stale_network_error = false;
         } else {
             state.status = FetchStatus::Error;
             state.loading_started_epoch_ms = 0;
             state.error_message = result.error_message.clone();
+            state.error_kind = result.error_kind;
             state.stale_network_error = false;
         }
+        drop(state);
+        self.update_repeat_menu_flag(repeat_check);
     }
 
     fn store_memory_from_fetch_output(&self, code: &str, language: &str, result: &FetchOutput) {
@@ -232,7 +358,9 @@ impl App {
         let entry = MemoryMenuEntry {
             ok: result.ok,
             error_message: result.error_message.clone(),
+            error_kind: result.error_kind,
             today_menu: result.today_menu.clone(),
+            week_menu: result.week_menu.clone(),
             restaurant_name: result.restaurant_name.clone(),
             restaurant_url: result.restaurant_url.clone(),
             provider: result.provider,
@@ -259,7 +387,7 @@ impl App {
             return false;
         };
 
-        if provider == Provider::Antell {
+        if is_dated_by_cache_mtime(provider) {
             if let Some(date_key) = antell_payload_date {
                 entry.payload_date = date_key.to_string();
             }
@@ -270,21 +398,59 @@ impl App {
         state.restaurant_name = entry.restaurant_name;
         state.restaurant_url = entry.restaurant_url;
         state.today_menu = entry.today_menu;
+        state.week_menu = entry.week_menu;
+        clamp_selected_day(&mut state);
         state.provider = entry.provider;
         state.payload_date = entry.payload_date;
         update_stale_date(&mut state);
+        let repeat_check = repeat_menu_check(&mut state);
         state.loading_started_epoch_ms = 0;
         state.stale_network_error = false;
         if entry.ok {
             state.status = FetchStatus::Ok;
             state.error_message.clear();
+            state.error_kind = FetchErrorKind::None;
         } else {
             state.status = FetchStatus::Error;
             state.error_message = entry.error_message;
+            state.error_kind = entry.error_kind;
         }
+        drop(state);
+        self.update_repeat_menu_flag(repeat_check);
         true
     }
 
+    /// Sets `same_as_previous_day` from the menu history file, which is read and written here
+    /// rather than under the `state` lock.
+    fn update_repeat_menu_flag(&self, check: Option<RepeatMenuCheck>) {
+        let Some(check) = check else {
+            return;
+        };
+        let mut history = cache::read_menu_history(check.provider, &check.code, &check.language);
+        let before = history.clone();
+        let same = record_menu_signatures(&mut history, &check);
+        if history != before {
+            if let Err(err) =
+                cache::write_menu_history(check.provider, &check.code, &check.language, &history)
+            {
+                log_line(&format!(
+                    "menu history write failed code={} err={}",
+                    check.code, err
+                ));
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let still_shown = state.settings.restaurant_code == check.code
+            && state
+                .today_menu
+                .as_ref()
+                .is_some_and(|today| today.date_iso == check.today_date);
+        if still_shown && state.settings.flag_repeat_menus {
+            state.same_as_previous_day = same;
+        }
+    }
+
     pub fn start_refresh(&self) {
         let code = {
             let state = self.state.lock().unwrap();
@@ -293,6 +459,25 @@ impl App {
         let _ = self.start_refresh_for_code(&code, true);
     }
 
+    /// Manual refresh that ignores repeats for the same code within `MANUAL_REFRESH_MIN_INTERVAL`.
+    pub fn start_manual_refresh(&self) -> bool {
+        let code = {
+            let state = self.state.lock().unwrap();
+            state.settings.restaurant_code.clone()
+        };
+        {
+            let mut last = self.last_manual_refresh.lock().unwrap();
+            if let Some(at) = last.get(&code) {
+                if at.elapsed() < MANUAL_REFRESH_MIN_INTERVAL {
+                    return false;
+                }
+            }
+            last.insert(code.clone(), Instant::now());
+        }
+        let _ = self.start_refresh_for_code(&code, true);
+        true
+    }
+
     pub fn start_refresh_retry(&self) {
         let code = {
             let state = self.state.lock().unwrap();
@@ -320,10 +505,11 @@ impl App {
             }
             if is_current {
                 state.error_message.clear();
+                state.error_kind = FetchErrorKind::None;
             }
             let mut settings = state.settings.clone();
             settings.restaurant_code = code.to_string();
-            let requested_language = settings.language.clone();
+            let requested_language = menu_language(&settings, code).to_string();
             (settings, requested_language, is_current)
         };
 
@@ -333,28 +519,43 @@ impl App {
             log_line(&format!("prefetch start code={}", code));
         }
 
-        let requested_code = code.to_string();
-        std::thread::spawn(move || {
-            let result = api::fetch_today(&settings);
-            let message = FetchMessage {
-                requested_code,
-                requested_language,
-                result,
-            };
-            let boxed = Box::new(message);
-            let ptr = Box::into_raw(boxed) as isize;
-            unsafe {
-                let _ = windows::Win32::UI::WindowsAndMessaging::PostMessageW(
-                    hwnd,
-                    crate::winmsg::WM_APP_FETCH_COMPLETE,
-                    windows::Win32::Foundation::WPARAM(0),
-                    windows::Win32::Foundation::LPARAM(ptr),
-                );
-            }
-        });
+        let job = FetchJob {
+            hwnd,
+            settings,
+            requested_code: code.to_string(),
+            requested_language,
+        };
+        if !api::network_available() {
+            log_line(&format!("offline; skipping fetch code={}", code));
+            let result = api::offline_output(&job.settings);
+            post_fetch_message(
+                job.hwnd,
+                FetchMessage {
+                    requested_code: job.requested_code,
+                    requested_language: job.requested_language,
+                    result,
+                },
+            );
+            return true;
+        }
+        // The current restaurant gets its own thread so it never waits behind prefetches.
+        if is_current_code {
+            std::thread::spawn(move || run_fetch_job(job));
+        } else {
+            self.queue_prefetch(job);
+        }
         true
     }
 
+    fn queue_prefetch(&self, job: FetchJob) {
+        let mut sender = self.prefetch_jobs.lock().unwrap();
+        let sender = sender.get_or_insert_with(spawn_prefetch_workers);
+        if let Err(mpsc::SendError(job)) = sender.send(job) {
+            log_line("prefetch workers gone; fetching on a new thread");
+            std::thread::spawn(move || run_fetch_job(job));
+        }
+    }
+
     pub fn apply_fetch_message(&self, message: FetchMessage) -> FetchApplyOutcome {
         let FetchMessage {
             requested_code,
@@ -366,11 +567,27 @@ impl App {
             let mut in_flight = self.in_flight_codes.lock().unwrap();
             in_flight.remove(&requested_code);
         }
+        if result.error_kind == FetchErrorKind::Parse {
+            // Validators from a response that failed to parse must not turn the next fetch into
+            // a 304 for the older cached payload.
+            cache::clear_cache_meta(result.provider, &requested_code, &requested_language);
+        }
 
-        let current_code = {
+        let (current_code, keep_history_days) = {
             let state = self.state.lock().unwrap();
-            state.settings.restaurant_code.clone()
+            (
+                state.settings.restaurant_code.clone(),
+                state.settings.keep_history_days,
+            )
         };
+        if result.ok {
+            write_history_copy(
+                &requested_code,
+                &requested_language,
+                &result,
+                keep_history_days,
+            );
+        }
 
         if requested_code != current_code {
             if result.ok {
@@ -385,6 +602,16 @@ impl App {
                         requested_code, err
                     ));
                 }
+                {
+                    let mut state = self.state.lock().unwrap();
+                    if remember_restaurant_url(
+                        &mut state.settings,
+                        &requested_code,
+                        &result.restaurant_url,
+                    ) {
+                        let _ = save_settings(&state.settings);
+                    }
+                }
                 self.store_memory_from_fetch_output(&requested_code, &requested_language, &result);
                 FetchApplyOutcome::BackgroundSuccess
             } else {
@@ -400,14 +627,23 @@ impl App {
                 state.status = FetchStatus::Ok;
                 state.loading_started_epoch_ms = 0;
                 state.error_message.clear();
+                state.error_kind = FetchErrorKind::None;
                 state.stale_network_error = false;
                 state.raw_payload = result.raw_json.clone();
                 state.restaurant_name = result.restaurant_name.clone();
                 state.restaurant_url = result.restaurant_url.clone();
                 state.today_menu = result.today_menu.clone();
+                state.week_menu = result.week_menu.clone();
+                clamp_selected_day(&mut state);
                 state.provider = result.provider;
                 state.payload_date = result.payload_date.clone();
                 update_stale_date(&mut state);
+                let repeat_check = repeat_menu_check(&mut state);
+                remember_restaurant_url(
+                    &mut state.settings,
+                    &requested_code,
+                    &result.restaurant_url,
+                );
                 state.settings.last_updated_epoch_ms = now_epoch_ms();
                 if let Err(err) = save_settings(&state.settings) {
                     log_line(&format!("save settings failed: {}", err));
@@ -425,22 +661,24 @@ impl App {
                 }
                 log_line(&format!("refresh ok code={}", requested_code));
                 drop(state);
+                self.update_repeat_menu_flag(repeat_check);
                 self.store_memory_from_fetch_output(&requested_code, &requested_language, &result);
                 FetchApplyOutcome::CurrentSuccess
             } else {
                 if !state.raw_payload.is_empty() {
                     state.status = FetchStatus::Stale;
                     state.loading_started_epoch_ms = 0;
-                    state.stale_network_error = is_probable_network_error(&result.error_message);
+                    state.stale_network_error = result.error_kind.is_network();
                 } else {
                     state.status = FetchStatus::Error;
                     state.loading_started_epoch_ms = 0;
                     state.stale_network_error = false;
                 }
                 state.error_message = result.error_message.clone();
+                state.error_kind = result.error_kind;
                 log_line(&format!(
-                    "refresh failed code={} err={}",
-                    requested_code, result.error_message
+                    "refresh failed code={} kind={:?} err={}",
+                    requested_code, result.error_kind, result.error_message
                 ));
                 FetchApplyOutcome::CurrentFailure
             }
@@ -449,16 +687,16 @@ impl App {
 
     pub fn set_restaurant(&self, code: &str) {
         let mut state = self.state.lock().unwrap();
-        state.settings.restaurant_code = code.to_string();
-        let restaurant = restaurant_for_code(
-            &state.settings.restaurant_code,
-            state.settings.enable_antell_restaurants,
-        );
+        let restaurant = restaurant_for_code(code, &state.settings);
+        state.settings.restaurant_code = restaurant.code.to_string();
         state.provider = restaurant.provider;
-        state.restaurant_url = restaurant.url.unwrap_or_default().to_string();
+        state.restaurant_url = known_restaurant_url(&state.settings, &restaurant);
         let _ = save_settings(&state.settings);
         state.raw_payload.clear();
         state.today_menu = None;
+        state.week_menu = WeekMenu::default();
+        state.selected_day = None;
+        state.same_as_previous_day = false;
         state.payload_date.clear();
         state.stale_date = false;
         state.status = FetchStatus::Idle;
@@ -468,15 +706,28 @@ impl App {
 
     pub fn set_language(&self, language: &str) {
         let mut state = self.state.lock().unwrap();
-        state.settings.language = language.to_string();
-        let _ = save_settings(&state.settings);
-        state.raw_payload.clear();
-        state.today_menu = None;
-        state.payload_date.clear();
-        state.stale_date = false;
-        state.status = FetchStatus::Idle;
-        state.loading_started_epoch_ms = 0;
-        state.stale_network_error = false;
+        state.settings.auto_language = false;
+        apply_language(&mut state, language);
+    }
+
+    pub fn set_auto_language(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.auto_language = true;
+        apply_language(&mut state, &system_language());
+    }
+
+    pub fn refresh_auto_language(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if !state.settings.auto_language {
+            return false;
+        }
+        let language = system_language();
+        if state.settings.language == language {
+            return false;
+        }
+        log_line(&format!("system language changed to {}", language));
+        apply_language(&mut state, &language);
+        true
     }
 
     pub fn toggle_show_prices(&self) {
@@ -485,6 +736,12 @@ impl App {
         let _ = save_settings(&state.settings);
     }
 
+    pub fn toggle_price_column(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.price_column = !state.settings.price_column;
+        let _ = save_settings(&state.settings);
+    }
+
     pub fn toggle_show_allergens(&self) {
         let mut state = self.state.lock().unwrap();
         state.settings.show_allergens = !state.settings.show_allergens;
@@ -509,6 +766,161 @@ impl App {
         let _ = save_settings(&state.settings);
     }
 
+    pub fn toggle_show_week_number(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.show_week_number = !state.settings.show_week_number;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_show_stale_marker(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.show_stale_marker = !state.settings.show_stale_marker;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_reverse_group_order(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.reverse_group_order = !state.settings.reverse_group_order;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_flag_repeat_menus(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.flag_repeat_menus = !state.settings.flag_repeat_menus;
+        let repeat_check = repeat_menu_check(&mut state);
+        let _ = save_settings(&state.settings);
+        drop(state);
+        self.update_repeat_menu_flag(repeat_check);
+    }
+
+    pub fn toggle_swap_click_buttons(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.swap_click_buttons = !state.settings.swap_click_buttons;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_invert_wheel(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.invert_wheel = !state.settings.invert_wheel;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_close_popup_on_open_url(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.close_popup_on_open_url = !state.settings.close_popup_on_open_url;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_pin_popup(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.pin_popup = !state.settings.pin_popup;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_open_on_hover(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.open_on_hover = !state.settings.open_on_hover;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_notify_daily_menu(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.notify_daily_menu = !state.settings.notify_daily_menu;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_lunch_countdown(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.lunch_countdown = !state.settings.lunch_countdown;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_refresh_on_click_open(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.refresh_on_click_open = !state.settings.refresh_on_click_open;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_refresh_on_hover_open(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.refresh_on_hover_open = !state.settings.refresh_on_hover_open;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_show_alternate_names(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.show_alternate_names = !state.settings.show_alternate_names;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_show_energy(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.show_energy = !state.settings.show_energy;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_highlight_favorites(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.highlight_favorites = !state.settings.highlight_favorites;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_micro_mode(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.micro_mode = !state.settings.micro_mode;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_show_allergen_legend(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.show_allergen_legend = !state.settings.show_allergen_legend;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_allergen_icons(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.allergen_icons = !state.settings.allergen_icons;
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_grow_direction(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.grow_direction = if state.settings.grow_direction == "down" {
+            "up".to_string()
+        } else {
+            "down".to_string()
+        };
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_heading_position(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.heading_position = if state.settings.heading_position == "below" {
+            "above".to_string()
+        } else {
+            "below".to_string()
+        };
+        let _ = save_settings(&state.settings);
+    }
+
+    pub fn toggle_starred_dish(&self, dish: &str) {
+        let mut state = self.state.lock().unwrap();
+        let dish = normalize_text(dish);
+        if dish.is_empty() {
+            return;
+        }
+        let starred = &mut state.settings.starred_dishes;
+        if is_starred_dish(starred, &dish) {
+            let key = dish.to_lowercase();
+            starred.retain(|entry| normalize_text(entry).to_lowercase() != key);
+            log_line(&format!("unstarred dish: {}", dish));
+        } else {
+            log_line(&format!("starred dish: {}", dish));
+            starred.push(dish);
+        }
+        let _ = save_settings(&state.settings);
+    }
+
     pub fn toggle_show_student_price(&self) {
         let mut state = self.state.lock().unwrap();
         state.settings.show_student_price = !state.settings.show_student_price;
@@ -527,10 +939,13 @@ impl App {
         let _ = save_settings(&state.settings);
     }
 
-    pub fn toggle_hide_expensive_student_meals(&self) {
+    /// `None` turns the filter off and keeps the last threshold for next time.
+    pub fn set_expensive_student_threshold(&self, threshold_eur: Option<f32>) {
         let mut state = self.state.lock().unwrap();
-        state.settings.hide_expensive_student_meals =
-            !state.settings.hide_expensive_student_meals;
+        state.settings.hide_expensive_student_meals = threshold_eur.is_some();
+        if let Some(threshold_eur) = threshold_eur {
+            state.settings.expensive_student_threshold_eur = threshold_eur;
+        }
         let _ = save_settings(&state.settings);
     }
 
@@ -540,10 +955,32 @@ impl App {
         let _ = save_settings(&state.settings);
     }
 
+    /// Replaces the settings with settings.json as edited on disk, so later saves keep the edit.
+    /// Returns false, keeping the current settings, if the file cannot be read.
+    pub fn reload_settings(&self) -> bool {
+        let Some(mut settings) = reload_settings_file() else {
+            log_line("settings reload failed; keeping the current settings");
+            return false;
+        };
+        set_log_enabled(settings.enable_logging);
+        let restaurant = restaurant_for_code(&settings.restaurant_code, &settings);
+        settings.restaurant_code = restaurant.code.to_string();
+        *self.popup_keys.lock().unwrap() = popup_keybindings(&settings.popup_keys);
+        let mut state = self.state.lock().unwrap();
+        state.provider = restaurant.provider;
+        state.restaurant_url = known_restaurant_url(&settings, &restaurant);
+        state.settings = settings;
+        log_line(&format!(
+            "settings reloaded; refresh_minutes {}",
+            state.settings.refresh_minutes
+        ));
+        true
+    }
+
     pub fn cycle_restaurant(&self, direction: i32) {
         let mut state = self.state.lock().unwrap();
         let current = state.settings.restaurant_code.as_str();
-        let list = available_restaurants(state.settings.enable_antell_restaurants);
+        let list = available_restaurants(&state.settings);
         let mut idx = list.iter().position(|c| c.code == current).unwrap_or(0) as i32;
         idx += direction;
         if idx < 0 {
@@ -553,9 +990,12 @@ impl App {
         }
         state.settings.restaurant_code = list[idx as usize].code.to_string();
         state.provider = list[idx as usize].provider;
-        state.restaurant_url = list[idx as usize].url.unwrap_or_default().to_string();
+        state.restaurant_url = known_restaurant_url(&state.settings, &list[idx as usize]);
         state.raw_payload.clear();
         state.today_menu = None;
+        state.week_menu = WeekMenu::default();
+        state.selected_day = None;
+        state.same_as_previous_day = false;
         state.payload_date.clear();
         state.stale_date = false;
         state.status = FetchStatus::Idle;
@@ -563,6 +1003,43 @@ impl App {
         state.stale_network_error = false;
     }
 
+    /// Selects the `index`th restaurant of the enabled list and returns the previous index;
+    /// None when out of range or already selected.
+    pub fn set_restaurant_by_index(&self, index: usize) -> Option<usize> {
+        let (code, previous) = {
+            let state = self.state.lock().unwrap();
+            let list = available_restaurants(&state.settings);
+            let current = state.settings.restaurant_code.as_str();
+            let previous = list.iter().position(|c| c.code == current).unwrap_or(0);
+            let restaurant = list.get(index).filter(|r| r.code != current)?;
+            (restaurant.code.to_string(), previous)
+        };
+        self.set_restaurant(&code);
+        Some(previous)
+    }
+
+    pub fn cycle_day(&self, direction: i32) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let days = &state.week_menu.days;
+        let current = state
+            .selected_day
+            .or_else(|| default_day_index(days, &local_date_key()))
+            .unwrap_or(0);
+        let mut index = current as i32;
+        loop {
+            index += direction;
+            if index < 0 || index >= days.len() as i32 {
+                return false;
+            }
+            let day = &days[index as usize];
+            if day.closed || !day.menus.is_empty() {
+                break;
+            }
+        }
+        state.selected_day = Some(index as usize);
+        true
+    }
+
     pub fn persist_settings(&self) {
         let settings = {
             let state = self.state.lock().unwrap();
@@ -571,13 +1048,21 @@ impl App {
         let _ = save_settings(&settings);
     }
 
-    pub fn open_current_url(&self) {
+    /// Returns false when the current restaurant has no link to open.
+    pub fn open_current_url(&self) -> bool {
         let url = {
             let state = self.state.lock().unwrap();
-            state.restaurant_url.clone()
+            if state.restaurant_url.is_empty() {
+                let restaurant =
+                    restaurant_for_code(&state.settings.restaurant_code, &state.settings);
+                known_restaurant_url(&state.settings, &restaurant)
+            } else {
+                state.restaurant_url.clone()
+            }
         };
         if url.is_empty() {
-            return;
+            log_line("open url skipped: no link for current restaurant");
+            return false;
         }
         let wide = crate::util::to_wstring(&url);
         unsafe {
@@ -590,6 +1075,7 @@ impl App {
                 windows::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL,
             );
         }
+        true
     }
 
     pub fn open_appdata_dir(&self) {
@@ -617,15 +1103,22 @@ impl App {
         state.settings.refresh_minutes
     }
 
+    pub fn auto_rotate_seconds(&self) -> u32 {
+        let state = self.state.lock().unwrap();
+        state.settings.auto_rotate_seconds
+    }
+
+    pub fn auto_hide_seconds(&self) -> u32 {
+        let state = self.state.lock().unwrap();
+        state.settings.auto_hide_seconds
+    }
+
     pub fn maybe_refresh_on_selection(&self) {
         let (restaurant, language, refresh_minutes) = {
             let state = self.state.lock().unwrap();
             (
-                restaurant_for_code(
-                    &state.settings.restaurant_code,
-                    state.settings.enable_antell_restaurants,
-                ),
-                state.settings.language.clone(),
+                restaurant_for_code(&state.settings.restaurant_code, &state.settings),
+                menu_language(&state.settings, &state.settings.restaurant_code).to_string(),
                 state.settings.refresh_minutes,
             )
         };
@@ -635,13 +1128,14 @@ impl App {
         }
 
         let now = now_epoch_ms();
-        let should_fetch = match cache::cache_mtime_ms(restaurant.provider, restaurant.code, &language) {
+        let provider = cache::cached_provider(&restaurant, &language);
+        let should_fetch = match cache::cache_mtime_ms(provider, &restaurant.code, &language) {
             None => true,
             Some(ts) => now.saturating_sub(ts) >= (refresh_minutes as i64) * 60_000,
         };
 
         if should_fetch {
-            let _ = self.start_refresh_for_code(restaurant.code, false);
+            let _ = self.start_refresh_for_code(&restaurant.code, false);
         }
     }
 
@@ -650,6 +1144,18 @@ impl App {
         state.restaurant_name.clone()
     }
 
+    /// Remembers a user-chosen popup width; written out by the next `persist_settings`.
+    pub fn set_popup_width(&self, width: i32) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.popup_width_px = Some(width);
+    }
+
+    pub fn set_popup_anchor(&self, anchor: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.settings.popup_anchor = normalize_popup_anchor(anchor);
+        let _ = save_settings(&state.settings);
+    }
+
     pub fn set_theme(&self, theme: &str) {
         let mut state = self.state.lock().unwrap();
         state.settings.theme = normalize_theme(theme);
@@ -669,31 +1175,23 @@ impl App {
     pub fn check_stale_date_and_refresh(&self) {
         let should_refresh = {
             let mut state = self.state.lock().unwrap();
-            let today_key = today_key();
-            if !state.payload_date.is_empty() {
-                let stale = state.payload_date != today_key;
-                state.stale_date = stale;
-                stale
-            } else {
-                state.stale_date = false;
-                false
-            }
+            update_stale_date_for(&mut state, &local_date_key())
         };
         if should_refresh {
             self.start_refresh_retry();
         }
     }
 
-    pub fn next_retry_delay_ms(&self) -> u32 {
+    /// None once `max_retries` is spent; retrying resumes after the next refresh resets the count.
+    pub fn next_retry_delay_ms(&self) -> Option<u32> {
+        let max_retries = self.state.lock().unwrap().settings.max_retries as usize;
         let mut step = self.retry_step.lock().unwrap();
-        let delay = match *step {
-            0 => 10_000,
-            1 => 30_000,
-            2 => 60_000,
-            _ => 5 * 60_000,
-        };
+        if *step >= max_retries {
+            return None;
+        }
+        let delay = retry_delay_ms(*step, retry_jitter());
         *step = step.saturating_add(1);
-        delay
+        Some(delay)
     }
 
     pub fn reset_retry_backoff(&self) {
@@ -715,18 +1213,36 @@ impl App {
             let state = self.state.lock().unwrap();
             (state.settings.clone(), state.settings.restaurant_code.clone())
         };
-        let today = today_key();
-        let restaurants = available_restaurants(settings.enable_antell_restaurants);
+        let today = local_date_key();
+        let restaurants = available_restaurants(&settings);
+
+        // A batch size of 0 queues everything; otherwise resume where the last pass stopped.
+        let batch_size = match settings.prefetch_batch_size {
+            0 => usize::MAX,
+            size => size as usize,
+        };
+        let mut cursor = self.prefetch_cursor.lock().unwrap();
+        let start = if restaurants.is_empty() {
+            0
+        } else {
+            *cursor % restaurants.len()
+        };
 
         let mut queued = 0usize;
-        for restaurant in restaurants {
+        for offset in 0..restaurants.len() {
+            if queued >= batch_size {
+                break;
+            }
+            let index = (start + offset) % restaurants.len();
+            let restaurant = &restaurants[index];
             if restaurant.code == current_code {
                 continue;
             }
+            let language = menu_language(&settings, &restaurant.code);
             let stale_or_missing = match cache::cache_mtime_ms(
-                restaurant.provider,
-                restaurant.code,
-                &settings.language,
+                cache::cached_provider(restaurant, language),
+                &restaurant.code,
+                language,
             ) {
                 None => true,
                 Some(ts) => match date_key_from_epoch_ms(ts) {
@@ -734,21 +1250,155 @@ impl App {
                     None => true,
                 },
             };
-            if stale_or_missing && self.start_refresh_for_code(restaurant.code, false) {
+            if stale_or_missing && self.start_refresh_for_code(&restaurant.code, false) {
                 queued += 1;
+                *cursor = index + 1;
             }
         }
-        log_line(&format!("prefetch queued={}", queued));
+        log_line(&format!("prefetch queued={} cursor={}", queued, *cursor));
+    }
+
+    /// The balloon to show after a fetch: the daily menu, the favourite dishes, or both.
+    pub fn take_menu_notification(&self) -> Option<(String, String)> {
+        combine_notifications(
+            self.take_favorite_notification(),
+            self.take_daily_notification(),
+        )
+    }
+
+    /// Title and text for the once-a-day menu notification, if one is due now.
+    pub fn take_daily_notification(&self) -> Option<(String, String)> {
+        let mut state = self.state.lock().unwrap();
+        if !state.settings.notify_daily_menu {
+            return None;
+        }
+        let today = local_date_key();
+        if date_key_from_epoch_ms(state.settings.last_notified_epoch_ms).as_deref()
+            == Some(today.as_str())
+        {
+            return None;
+        }
+        let menu = state.today_menu.as_ref()?;
+        if menu.closed || menu.menus.is_empty() || menu.date_iso != today {
+            return None;
+        }
+        let text = menu
+            .menus
+            .iter()
+            .take(2)
+            .map(|group| {
+                let heading = normalize_text(&group.name);
+                match group.components.first() {
+                    Some(dish) if heading.is_empty() => normalize_text(dish),
+                    Some(dish) => format!("{}: {}", heading, normalize_text(dish)),
+                    None => heading,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let title = state.restaurant_name.clone();
+        state.settings.last_notified_epoch_ms = now_epoch_ms();
+        let _ = save_settings(&state.settings);
+        Some((title, text))
+    }
+
+    /// Restaurant name and first dish for the scheduled lunch reminder, if today has a menu.
+    pub fn lunch_reminder(&self) -> Option<(String, String)> {
+        let state = self.state.lock().unwrap();
+        let menu = state.today_menu.as_ref()?;
+        if menu.closed || menu.date_iso != local_date_key() {
+            return None;
+        }
+        let dish = menu
+            .menus
+            .iter()
+            .flat_map(|group| group.components.iter())
+            .map(|dish| normalize_text(dish))
+            .find(|dish| !dish.is_empty())?;
+        Some((state.restaurant_name.clone(), dish))
+    }
+
+    /// Today's dishes matching a favorite keyword, at most once per day.
+    pub fn take_favorite_notification(&self) -> Option<(String, String)> {
+        let mut state = self.state.lock().unwrap();
+        if !state.settings.highlight_favorites || state.settings.favorite_keywords.is_empty() {
+            return None;
+        }
+        let today = local_date_key();
+        if date_key_from_epoch_ms(state.settings.last_favorite_notified_epoch_ms).as_deref()
+            == Some(today.as_str())
+        {
+            return None;
+        }
+        let menu = state.today_menu.as_ref()?;
+        if menu.closed || menu.date_iso != today {
+            return None;
+        }
+        let dishes: Vec<String> = menu
+            .menus
+            .iter()
+            .flat_map(|group| group.components.iter())
+            .map(|dish| normalize_text(dish))
+            .filter(|dish| matches_favorite_keyword(&state.settings.favorite_keywords, dish))
+            .collect();
+        if dishes.is_empty() {
+            return None;
+        }
+        let title = format!(
+            "{} · {}",
+            state.restaurant_name,
+            text_for(&state.settings.language, "favoriteToday")
+        );
+        state.settings.last_favorite_notified_epoch_ms = now_epoch_ms();
+        let _ = save_settings(&state.settings);
+        Some((title, dishes.join("\n")))
     }
 
     pub fn set_hover_point(&self, x: i32, y: i32) {
         let mut point = self.hover_point.lock().unwrap();
         *point = Some((x, y));
+        self.hover_started
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
     }
 
     pub fn clear_hover_point(&self) {
         let mut point = self.hover_point.lock().unwrap();
         *point = None;
+        *self.hover_started.lock().unwrap() = None;
+        *self.hover_left.lock().unwrap() = None;
+    }
+
+    pub fn hover_elapsed_ms(&self) -> Option<u128> {
+        let started = self.hover_started.lock().unwrap();
+        started.map(|at| at.elapsed().as_millis())
+    }
+
+    pub fn finish_hover_open(&self) {
+        *self.hover_started.lock().unwrap() = None;
+        *self.hover_left.lock().unwrap() = None;
+    }
+
+    /// Whether the cursor has now been away from a hover-opened popup for the whole
+    /// `hover_close_grace_ms`. The first call after it leaves starts the clock.
+    pub fn hover_close_grace_elapsed(&self) -> bool {
+        let grace_ms = self.state.lock().unwrap().settings.hover_close_grace_ms;
+        let mut left = self.hover_left.lock().unwrap();
+        let left_at = left.get_or_insert_with(Instant::now);
+        left_at.elapsed().as_millis() >= grace_ms as u128
+    }
+
+    pub fn hover_returned(&self) {
+        *self.hover_left.lock().unwrap() = None;
+    }
+
+    pub fn hover_open_delay_ms(&self) -> Option<u32> {
+        let state = self.state.lock().unwrap();
+        state
+            .settings
+            .open_on_hover
+            .then_some(state.settings.hover_open_ms)
     }
 
     pub fn hover_point(&self) -> Option<(i32, i32)> {
@@ -767,6 +1417,24 @@ impl App {
     }
 }
 
+/// Backoff schedule with `jitter` in -1.0..=1.0 scaling the delay by up to ±20%.
+fn retry_delay_ms(step: usize, jitter: f64) -> u32 {
+    let base: u32 = match step {
+        0 => 10_000,
+        1 => 30_000,
+        2 => 60_000,
+        _ => 5 * 60_000,
+    };
+    (base as f64 * (1.0 + 0.2 * jitter.clamp(-1.0, 1.0))).round() as u32
+}
+
+/// Cheap pseudo-random value in -1.0..=1.0 so instances don't retry in lockstep.
+fn retry_jitter() -> f64 {
+    let nanos = OffsetDateTime::now_utc().unix_timestamp_nanos() as u64;
+    let mixed = nanos.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+    (mixed % 2001) as f64 / 1000.0 - 1.0
+}
+
 pub fn now_epoch_ms() -> i64 {
     let now = OffsetDateTime::now_utc();
     (now.unix_timestamp_nanos() / 1_000_000) as i64
@@ -776,53 +1444,358 @@ fn menu_cache_key(code: &str, language: &str) -> String {
     format!("{}|{}", language, code)
 }
 
-fn today_key() -> String {
-    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
-    let date = now.date();
-    format!("{:04}-{:02}-{:02}", date.year(), date.month() as u8, date.day())
+fn write_history_copy(code: &str, language: &str, result: &FetchOutput, keep_days: u32) {
+    if let Err(err) = cache::write_dated_cache(
+        result.provider,
+        code,
+        language,
+        local_now().date(),
+        &result.raw_json,
+        keep_days,
+    ) {
+        log_line(&format!(
+            "history cache write failed code={} err={}",
+            code, err
+        ));
+    }
 }
 
-fn update_stale_date(state: &mut AppState) {
-    if !state.payload_date.is_empty() {
-        state.stale_date = state.payload_date != today_key();
-    } else {
-        state.stale_date = false;
+fn apply_language(state: &mut AppState, language: &str) {
+    state.settings.language = language.to_string();
+    let _ = save_settings(&state.settings);
+    state.raw_payload.clear();
+    state.today_menu = None;
+    state.week_menu = WeekMenu::default();
+    state.selected_day = None;
+    state.same_as_previous_day = false;
+    state.payload_date.clear();
+    state.stale_date = false;
+    state.status = FetchStatus::Idle;
+    state.loading_started_epoch_ms = 0;
+    state.stale_network_error = false;
+}
+
+pub fn is_dated_by_cache_mtime(provider: Provider) -> bool {
+    matches!(provider, Provider::Antell | Provider::SodexoJson)
+}
+
+fn known_restaurant_url(settings: &Settings, restaurant: &Restaurant) -> String {
+    settings
+        .restaurant_urls
+        .get(restaurant.code.as_ref())
+        .filter(|url| !url.is_empty())
+        .cloned()
+        .unwrap_or_else(|| restaurant.url.as_deref().unwrap_or_default().to_string())
+}
+
+fn remember_restaurant_url(settings: &mut Settings, code: &str, url: &str) -> bool {
+    let url = url.trim();
+    let known = settings.restaurant_urls.get(code);
+    if url.is_empty() || known.is_some_and(|known| known == url) {
+        return false;
     }
+    settings
+        .restaurant_urls
+        .insert(code.to_string(), url.to_string());
+    true
 }
 
-fn date_key_from_epoch_ms(ms: i64) -> Option<String> {
-    if ms <= 0 {
+pub fn default_day_index(days: &[TodayMenu], today_key: &str) -> Option<usize> {
+    days.iter()
+        .position(|day| day.date_iso.as_str() >= today_key && !day.menus.is_empty())
+        .or_else(|| days.iter().position(|day| day.date_iso == today_key))
+}
+
+/// Index of the day `offset` weekdays after `today`, skipping weekends, if the week has it.
+pub fn weekday_index_after(days: &[TodayMenu], today: Date, offset: usize) -> Option<usize> {
+    let mut date = today;
+    for _ in 0..offset {
+        date = date.next_day()?;
+        while matches!(date.weekday(), Weekday::Saturday | Weekday::Sunday) {
+            date = date.next_day()?;
+        }
+    }
+    let key = date_key(date);
+    days.iter().position(|day| day.date_iso == key)
+}
+
+fn clamp_selected_day(state: &mut AppState) {
+    if state
+        .selected_day
+        .is_some_and(|index| index >= state.week_menu.days.len())
+    {
+        state.selected_day = None;
+    }
+}
+
+/// Menu signatures of the shown restaurant, taken under the `state` lock for
+/// `App::update_repeat_menu_flag` to compare with the history file afterwards.
+struct RepeatMenuCheck {
+    provider: Provider,
+    code: String,
+    language: String,
+    today_date: String,
+    today_signature: Vec<Vec<String>>,
+    day_signatures: Vec<(String, Vec<Vec<String>>)>,
+}
+
+/// Clears `same_as_previous_day`; None when repeat flagging is off or there is no menu today.
+fn repeat_menu_check(state: &mut AppState) -> Option<RepeatMenuCheck> {
+    state.same_as_previous_day = false;
+    if !state.settings.flag_repeat_menus {
         return None;
     }
-    let secs = ms / 1000;
-    let nanos = ((ms % 1000) * 1_000_000) as u32;
-    let mut dt = OffsetDateTime::from_unix_timestamp(secs).ok()?;
-    dt = dt.replace_nanosecond(nanos).ok()?;
-    let offset = time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC);
-    let local = dt.to_offset(offset);
-    let date = local.date();
-    Some(format!("{:04}-{:02}-{:02}", date.year(), date.month() as u8, date.day()))
+    let today = state.today_menu.as_ref()?;
+    let today_signature = menu_signature(today);
+    if today_signature.is_empty() {
+        return None;
+    }
+    let code = state.settings.restaurant_code.clone();
+    Some(RepeatMenuCheck {
+        provider: state.provider,
+        language: menu_language(&state.settings, &code).to_string(),
+        code,
+        today_date: today.date_iso.clone(),
+        today_signature,
+        day_signatures: state
+            .week_menu
+            .days
+            .iter()
+            .filter(|day| !day.date_iso.is_empty())
+            .map(|day| (day.date_iso.clone(), menu_signature(day)))
+            .filter(|(_, signature)| !signature.is_empty())
+            .collect(),
+    })
+}
+
+/// Adds the check's signatures to `history`; true when the latest earlier day served the
+/// same menu as today, so a Monday repeating Friday's menu counts.
+fn record_menu_signatures(history: &mut MenuHistory, check: &RepeatMenuCheck) -> bool {
+    for (date, signature) in &check.day_signatures {
+        history.insert(date.clone(), signature.clone());
+    }
+    history.insert(check.today_date.clone(), check.today_signature.clone());
+    history
+        .range(..check.today_date.clone())
+        .next_back()
+        .is_some_and(|(_, previous)| *previous == check.today_signature)
 }
 
-fn is_probable_network_error(message: &str) -> bool {
-    let lower = message.to_ascii_lowercase();
-    [
-        "timed out",
-        "timeout",
-        "dns",
-        "network",
-        "connection",
-        "connect",
-        "host",
-        "resolve",
-        "name or service not known",
-        "temporary failure",
-        "connection reset",
-        "connection refused",
-        "tls",
-        "certificate",
-        "os error",
-    ]
-    .iter()
-    .any(|token| lower.contains(token))
+/// Puts the favourite dishes on top of the daily menu when both are due, so showing one does
+/// not swallow the other.
+fn combine_notifications(
+    favorite: Option<(String, String)>,
+    daily: Option<(String, String)>,
+) -> Option<(String, String)> {
+    match (favorite, daily) {
+        (Some((title, dishes)), Some((_, menu))) => {
+            let dishes: Vec<String> = dishes.lines().map(|dish| format!("★ {}", dish)).collect();
+            Some((title, format!("{}\n{}", dishes.join("\n"), menu)))
+        }
+        (favorite, daily) => favorite.or(daily),
+    }
+}
+
+fn update_stale_date(state: &mut AppState) {
+    update_stale_date_for(state, &local_date_key());
+}
+
+fn update_stale_date_for(state: &mut AppState, today_key: &str) -> bool {
+    state.stale_date = !state.payload_date.is_empty() && state.payload_date != today_key;
+    state.stale_date
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        combine_notifications, default_day_index, record_menu_signatures, repeat_menu_check,
+        retry_delay_ms, update_stale_date_for, weekday_index_after, AppState, FetchStatus,
+    };
+    use crate::api::FetchErrorKind;
+    use crate::cache::MenuHistory;
+    use crate::model::{MenuGroup, TodayMenu, WeekMenu};
+    use crate::restaurant::Provider;
+    use crate::settings::Settings;
+
+    fn state_with_payload_date(payload_date: &str) -> AppState {
+        AppState {
+            settings: Settings::default(),
+            status: FetchStatus::Ok,
+            loading_started_epoch_ms: 0,
+            error_message: String::new(),
+            error_kind: FetchErrorKind::None,
+            stale_network_error: false,
+            today_menu: None,
+            week_menu: WeekMenu::default(),
+            selected_day: None,
+            restaurant_name: String::new(),
+            restaurant_url: String::new(),
+            raw_payload: String::new(),
+            provider: Provider::Compass,
+            payload_date: payload_date.to_string(),
+            stale_date: false,
+            same_as_previous_day: false,
+        }
+    }
+
+    fn menu_day(date: &str, dishes: &[&str]) -> TodayMenu {
+        TodayMenu {
+            date_iso: date.to_string(),
+            lunch_time: String::new(),
+            menus: vec![MenuGroup {
+                name: "Lounas".to_string(),
+                price: String::new(),
+                components: dishes.iter().map(|dish| dish.to_string()).collect(),
+                energy: Vec::new(),
+                prices: Vec::new(),
+            }],
+            closed: false,
+        }
+    }
+
+    #[test]
+    fn repeat_menu_compares_with_the_latest_earlier_day() {
+        let mut state = state_with_payload_date("2025-05-05");
+        state.same_as_previous_day = true;
+        assert!(repeat_menu_check(&mut state).is_none());
+        assert!(!state.same_as_previous_day);
+
+        state.settings.flag_repeat_menus = true;
+        let monday = menu_day("2025-05-05", &["Lohikeitto", "Ruisleipä"]);
+        state.today_menu = Some(monday.clone());
+        state.week_menu = WeekMenu {
+            days: vec![monday.clone(), menu_day("2025-05-06", &["Pizza"])],
+        };
+        let check = repeat_menu_check(&mut state).unwrap();
+
+        // Friday's menu, saved by an earlier fetch, carries over the weekend.
+        let mut history = MenuHistory::new();
+        let mut friday = menu_day("2025-05-02", &["ruisleipä", " Lohikeitto "]);
+        history.insert(
+            friday.date_iso.clone(),
+            crate::format::menu_signature(&friday),
+        );
+        assert!(record_menu_signatures(&mut history, &check));
+        assert_eq!(history.len(), 3);
+
+        friday.menus[0].components = vec!["Makaronilaatikko".to_string()];
+        let mut history = MenuHistory::new();
+        history.insert(
+            friday.date_iso.clone(),
+            crate::format::menu_signature(&friday),
+        );
+        assert!(!record_menu_signatures(&mut history, &check));
+
+        assert!(!record_menu_signatures(&mut MenuHistory::new(), &check));
+    }
+
+    #[test]
+    fn payload_dated_today_is_not_stale() {
+        let mut state = state_with_payload_date("2025-05-06");
+        let should_refresh = update_stale_date_for(&mut state, "2025-05-06");
+        assert!(!state.stale_date);
+        assert!(!should_refresh);
+    }
+
+    #[test]
+    fn payload_dated_yesterday_is_stale_and_refreshes() {
+        let mut state = state_with_payload_date("2025-05-05");
+        let should_refresh = update_stale_date_for(&mut state, "2025-05-06");
+        assert!(state.stale_date);
+        assert!(should_refresh);
+    }
+
+    #[test]
+    fn empty_payload_date_is_not_stale() {
+        let mut state = state_with_payload_date("");
+        state.stale_date = true;
+        let should_refresh = update_stale_date_for(&mut state, "2025-05-06");
+        assert!(!state.stale_date);
+        assert!(!should_refresh);
+    }
+
+    fn day(date_iso: &str, has_menu: bool) -> TodayMenu {
+        let menus = if has_menu {
+            vec![MenuGroup {
+                name: "Lunch".to_string(),
+                price: String::new(),
+                components: vec!["Soup".to_string()],
+                energy: Vec::new(),
+                prices: Vec::new(),
+            }]
+        } else {
+            Vec::new()
+        };
+        TodayMenu {
+            date_iso: date_iso.to_string(),
+            lunch_time: String::new(),
+            menus,
+            closed: false,
+        }
+    }
+
+    #[test]
+    fn default_day_is_today_on_weekdays() {
+        let days = vec![day("2025-05-05", true), day("2025-05-06", true)];
+        assert_eq!(default_day_index(&days, "2025-05-06"), Some(1));
+    }
+
+    #[test]
+    fn default_day_skips_weekend_to_next_day_with_menu() {
+        let days = vec![
+            day("2025-05-09", true),
+            day("2025-05-10", false),
+            day("2025-05-11", false),
+            day("2025-05-12", true),
+        ];
+        assert_eq!(default_day_index(&days, "2025-05-10"), Some(3));
+    }
+
+    #[test]
+    fn tomorrow_peek_skips_weekend_to_monday() {
+        let days = vec![
+            day("2025-05-08", true),
+            day("2025-05-09", true),
+            day("2025-05-12", true),
+        ];
+        let may = |day: u8| time::Date::from_calendar_date(2025, time::Month::May, day).unwrap();
+        assert_eq!(weekday_index_after(&days, may(8), 1), Some(1));
+        assert_eq!(weekday_index_after(&days, may(9), 1), Some(2));
+        assert_eq!(weekday_index_after(&days, may(10), 1), Some(2));
+        assert_eq!(weekday_index_after(&days, may(12), 1), None);
+    }
+
+    #[test]
+    fn retry_delay_steps_up_and_stays_within_jitter() {
+        assert_eq!(retry_delay_ms(0, 0.0), 10_000);
+        assert_eq!(retry_delay_ms(1, 0.0), 30_000);
+        assert_eq!(retry_delay_ms(2, 0.0), 60_000);
+        assert_eq!(retry_delay_ms(3, 0.0), 300_000);
+        assert_eq!(retry_delay_ms(9, 0.0), 300_000);
+        assert_eq!(retry_delay_ms(0, -1.0), 8_000);
+        assert_eq!(retry_delay_ms(0, 1.0), 12_000);
+        assert_eq!(retry_delay_ms(3, 5.0), 360_000);
+    }
+
+    #[test]
+    fn favorite_and_daily_notifications_share_one_balloon() {
+        let favorite = Some((
+            "Snellmania · Favourite today".to_string(),
+            "Salmon soup".to_string(),
+        ));
+        let daily = Some((
+            "Snellmania".to_string(),
+            "Lunch: Chicken curry\nSoup: Salmon soup".to_string(),
+        ));
+        assert_eq!(
+            combine_notifications(favorite.clone(), daily.clone()),
+            Some((
+                "Snellmania · Favourite today".to_string(),
+                "★ Salmon soup\nLunch: Chicken curry\nSoup: Salmon soup".to_string()
+            ))
+        );
+        assert_eq!(combine_notifications(None, daily.clone()), daily);
+        assert_eq!(combine_notifications(favorite.clone(), None), favorite);
+        assert_eq!(combine_notifications(None, None), None);
+    }
 }
@@ -1,15 +1,19 @@
-use crate::restaurant::{provider_key, Provider};
+use crate::restaurant::{provider_key, Provider, Restaurant};
+use crate::util::{date_key, write_atomic};
 use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use time::{Date, Duration};
 
 pub fn cache_dir() -> PathBuf {
     let base = std::env::var("LOCALAPPDATA").unwrap_or_else(|_| ".".to_string());
     Path::new(&base).join("compass-lunch").join("cache")
 }
 
-pub fn cache_path(provider: Provider, code: &str, language: &str) -> PathBuf {
-    cache_dir().join(cache_filename(provider, code, language))
+pub fn cache_path(dir: &Path, provider: Provider, code: &str, language: &str) -> PathBuf {
+    dir.join(cache_filename(provider, code, language))
 }
 
 fn cache_filename(provider: Provider, code: &str, language: &str) -> String {
@@ -18,6 +22,8 @@ fn cache_filename(provider: Provider, code: &str, language: &str) -> String {
         Provider::CompassRss => "xml",
         Provider::Antell => "html",
         Provider::HuomenJson => "json",
+        Provider::SodexoJson => "json",
+        Provider::UnicaJson => "json",
     };
     format!(
         "{}__{}__{}.{}",
@@ -28,15 +34,17 @@ fn cache_filename(provider: Provider, code: &str, language: &str) -> String {
     )
 }
 
-fn legacy_cache_path(provider: Provider, code: &str, language: &str) -> PathBuf {
+fn legacy_cache_path(dir: &Path, provider: Provider, code: &str, language: &str) -> PathBuf {
     let ext = match provider {
         Provider::Compass => "json",
         Provider::CompassRss => "xml",
         Provider::Antell => "html",
         Provider::HuomenJson => "json",
+        Provider::SodexoJson => "json",
+        Provider::UnicaJson => "json",
     };
     let filename = format!("{}|{}|{}.{}", provider_key(provider), code, language, ext);
-    cache_dir().join(filename)
+    dir.join(filename)
 }
 
 fn sanitize_key_segment(value: &str) -> String {
@@ -53,35 +61,610 @@ fn sanitize_key_segment(value: &str) -> String {
 }
 
 pub fn read_cache(provider: Provider, code: &str, language: &str) -> Option<String> {
-    let path = cache_path(provider, code, language);
+    read_cache_in(&cache_dir(), provider, code, language)
+}
+
+fn read_cache_in(dir: &Path, provider: Provider, code: &str, language: &str) -> Option<String> {
+    let path = cache_path(dir, provider, code, language);
     match fs::read_to_string(&path) {
         Ok(data) => Some(data),
         Err(_) => {
-            let legacy_path = legacy_cache_path(provider, code, language);
+            let legacy_path = legacy_cache_path(dir, provider, code, language);
             fs::read_to_string(legacy_path).ok()
         }
     }
 }
 
 pub fn cache_mtime_ms(provider: Provider, code: &str, language: &str) -> Option<i64> {
-    let path = cache_path(provider, code, language);
+    cache_mtime_ms_in(&cache_dir(), provider, code, language)
+}
+
+fn cache_mtime_ms_in(dir: &Path, provider: Provider, code: &str, language: &str) -> Option<i64> {
+    let path = cache_path(dir, provider, code, language);
     let metadata = fs::metadata(&path)
-        .or_else(|_| fs::metadata(legacy_cache_path(provider, code, language)))
+        .or_else(|_| fs::metadata(legacy_cache_path(dir, provider, code, language)))
         .ok()?;
     let modified = metadata.modified().ok()?;
     let duration = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
     Some(duration.as_millis() as i64)
 }
 
+/// Provider whose cache file holds `restaurant`'s newest payload: its own, or `CompassRss` when
+/// the last fetch of a Compass restaurant fell back to its RSS feed.
+pub fn cached_provider(restaurant: &Restaurant, language: &str) -> Provider {
+    let own = restaurant.provider;
+    if restaurant.fallback_rss.is_none() {
+        return own;
+    }
+    let rss_mtime = cache_mtime_ms(Provider::CompassRss, &restaurant.code, language);
+    if rss_mtime > cache_mtime_ms(own, &restaurant.code, language) {
+        Provider::CompassRss
+    } else {
+        own
+    }
+}
+
 pub fn write_cache(
     provider: Provider,
     code: &str,
     language: &str,
     payload: &str,
 ) -> anyhow::Result<()> {
+    write_cache_in(&cache_dir(), provider, code, language, payload)
+}
+
+fn write_cache_in(
+    dir: &Path,
+    provider: Provider,
+    code: &str,
+    language: &str,
+    payload: &str,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(dir).context("create cache dir")?;
+    let path = cache_path(dir, provider, code, language);
+    write_atomic(&path, payload).with_context(|| format!("write cache file {}", path.display()))?;
+    Ok(())
+}
+
+/// `<cache name>_<YYYY-MM-DD>.<ext>`, a per-day copy kept alongside the live cache file.
+fn dated_cache_filename(provider: Provider, code: &str, language: &str, date_key: &str) -> String {
+    let name = cache_filename(provider, code, language);
+    match name.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}_{}.{}", stem, date_key, ext),
+        None => format!("{}_{}", name, date_key),
+    }
+}
+
+pub fn read_cache_for_date(
+    provider: Provider,
+    code: &str,
+    language: &str,
+    date_key: &str,
+) -> Option<String> {
+    read_cache_for_date_in(&cache_dir(), provider, code, language, date_key)
+}
+
+fn read_cache_for_date_in(
+    dir: &Path,
+    provider: Provider,
+    code: &str,
+    language: &str,
+    date_key: &str,
+) -> Option<String> {
+    fs::read_to_string(dir.join(dated_cache_filename(provider, code, language, date_key))).ok()
+}
+
+/// Like `read_cache_for_date`, but also finds a dated copy written by the RSS fallback;
+/// returns the provider to parse it with.
+pub fn read_restaurant_cache_for_date(
+    restaurant: &Restaurant,
+    language: &str,
+    date_key: &str,
+) -> Option<(Provider, String)> {
+    let fallback = restaurant
+        .fallback_rss
+        .as_ref()
+        .map(|_| Provider::CompassRss);
+    std::iter::once(restaurant.provider)
+        .chain(fallback)
+        .find_map(|provider| {
+            read_cache_for_date(provider, &restaurant.code, language, date_key)
+                .map(|raw| (provider, raw))
+        })
+}
+
+/// Writes today's dated copy and removes copies older than `keep_days` days (today included).
+pub fn write_dated_cache(
+    provider: Provider,
+    code: &str,
+    language: &str,
+    today: Date,
+    payload: &str,
+    keep_days: u32,
+) -> anyhow::Result<()> {
+    let dir = cache_dir();
+    write_dated_cache_in(&dir, provider, code, language, today, payload, keep_days)
+}
+
+fn write_dated_cache_in(
+    dir: &Path,
+    provider: Provider,
+    code: &str,
+    language: &str,
+    today: Date,
+    payload: &str,
+    keep_days: u32,
+) -> anyhow::Result<()> {
+    if keep_days == 0 {
+        return Ok(());
+    }
+    fs::create_dir_all(dir).context("create cache dir")?;
+    let path = dir.join(dated_cache_filename(
+        provider,
+        code,
+        language,
+        &date_key(today),
+    ));
+    write_atomic(&path, payload).with_context(|| format!("write cache file {}", path.display()))?;
+
+    let oldest_kept = date_key(today - Duration::days(keep_days as i64 - 1));
+    let template = dated_cache_filename(provider, code, language, "0000-00-00");
+    let (prefix, suffix) = template.rsplit_once("0000-00-00").unwrap_or_default();
+    for entry in fs::read_dir(dir).context("read cache dir")?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(date) = name
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(suffix))
+        else {
+            continue;
+        };
+        if date.len() == 10 && date < oldest_kept.as_str() {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Validators from the last full response, sent back so an unchanged feed answers 304.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheMeta {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+fn meta_filename(provider: Provider, code: &str, language: &str) -> String {
+    let mut name = cache_filename(provider, code, language);
+    if let Some(dot) = name.rfind('.') {
+        name.truncate(dot);
+    }
+    format!("{}.meta.json", name)
+}
+
+fn meta_path(dir: &Path, provider: Provider, code: &str, language: &str) -> PathBuf {
+    dir.join(meta_filename(provider, code, language))
+}
+
+pub fn read_cache_meta(provider: Provider, code: &str, language: &str) -> CacheMeta {
+    read_cache_meta_in(&cache_dir(), provider, code, language)
+}
+
+fn read_cache_meta_in(dir: &Path, provider: Provider, code: &str, language: &str) -> CacheMeta {
+    fs::read_to_string(meta_path(dir, provider, code, language))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_cache_meta(
+    provider: Provider,
+    code: &str,
+    language: &str,
+    meta: &CacheMeta,
+) -> anyhow::Result<()> {
+    write_cache_meta_in(&cache_dir(), provider, code, language, meta)
+}
+
+fn write_cache_meta_in(
+    dir: &Path,
+    provider: Provider,
+    code: &str,
+    language: &str,
+    meta: &CacheMeta,
+) -> anyhow::Result<()> {
+    let path = meta_path(dir, provider, code, language);
+    if meta == &CacheMeta::default() {
+        // Nothing to revalidate with; drop any validators left from an earlier response.
+        let _ = fs::remove_file(&path);
+        return Ok(());
+    }
+    fs::create_dir_all(dir).context("create cache dir")?;
+    let data = serde_json::to_string(meta)?;
+    write_atomic(&path, data).with_context(|| format!("write cache meta {}", path.display()))?;
+    Ok(())
+}
+
+pub fn clear_cache_meta(provider: Provider, code: &str, language: &str) {
+    clear_cache_meta_in(&cache_dir(), provider, code, language);
+}
+
+fn clear_cache_meta_in(dir: &Path, provider: Provider, code: &str, language: &str) {
+    let _ = fs::remove_file(meta_path(dir, provider, code, language));
+}
+
+/// Cache files untouched for this long belong to restaurants no longer shown.
+pub const CACHE_MAX_AGE_DAYS: u64 = 30;
+
+/// Deletes cache files not written in `max_age_days` days, `.meta.json` sidecars left without
+/// a cache file, and legacy `|`-named files that have a current-named copy. Dated copies and
+/// menu histories may be as old as `keep_history_days`. Returns how many were removed.
+pub fn prune(max_age_days: u64, keep_history_days: u32) -> usize {
+    let day = 24 * 60 * 60;
+    prune_dir(
+        &cache_dir(),
+        std::time::Duration::from_secs(max_age_days * day),
+        std::time::Duration::from_secs(max_age_days.max(keep_history_days as u64) * day),
+    )
+}
+
+fn prune_dir(
+    dir: &Path,
+    max_age: std::time::Duration,
+    history_max_age: std::time::Duration,
+) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let now = std::time::SystemTime::now();
+    let files: Vec<(String, Option<std::time::Duration>)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())?;
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok());
+            Some((entry.file_name().to_string_lossy().into_owned(), age))
+        })
+        .collect();
+    let names: HashSet<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+
+    let mut removed = 0;
+    let mut kept = HashSet::new();
+    for (name, age) in &files {
+        if name.ends_with(".meta.json") {
+            continue;
+        }
+        let limit = if is_history_file(name) {
+            history_max_age
+        } else {
+            max_age
+        };
+        let expired = age.is_some_and(|age| age > limit);
+        let superseded =
+            modern_name_for_legacy(name).is_some_and(|modern| names.contains(modern.as_str()));
+        if (expired || superseded) && fs::remove_file(dir.join(name)).is_ok() {
+            removed += 1;
+        } else {
+            kept.insert(name.as_str());
+        }
+    }
+    // A sidecar lives as long as its cache file; 304 answers refresh only the cache file.
+    for (name, _) in &files {
+        let Some(stem) = name.strip_suffix(".meta.json") else {
+            continue;
+        };
+        let has_cache_file = kept.iter().any(|kept_name| {
+            kept_name
+                .strip_prefix(stem)
+                .and_then(|rest| rest.strip_prefix('.'))
+                .is_some_and(|ext| !ext.contains('.'))
+        });
+        if !has_cache_file && fs::remove_file(dir.join(name)).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// Dated copies from `write_dated_cache` and `history__` files, kept for `keep_history_days`.
+fn is_history_file(name: &str) -> bool {
+    if name.starts_with("history__") {
+        return true;
+    }
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    stem.rsplit_once('_').is_some_and(|(_, date)| {
+        date.len() == 10
+            && date.char_indices().all(|(index, ch)| match index {
+                4 | 7 => ch == '-',
+                _ => ch.is_ascii_digit(),
+            })
+    })
+}
+
+/// `compass|0437|fi.json` -> `compass__0437__fi.json`, the name `cache_filename` gives it now.
+fn modern_name_for_legacy(name: &str) -> Option<String> {
+    let (key, ext) = name.rsplit_once('.')?;
+    let mut parts = key.split('|');
+    let (provider, code, language) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(format!(
+        "{}__{}__{}.{}",
+        sanitize_key_segment(provider),
+        sanitize_key_segment(code),
+        sanitize_key_segment(language),
+        ext
+    ))
+}
+
+pub type MenuHistory = BTreeMap<String, Vec<Vec<String>>>;
+
+const MENU_HISTORY_DAYS: usize = 14;
+
+fn history_path(dir: &Path, provider: Provider, code: &str, language: &str) -> PathBuf {
+    dir.join(format!(
+        "history__{}__{}__{}.json",
+        sanitize_key_segment(provider_key(provider)),
+        sanitize_key_segment(code),
+        sanitize_key_segment(language)
+    ))
+}
+
+pub fn read_menu_history(provider: Provider, code: &str, language: &str) -> MenuHistory {
+    fs::read_to_string(history_path(&cache_dir(), provider, code, language))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_menu_history(
+    provider: Provider,
+    code: &str,
+    language: &str,
+    history: &MenuHistory,
+) -> anyhow::Result<()> {
+    let mut trimmed = history.clone();
+    while trimmed.len() > MENU_HISTORY_DAYS {
+        let oldest = trimmed.keys().next().cloned().unwrap_or_default();
+        trimmed.remove(&oldest);
+    }
     let dir = cache_dir();
     fs::create_dir_all(&dir).context("create cache dir")?;
-    let path = cache_path(provider, code, language);
-    fs::write(&path, payload).with_context(|| format!("write cache file {}", path.display()))?;
+    let path = history_path(&dir, provider, code, language);
+    let data = serde_json::to_string(&trimmed)?;
+    write_atomic(&path, data).with_context(|| format!("write history file {}", path.display()))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cache_filename, cache_mtime_ms_in, clear_cache_meta_in, dated_cache_filename,
+        is_history_file, legacy_cache_path, meta_filename, meta_path, modern_name_for_legacy,
+        prune_dir, read_cache_for_date_in, read_cache_in, read_cache_meta_in, sanitize_key_segment,
+        write_cache_in, write_cache_meta_in, write_dated_cache_in, CacheMeta,
+    };
+    use crate::restaurant::Provider;
+    use std::fs;
+    use std::path::PathBuf;
+    use time::{Date, Month};
+
+    #[test]
+    fn sanitizes_unsafe_key_characters() {
+        assert_eq!(sanitize_key_segment("a b/c\\d|e"), "a_b_c_d_e");
+        assert_eq!(sanitize_key_segment("snellari-rss"), "snellari-rss");
+        assert_eq!(
+            cache_filename(Provider::Compass, "my code/1", "fi"),
+            "compass__my_code_1__fi.json"
+        );
+        assert_eq!(
+            meta_filename(Provider::CompassRss, "snellari-rss", "fi"),
+            "compass-rss__snellari-rss__fi.meta.json"
+        );
+        assert_eq!(
+            dated_cache_filename(Provider::Antell, "antell-round", "fi", "2026-10-16"),
+            "antell__antell-round__fi_2026-10-16.html"
+        );
+    }
+
+    #[test]
+    fn extension_follows_provider() {
+        assert!(cache_filename(Provider::Compass, "0437", "fi").ends_with(".json"));
+        assert!(cache_filename(Provider::CompassRss, "snellari-rss", "fi").ends_with(".xml"));
+        assert!(cache_filename(Provider::Antell, "antell-round", "fi").ends_with(".html"));
+        assert!(cache_filename(Provider::HuomenJson, "huomen", "fi").ends_with(".json"));
+        assert!(cache_filename(Provider::SodexoJson, "sodexo", "fi").ends_with(".json"));
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("compass-lunch-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn falls_back_to_legacy_cache_file() {
+        let dir = test_dir("legacy");
+        fs::create_dir_all(&dir).unwrap();
+
+        let legacy = legacy_cache_path(&dir, Provider::Compass, "0437", "en");
+        fs::write(&legacy, "{}").unwrap();
+        assert_eq!(
+            read_cache_in(&dir, Provider::Compass, "0437", "en").as_deref(),
+            Some("{}")
+        );
+        assert!(cache_mtime_ms_in(&dir, Provider::Compass, "0437", "en").is_some());
+        assert!(read_cache_in(&dir, Provider::Compass, "0439", "en").is_none());
+
+        write_cache_in(&dir, Provider::Compass, "0437", "en", "[]").unwrap();
+        assert_eq!(
+            read_cache_in(&dir, Provider::Compass, "0437", "en").as_deref(),
+            Some("[]")
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn writes_reads_and_clears_cache_meta() {
+        let dir = test_dir("meta");
+        let meta = CacheMeta {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some("Tue, 06 May 2025 08:00:00 GMT".to_string()),
+        };
+        write_cache_meta_in(&dir, Provider::Compass, "0437", "fi", &meta).unwrap();
+        assert_eq!(
+            read_cache_meta_in(&dir, Provider::Compass, "0437", "fi"),
+            meta
+        );
+        clear_cache_meta_in(&dir, Provider::Compass, "0437", "fi");
+        assert_eq!(
+            read_cache_meta_in(&dir, Provider::Compass, "0437", "fi"),
+            CacheMeta::default()
+        );
+
+        write_cache_meta_in(&dir, Provider::Compass, "0437", "fi", &meta).unwrap();
+        write_cache_meta_in(&dir, Provider::Compass, "0437", "fi", &CacheMeta::default()).unwrap();
+        assert!(!meta_path(&dir, Provider::Compass, "0437", "fi").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keeps_dated_copies_for_keep_days() {
+        let dir = test_dir("dated");
+        let day = |d: u8| Date::from_calendar_date(2026, Month::October, d).unwrap();
+        for d in [10, 14, 16] {
+            let payload = format!("day {}", d);
+            write_dated_cache_in(&dir, Provider::Compass, "0437", "fi", day(d), &payload, 5)
+                .unwrap();
+        }
+        write_dated_cache_in(&dir, Provider::Compass, "0436", "fi", day(1), "other", 5).unwrap();
+        let read = |code: &str, date: &str| {
+            read_cache_for_date_in(&dir, Provider::Compass, code, "fi", date)
+        };
+        assert!(read("0437", "2026-10-10").is_none());
+        assert_eq!(read("0437", "2026-10-14").as_deref(), Some("day 14"));
+        assert!(read("0437", "2026-10-16").is_some());
+        assert!(read("0436", "2026-10-01").is_some());
+
+        write_dated_cache_in(&dir, Provider::Compass, "0438", "fi", day(16), "off", 0).unwrap();
+        assert!(read("0438", "2026-10-16").is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn overwrites_cache_without_leaving_temp_files() {
+        let dir = test_dir("atomic");
+        write_cache_in(&dir, Provider::CompassRss, "snellari-rss", "fi", "<rss/>").unwrap();
+        assert_eq!(
+            read_cache_in(&dir, Provider::CompassRss, "snellari-rss", "fi").as_deref(),
+            Some("<rss/>")
+        );
+        assert!(cache_mtime_ms_in(&dir, Provider::CompassRss, "snellari-rss", "fi").is_some());
+        write_cache_in(
+            &dir,
+            Provider::CompassRss,
+            "snellari-rss",
+            "fi",
+            "<rss></rss>",
+        )
+        .unwrap();
+        assert_eq!(
+            read_cache_in(&dir, Provider::CompassRss, "snellari-rss", "fi").as_deref(),
+            Some("<rss></rss>")
+        );
+        let names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["compass-rss__snellari-rss__fi.xml".to_string()]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn prunes_old_and_superseded_files() {
+        let dir = std::env::temp_dir().join(format!("compass-lunch-prune-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(40 * 86_400);
+        let write = |name: &str, modified: Option<std::time::SystemTime>| {
+            let path = dir.join(name);
+            fs::write(&path, "x").unwrap();
+            if let Some(modified) = modified {
+                fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .set_modified(modified)
+                    .unwrap();
+            }
+        };
+        write("compass__0436__fi.json", Some(old));
+        write("compass__0436__fi.meta.json", None);
+        write("compass__0437__fi.meta.json", Some(old));
+        write("compass__0437__fi.json", None);
+        write("compass|0437|fi.json", None);
+        write("compass|0439|fi.json", None);
+
+        let month = std::time::Duration::from_secs(30 * 86_400);
+        assert_eq!(prune_dir(&dir, month, month), 3);
+        assert!(!dir.join("compass__0436__fi.meta.json").exists());
+        assert!(!dir.join("compass|0437|fi.json").exists());
+        assert!(dir.join("compass__0437__fi.json").exists());
+        assert!(dir.join("compass__0437__fi.meta.json").exists());
+        assert!(dir.join("compass|0439|fi.json").exists());
+        assert_eq!(
+            modern_name_for_legacy("compass-rss|snellari rss|fi.xml").as_deref(),
+            Some("compass-rss__snellari_rss__fi.xml")
+        );
+        assert_eq!(modern_name_for_legacy("compass__0437__fi.json"), None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn prune_keeps_history_for_keep_history_days() {
+        let dir =
+            std::env::temp_dir().join(format!("compass-lunch-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let days_ago = |days: u64| {
+            std::time::SystemTime::now() - std::time::Duration::from_secs(days * 86_400)
+        };
+        let write = |name: &str, modified: std::time::SystemTime| {
+            let path = dir.join(name);
+            fs::write(&path, "x").unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        write("compass__0437__fi.json", days_ago(1));
+        write("compass__0437__fi_2026-08-20.json", days_ago(40));
+        write("compass__0437__fi_2026-06-01.json", days_ago(100));
+        write("history__compass__0437__fi.json", days_ago(40));
+        write("compass__0439__fi.meta.json", days_ago(1));
+
+        let month = std::time::Duration::from_secs(30 * 86_400);
+        let quarter = std::time::Duration::from_secs(90 * 86_400);
+        assert_eq!(prune_dir(&dir, month, quarter), 2);
+        assert!(dir.join("compass__0437__fi.json").exists());
+        assert!(dir.join("compass__0437__fi_2026-08-20.json").exists());
+        assert!(dir.join("history__compass__0437__fi.json").exists());
+        assert!(!dir.join("compass__0437__fi_2026-06-01.json").exists());
+        assert!(!dir.join("compass__0439__fi.meta.json").exists());
+        assert!(is_history_file("antell__antell-round__fi_2026-10-16.html"));
+        assert!(!is_history_file("compass__snellari_rss__fi.xml"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::util::to_wstring;
+use std::backtrace::Backtrace;
+use std::fs::{create_dir_all, File};
+use std::io::Write;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::thread::{self, ThreadId};
+use std::time::{SystemTime, UNIX_EPOCH};
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_ICONERROR, MB_OK};
+
+pub fn install_panic_hook() {
+    let main_thread = thread::current().id();
+    std::panic::set_hook(Box::new(move |info| handle_panic(info, main_thread)));
+}
+
+fn handle_panic(info: &PanicHookInfo<'_>, main_thread: ThreadId) {
+    let report_path = write_crash_report(info);
+    if thread::current().id() == main_thread {
+        show_crash_message(report_path);
+    }
+}
+
+fn crash_dir() -> PathBuf {
+    let base = std::env::var("LOCALAPPDATA").unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(base).join("compass-lunch")
+}
+
+fn write_crash_report(info: &PanicHookInfo<'_>) -> Option<PathBuf> {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let dir = crash_dir();
+    create_dir_all(&dir).ok()?;
+    let path = dir.join(format!("crash-{}.log", ts));
+    let mut file = File::create(&path).ok()?;
+
+    let thread = thread::current();
+    let _ = writeln!(file, "compass-lunch {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(file, "time: {}", ts);
+    let _ = writeln!(file, "thread: {}", thread.name().unwrap_or("<unnamed>"));
+    let _ = writeln!(file, "panic: {}", info);
+    let _ = writeln!(file);
+    let _ = writeln!(file, "{}", Backtrace::force_capture());
+    Some(path)
+}
+
+fn show_crash_message(report_path: Option<PathBuf>) {
+    let text = match report_path {
+        Some(path) => format!(
+            "Compass Lunch crashed.\n\nA crash report was written to:\n{}",
+            path.display()
+        ),
+        None => "Compass Lunch crashed, and the crash report could not be written.".to_string(),
+    };
+    let text = to_wstring(&text);
+    let caption = to_wstring("Compass Lunch");
+    unsafe {
+        MessageBoxW(
+            HWND(0),
+            PCWSTR(text.as_ptr()),
+            PCWSTR(caption.as_ptr()),
+            MB_OK | MB_ICONERROR,
+        );
+    }
+}
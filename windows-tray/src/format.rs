@@ -1,5 +1,7 @@
-use crate::model::{MenuGroup, TodayMenu};
+use crate::model::{MenuGroup, PriceEntry, PriceGroup, TodayMenu};
 use crate::restaurant::Provider;
+use crate::util::{local_date_key, local_now};
+use time::{Date, Month, OffsetDateTime, Weekday};
 
 #[derive(Debug, Clone, Copy)]
 pub struct PriceGroups {
@@ -8,24 +10,13 @@ pub struct PriceGroups {
     pub guest: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum PriceGroup {
-    Student,
-    Staff,
-    Guest,
-}
-
-#[derive(Debug, Clone)]
-struct PriceEntry {
-    group: PriceGroup,
-    text: String,
-    value: Option<f32>,
-}
-
 pub fn normalize_text(value: &str) -> String {
     let mut out = String::new();
     let mut last_was_space = false;
     for ch in value.chars() {
+        if is_zero_width(ch) {
+            continue;
+        }
         let is_space = ch.is_whitespace();
         if is_space {
             if !last_was_space {
@@ -40,6 +31,36 @@ pub fn normalize_text(value: &str) -> String {
     out.trim().to_string()
 }
 
+fn is_zero_width(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
+    )
+}
+
+/// Base direction per the first strong character: true when it is right-to-left (Hebrew,
+/// Arabic and related scripts, or an RLM), false for left-to-right or no strong character.
+pub fn is_rtl_text(text: &str) -> bool {
+    for ch in text.chars() {
+        match ch {
+            '\u{200F}' => return true,
+            '\u{200E}' => return false,
+            '\u{0590}'..='\u{08FF}'
+            | '\u{FB1D}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFE}'
+            | '\u{10800}'..='\u{10FFF}'
+            | '\u{1E800}'..='\u{1EFFF}'
+                if ch.is_alphabetic() =>
+            {
+                return true;
+            }
+            _ if ch.is_alphabetic() => return false,
+            _ => {}
+        }
+    }
+    false
+}
+
 pub fn normalize_optional(value: Option<&str>) -> String {
     match value {
         Some(v) => normalize_text(v),
@@ -62,7 +83,7 @@ pub fn format_display_date(date_iso: &str, language: &str) -> String {
         Ok(d) => d,
         Err(_) => return iso,
     };
-    if language == "fi" {
+    if language == "fi" || language == "sv" {
         return format!("{}.{}.{}", day, month, year);
     }
     format!("{}/{}/{}", month, day, year)
@@ -84,27 +105,400 @@ pub fn date_and_time_line(today_menu: Option<&TodayMenu>, language: &str) -> Str
     }
 }
 
+pub fn week_number_label(menu: Option<&TodayMenu>, language: &str) -> String {
+    let date = menu
+        .and_then(|menu| parse_date_iso(&menu.date_iso))
+        .unwrap_or_else(|| {
+            let now = local_now();
+            now.date()
+        });
+    week_number_label_for(date, language)
+}
+
+/// "Lunch starts/ends in N min" for a `lunch_time` like "10:30-13:00", given minutes since
+/// midnight. None once lunch is over or when the range can't be parsed.
+pub fn lunch_countdown_text(lunch_time: &str, now_minutes: i32, language: &str) -> Option<String> {
+    let normalized = normalize_text(lunch_time).replace(['–', '—'], "-");
+    let (start, end) = normalized.split_once('-')?;
+    let start = parse_clock_minutes(start)?;
+    let end = parse_clock_minutes(end)?;
+    if end <= start {
+        return None;
+    }
+    let (key, minutes) = if now_minutes < start {
+        ("lunchStartsIn", start - now_minutes)
+    } else if now_minutes < end {
+        ("lunchEndsIn", end - now_minutes)
+    } else {
+        return None;
+    };
+    Some(text_for(language, key).replace("{}", &minutes.to_string()))
+}
+
+/// Footer text for when the shown menu was fetched: "N min ago" within the hour, otherwise the
+/// clock time, prefixed with the date when it was not today. Both times are local.
+pub fn updated_text(updated: OffsetDateTime, now: OffsetDateTime, language: &str) -> String {
+    let minutes = (now - updated).whole_minutes();
+    if (0..60).contains(&minutes) {
+        return text_for(language, "updatedMinutesAgo").replace("{}", &minutes.to_string());
+    }
+    let clock = format!("{:02}:{:02}", updated.hour(), updated.minute());
+    let when = if updated.date() == now.date() {
+        clock
+    } else {
+        format!("{}.{}. {}", updated.day(), updated.month() as u8, clock)
+    };
+    text_for(language, "updatedAt").replace("{}", &when)
+}
+
+pub fn parse_clock_minutes(value: &str) -> Option<i32> {
+    let value = value.trim();
+    let (hours, minutes) = value
+        .split_once(':')
+        .or_else(|| value.split_once('.'))
+        .unwrap_or((value, "0"));
+    let hours = hours.trim().parse::<i32>().ok()?;
+    let minutes = minutes.trim().parse::<i32>().ok()?;
+    if !(0..24).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+    Some(hours * 60 + minutes)
+}
+
+fn parse_date_iso(date_iso: &str) -> Option<Date> {
+    let mut parts = date_iso.trim().splitn(3, '-');
+    let year = parts.next()?.parse::<i32>().ok()?;
+    let month = Month::try_from(parts.next()?.parse::<u8>().ok()?).ok()?;
+    let day = parts.next()?.parse::<u8>().ok()?;
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+fn week_number_label_for(date: Date, language: &str) -> String {
+    format!("{} {}", text_for(language, "week"), date.iso_week())
+}
+
+pub fn weekday_initial(weekday: Weekday, language: &str) -> char {
+    match (language, weekday) {
+        (_, Weekday::Monday) => 'M',
+        (_, Weekday::Tuesday) => 'T',
+        ("fi", Weekday::Wednesday) => 'K',
+        ("sv", Weekday::Wednesday) => 'O',
+        (_, Weekday::Wednesday) => 'W',
+        (_, Weekday::Thursday) => 'T',
+        ("fi", Weekday::Friday) => 'P',
+        (_, Weekday::Friday) => 'F',
+        ("fi" | "sv", Weekday::Saturday) => 'L',
+        (_, Weekday::Saturday) => 'S',
+        (_, Weekday::Sunday) => 'S',
+    }
+}
+
 pub fn text_for(language: &str, key: &str) -> String {
-    if language == "fi" {
-        match key {
+    match language {
+        "fi" => match key {
             "loading" => "Ladataan ruokalistaa...".to_string(),
             "noMenu" => "Tälle päivälle ei ole lounaslistaa.".to_string(),
             "stale" => "Päivitys epäonnistui. Näytetään viimeisin tallennettu lista.".to_string(),
             "staleNetwork" => {
                 "Ei verkkoyhteyttä. Näytetään viimeisin tallennettu lista.".to_string()
             }
+            "staleTimeout" => "Näytetään tallennettu lista (päivitys aikakatkaistiin).".to_string(),
             "fetchError" => "Päivitysvirhe".to_string(),
+            "noMenuOffline" => "Ei verkkoyhteyttä eikä tallennettua listaa.".to_string(),
+            "noMenuTimeout" => {
+                "Päivitys aikakatkaistiin, eikä tallennettua listaa ole.".to_string()
+            }
+            "noMenuUpdateFailed" => {
+                "Päivitys epäonnistui, eikä tallennettua listaa ole.".to_string()
+            }
+            "week" => "Viikko".to_string(),
+            "sameAsYesterday" => "Sama lista kuin edellisenä päivänä.".to_string(),
+            "fridayMenu" => "(Perjantain lista)".to_string(),
+            "updatedMinutesAgo" => "Päivitetty {} min sitten".to_string(),
+            "updatedAt" => "Päivitetty {}".to_string(),
+            "justRefreshed" => "Päivitetty juuri".to_string(),
+            "noMenuForDate" => "Valitulle päivälle ei ole lounaslistaa.".to_string(),
+            "closed" => "Suljettu.".to_string(),
+            "closedToday" => "Ravintola on suljettu tänään.".to_string(),
+            "lunchStartsIn" => "Lounas alkaa {} min päästä".to_string(),
+            "favoriteToday" => "Suosikki tänään".to_string(),
+            "lunchEndsIn" => "Lounas päättyy {} min päästä".to_string(),
+            "allergenLegend" => "Merkinnät".to_string(),
+            "allergenG" => "gluteeniton".to_string(),
+            "allergenL" => "laktoositon".to_string(),
+            "allergenVL" => "vähälaktoosinen".to_string(),
+            "allergenM" => "maidoton".to_string(),
+            "allergenVEG" => "vegaaninen".to_string(),
+            "allergenVS" => "sisältää tuoretta valkosipulia".to_string(),
+            "allergenA" => "sisältää allergeeneja".to_string(),
+            "allergenILM" => "ilmastoystävällinen".to_string(),
+            "lunch" => "Lounas".to_string(),
+            "menuRestaurant" => "Ravintola".to_string(),
+            "menuLanguage" => "Kieli".to_string(),
+            "menuLanguageAuto" => "Automaattinen ({})".to_string(),
+            "languageNameFi" => "suomi".to_string(),
+            "languageNameSv" => "ruotsi".to_string(),
+            "languageNameEn" => "englanti".to_string(),
+            "menuShowPrices" => "Näytä hinnat".to_string(),
+            "menuPriceColumn" => "Tasaa hinnat sarakkeeseen".to_string(),
+            "menuPriceGroups" => "Hintaryhmät".to_string(),
+            "menuPriceStudent" => "Opiskelija".to_string(),
+            "menuPriceStaff" => "Henkilökunta".to_string(),
+            "menuPriceGuest" => "Vierailija".to_string(),
+            "menuHideExpensive" => "Piilota kalliit opiskelija-ateriat".to_string(),
+            "menuThresholdOff" => "Pois".to_string(),
+            "menuThresholdOver" => "Yli {}".to_string(),
+            "menuShowAllergens" => "Näytä allergeenit".to_string(),
+            "menuHighlightAllergens" => "Korosta allergeenit".to_string(),
+            "menuShowWeekNumber" => "Näytä viikkonumero".to_string(),
+            "menuShowStale" => "Näytä vanhentuneen listan merkki".to_string(),
+            "menuReverseOrder" => "Käännä listan järjestys".to_string(),
+            "menuHeadingsBelow" => "Otsikot ruokien alla".to_string(),
+            "menuGrowDown" => "Laajenna ikkunaa alaspäin".to_string(),
+            "menuPopupAnchor" => "Ikkunan sijainti".to_string(),
+            "menuAnchorTray" => "Ilmoitusalueen kuvakkeen vieressä".to_string(),
+            "menuAnchorCursor" => "Osoittimen vieressä".to_string(),
+            "menuAnchorTopRight" => "Oikeassa yläkulmassa".to_string(),
+            "menuAnchorBottomRight" => "Oikeassa alakulmassa".to_string(),
+            "menuFlagRepeated" => "Merkitse toistuvat listat".to_string(),
+            "menuOpenOnHover" => "Avaa osoittamalla".to_string(),
+            "menuDailyNotification" => "Päivittäinen ilmoitus".to_string(),
+            "menuLunchCountdown" => "Lounaslaskuri".to_string(),
+            "menuRefreshOnClick" => "Päivitä klikkauksella avattaessa".to_string(),
+            "menuRefreshOnHover" => "Päivitä osoittamalla avattaessa".to_string(),
+            "menuAlternateNames" => "Näytä ravintoloiden vaihtoehtoiset nimet".to_string(),
+            "menuShowEnergy" => "Näytä energia (kcal)".to_string(),
+            "menuHighlightFavorites" => "Korosta suosikit".to_string(),
+            "menuMicroMode" => "Minitila (kokeellinen)".to_string(),
+            "menuAllergenLegend" => "Merkintöjen selitteet".to_string(),
+            "menuAllergenIcons" => "Merkinnät symboleina".to_string(),
+            "menuSwapClicks" => "Vaihda vasen/oikea klikkaus".to_string(),
+            "menuInvertWheel" => "Käännä rullan suunta".to_string(),
+            "menuCloseOnOpenUrl" => "Sulje ikkuna linkin avaamisen jälkeen".to_string(),
+            "menuPinPopup" => "Kiinnitä ikkuna".to_string(),
+            "menuTheme" => "Teema".to_string(),
+            "menuThemeLight" => "Vaalea".to_string(),
+            "menuThemeDark" => "Tumma".to_string(),
+            "menuThemeBlue" => "Sininen".to_string(),
+            "menuThemeGreen" => "Vihreä".to_string(),
+            "menuThemeCustom" => "Oma".to_string(),
+            "menuRunAtStartup" => "Käynnistä Windowsin mukana".to_string(),
+            "menuDeveloper" => "Kehittäjä".to_string(),
+            "menuEnableLogging" => "Ota lokitus käyttöön".to_string(),
+            "menuOpenAppData" => "Avaa sovelluksen datakansio".to_string(),
+            "menuCopyRawPayload" => "Kopioi raakadata".to_string(),
+            "menuTeletextFullscreen" => "Teksti-TV koko näytölle".to_string(),
+            "menuRefreshNow" => "Päivitä nyt".to_string(),
+            "menuAutoRefresh" => "Automaattinen päivitys".to_string(),
+            "menuRefreshOff" => "Pois".to_string(),
+            "menuMinutes" => "{} minuuttia".to_string(),
+            "menuMinutesShort" => "{} min".to_string(),
+            "menuRefreshCustom" => "Muu ({})".to_string(),
+            "menuAutoRefreshEvery" => "Automaattinen päivitys: {} min välein".to_string(),
+            "menuAutoRefreshStatusOff" => "Automaattinen päivitys: pois".to_string(),
+            "menuQuit" => "Lopeta".to_string(),
             _ => key.to_string(),
-        }
-    } else {
-        match key {
+        },
+        "sv" => match key {
+            "loading" => "Laddar menyn...".to_string(),
+            "noMenu" => "Ingen lunchmeny för i dag.".to_string(),
+            "stale" => "Uppdateringen misslyckades. Visar senast sparade meny.".to_string(),
+            "staleNetwork" => "Offline. Visar senast sparade meny.".to_string(),
+            "staleTimeout" => "Visar sparad meny (uppdateringen tog för lång tid).".to_string(),
+            "fetchError" => "Uppdateringsfel".to_string(),
+            "noMenuOffline" => "Offline och ingen sparad meny.".to_string(),
+            "noMenuTimeout" => {
+                "Uppdateringen tog för lång tid och ingen meny är sparad.".to_string()
+            }
+            "noMenuUpdateFailed" => {
+                "Uppdateringen misslyckades och ingen meny är sparad.".to_string()
+            }
+            "week" => "Vecka".to_string(),
+            "sameAsYesterday" => "Samma meny som i går.".to_string(),
+            "fridayMenu" => "(Fredagens meny)".to_string(),
+            "updatedMinutesAgo" => "Uppdaterad för {} min sedan".to_string(),
+            "updatedAt" => "Uppdaterad {}".to_string(),
+            "justRefreshed" => "Nyss uppdaterad".to_string(),
+            "noMenuForDate" => "Ingen lunchmeny för det datumet.".to_string(),
+            "closed" => "Stängt.".to_string(),
+            "closedToday" => "Restaurangen är stängd i dag.".to_string(),
+            "lunchStartsIn" => "Lunchen börjar om {} min".to_string(),
+            "favoriteToday" => "Favorit i dag".to_string(),
+            "lunchEndsIn" => "Lunchen slutar om {} min".to_string(),
+            "allergenLegend" => "Förklaringar".to_string(),
+            "allergenG" => "glutenfri".to_string(),
+            "allergenL" => "laktosfri".to_string(),
+            "allergenVL" => "laktosfattig".to_string(),
+            "allergenM" => "mjölkfri".to_string(),
+            "allergenVEG" => "vegansk".to_string(),
+            "allergenVS" => "innehåller färsk vitlök".to_string(),
+            "allergenA" => "innehåller allergener".to_string(),
+            "allergenILM" => "klimatvänlig".to_string(),
+            "lunch" => "Lunch".to_string(),
+            "menuRestaurant" => "Restaurang".to_string(),
+            "menuLanguage" => "Språk".to_string(),
+            "menuLanguageAuto" => "Automatiskt ({})".to_string(),
+            "languageNameFi" => "finska".to_string(),
+            "languageNameSv" => "svenska".to_string(),
+            "languageNameEn" => "engelska".to_string(),
+            "menuShowPrices" => "Visa priser".to_string(),
+            "menuPriceColumn" => "Justera priserna i en kolumn".to_string(),
+            "menuPriceGroups" => "Prisgrupper".to_string(),
+            "menuPriceStudent" => "Studerande".to_string(),
+            "menuPriceStaff" => "Personal".to_string(),
+            "menuPriceGuest" => "Gäst".to_string(),
+            "menuHideExpensive" => "Dölj dyra studentmåltider".to_string(),
+            "menuThresholdOff" => "Av".to_string(),
+            "menuThresholdOver" => "Över {}".to_string(),
+            "menuShowAllergens" => "Visa allergener".to_string(),
+            "menuHighlightAllergens" => "Markera allergener".to_string(),
+            "menuShowWeekNumber" => "Visa veckonummer".to_string(),
+            "menuShowStale" => "Visa markering för gammal meny".to_string(),
+            "menuReverseOrder" => "Omvänd menyordning".to_string(),
+            "menuHeadingsBelow" => "Rubriker under rätterna".to_string(),
+            "menuGrowDown" => "Väx fönstret nedåt".to_string(),
+            "menuPopupAnchor" => "Fönstrets position".to_string(),
+            "menuAnchorTray" => "Vid ikonen i meddelandefältet".to_string(),
+            "menuAnchorCursor" => "Vid muspekaren".to_string(),
+            "menuAnchorTopRight" => "Övre högra hörnet".to_string(),
+            "menuAnchorBottomRight" => "Nedre högra hörnet".to_string(),
+            "menuFlagRepeated" => "Markera upprepade menyer".to_string(),
+            "menuOpenOnHover" => "Öppna vid hovring".to_string(),
+            "menuDailyNotification" => "Daglig menyavisering".to_string(),
+            "menuLunchCountdown" => "Lunchnedräkning".to_string(),
+            "menuRefreshOnClick" => "Uppdatera vid öppning med klick".to_string(),
+            "menuRefreshOnHover" => "Uppdatera vid öppning med hovring".to_string(),
+            "menuAlternateNames" => "Visa alternativa restaurangnamn".to_string(),
+            "menuShowEnergy" => "Visa energi (kcal)".to_string(),
+            "menuHighlightFavorites" => "Markera favoriter".to_string(),
+            "menuMicroMode" => "Miniläge (experimentellt)".to_string(),
+            "menuAllergenLegend" => "Förklaring av märkningar".to_string(),
+            "menuAllergenIcons" => "Märkningar som symboler".to_string(),
+            "menuSwapClicks" => "Byt vänster/höger klick".to_string(),
+            "menuInvertWheel" => "Invertera scrollhjulet".to_string(),
+            "menuCloseOnOpenUrl" => "Stäng fönstret efter att länken öppnats".to_string(),
+            "menuPinPopup" => "Fäst fönstret".to_string(),
+            "menuTheme" => "Tema".to_string(),
+            "menuThemeLight" => "Ljust".to_string(),
+            "menuThemeDark" => "Mörkt".to_string(),
+            "menuThemeBlue" => "Blått".to_string(),
+            "menuThemeGreen" => "Grönt".to_string(),
+            "menuThemeCustom" => "Eget".to_string(),
+            "menuRunAtStartup" => "Starta med Windows".to_string(),
+            "menuDeveloper" => "Utvecklare".to_string(),
+            "menuEnableLogging" => "Aktivera loggning".to_string(),
+            "menuOpenAppData" => "Öppna appens datamapp".to_string(),
+            "menuCopyRawPayload" => "Kopiera rådata".to_string(),
+            "menuTeletextFullscreen" => "Text-TV i helskärm".to_string(),
+            "menuRefreshNow" => "Uppdatera nu".to_string(),
+            "menuAutoRefresh" => "Automatisk uppdatering".to_string(),
+            "menuRefreshOff" => "Av".to_string(),
+            "menuMinutes" => "{} minuter".to_string(),
+            "menuMinutesShort" => "{} min".to_string(),
+            "menuRefreshCustom" => "Annat ({})".to_string(),
+            "menuAutoRefreshEvery" => "Automatisk uppdatering: var {} min".to_string(),
+            "menuAutoRefreshStatusOff" => "Automatisk uppdatering: av".to_string(),
+            "menuQuit" => "Avsluta".to_string(),
+            _ => key.to_string(),
+        },
+        _ => match key {
             "loading" => "Loading menu...".to_string(),
             "noMenu" => "No lunch menu available for today.".to_string(),
             "stale" => "Update failed. Showing last cached menu.".to_string(),
             "staleNetwork" => "Offline. Showing last cached menu.".to_string(),
+            "staleTimeout" => "Showing cached menu (live update timed out).".to_string(),
             "fetchError" => "Fetch error".to_string(),
+            "noMenuOffline" => "Offline and no saved menu to show.".to_string(),
+            "noMenuTimeout" => "Update timed out and no saved menu to show.".to_string(),
+            "noMenuUpdateFailed" => "Update failed and no saved menu to show.".to_string(),
+            "week" => "Week".to_string(),
+            "sameAsYesterday" => "Same as yesterday.".to_string(),
+            "fridayMenu" => "(Friday's menu)".to_string(),
+            "updatedMinutesAgo" => "Updated {} min ago".to_string(),
+            "updatedAt" => "Updated {}".to_string(),
+            "justRefreshed" => "Just refreshed".to_string(),
+            "noMenuForDate" => "No lunch menu available for that date.".to_string(),
+            "closed" => "Closed.".to_string(),
+            "closedToday" => "The restaurant is closed today.".to_string(),
+            "lunchStartsIn" => "Lunch starts in {} min".to_string(),
+            "favoriteToday" => "Favorite today".to_string(),
+            "lunchEndsIn" => "Lunch ends in {} min".to_string(),
+            "allergenLegend" => "Legend".to_string(),
+            "allergenG" => "gluten-free".to_string(),
+            "allergenL" => "lactose-free".to_string(),
+            "allergenVL" => "low lactose".to_string(),
+            "allergenM" => "milk-free".to_string(),
+            "allergenVEG" => "vegan".to_string(),
+            "allergenVS" => "contains fresh garlic".to_string(),
+            "allergenA" => "contains allergens".to_string(),
+            "allergenILM" => "climate-friendly".to_string(),
+            "lunch" => "Lunch".to_string(),
+            "menuRestaurant" => "Restaurant".to_string(),
+            "menuLanguage" => "Language".to_string(),
+            "menuLanguageAuto" => "Automatic ({})".to_string(),
+            "languageNameFi" => "Finnish".to_string(),
+            "languageNameSv" => "Swedish".to_string(),
+            "languageNameEn" => "English".to_string(),
+            "menuShowPrices" => "Show prices".to_string(),
+            "menuPriceColumn" => "Align prices in a column".to_string(),
+            "menuPriceGroups" => "Price groups".to_string(),
+            "menuPriceStudent" => "Student".to_string(),
+            "menuPriceStaff" => "Staff".to_string(),
+            "menuPriceGuest" => "Guest".to_string(),
+            "menuHideExpensive" => "Hide expensive student meals".to_string(),
+            "menuThresholdOff" => "Off".to_string(),
+            "menuThresholdOver" => "Over {}".to_string(),
+            "menuShowAllergens" => "Show allergens".to_string(),
+            "menuHighlightAllergens" => "Highlight allergens".to_string(),
+            "menuShowWeekNumber" => "Show week number".to_string(),
+            "menuShowStale" => "Show stale marker".to_string(),
+            "menuReverseOrder" => "Reverse menu order".to_string(),
+            "menuHeadingsBelow" => "Headings below dishes".to_string(),
+            "menuGrowDown" => "Grow popup downward".to_string(),
+            "menuPopupAnchor" => "Popup position".to_string(),
+            "menuAnchorTray" => "Next to tray icon".to_string(),
+            "menuAnchorCursor" => "Next to cursor".to_string(),
+            "menuAnchorTopRight" => "Top-right corner".to_string(),
+            "menuAnchorBottomRight" => "Bottom-right corner".to_string(),
+            "menuFlagRepeated" => "Flag repeated menus".to_string(),
+            "menuOpenOnHover" => "Open on hover".to_string(),
+            "menuDailyNotification" => "Daily menu notification".to_string(),
+            "menuLunchCountdown" => "Lunch countdown".to_string(),
+            "menuRefreshOnClick" => "Refresh when opened by click".to_string(),
+            "menuRefreshOnHover" => "Refresh when opened by hover".to_string(),
+            "menuAlternateNames" => "Show alternate restaurant names".to_string(),
+            "menuShowEnergy" => "Show energy (kcal)".to_string(),
+            "menuHighlightFavorites" => "Highlight favorites".to_string(),
+            "menuMicroMode" => "Micro mode (experimental)".to_string(),
+            "menuAllergenLegend" => "Allergen legend".to_string(),
+            "menuAllergenIcons" => "Allergen icons".to_string(),
+            "menuSwapClicks" => "Swap left/right click".to_string(),
+            "menuInvertWheel" => "Invert mouse wheel".to_string(),
+            "menuCloseOnOpenUrl" => "Close popup after opening link".to_string(),
+            "menuPinPopup" => "Pin popup".to_string(),
+            "menuTheme" => "Theme".to_string(),
+            "menuThemeLight" => "Light".to_string(),
+            "menuThemeDark" => "Dark".to_string(),
+            "menuThemeBlue" => "Blue".to_string(),
+            "menuThemeGreen" => "Green".to_string(),
+            "menuThemeCustom" => "Custom".to_string(),
+            "menuRunAtStartup" => "Run at startup".to_string(),
+            "menuDeveloper" => "Developer".to_string(),
+            "menuEnableLogging" => "Enable logging".to_string(),
+            "menuOpenAppData" => "Open app data folder".to_string(),
+            "menuCopyRawPayload" => "Copy raw payload".to_string(),
+            "menuTeletextFullscreen" => "Teletext full screen".to_string(),
+            "menuRefreshNow" => "Refresh now".to_string(),
+            "menuAutoRefresh" => "Auto refresh".to_string(),
+            "menuRefreshOff" => "Off".to_string(),
+            "menuMinutes" => "{} minutes".to_string(),
+            "menuMinutesShort" => "{} min".to_string(),
+            "menuRefreshCustom" => "Custom ({})".to_string(),
+            "menuAutoRefreshEvery" => "Auto refresh: every {} min".to_string(),
+            "menuAutoRefreshStatusOff" => "Auto refresh: off".to_string(),
+            "menuQuit" => "Quit".to_string(),
             _ => key.to_string(),
-        }
+        },
     }
 }
 
@@ -114,35 +508,176 @@ pub fn menu_heading(
     show_prices: bool,
     groups: PriceGroups,
 ) -> String {
+    let (heading, price) = menu_heading_parts(menu, provider, show_prices, groups);
+    if price.is_empty() {
+        heading
+    } else {
+        format!("{} - {}", heading, price)
+    }
+}
+
+/// The group name and the price text `menu_heading` would append to it (empty when no price
+/// is shown), for layouts that place the price separately.
+pub fn menu_heading_parts(
+    menu: &MenuGroup,
+    provider: Provider,
+    show_prices: bool,
+    groups: PriceGroups,
+) -> (String, String) {
     let mut heading = normalize_text(&menu.name);
     if heading.is_empty() {
         heading = "Menu".to_string();
     }
     let price = normalize_text(&menu.price);
-    if show_prices && !price.is_empty() {
-        if provider == Provider::Compass {
-            let filtered = price_text_for_groups(&price, groups);
-            if filtered.is_empty() {
-                heading
-            } else {
-                format!("{} - {}", heading, filtered)
-            }
+    if !show_prices || price.is_empty() {
+        return (heading, String::new());
+    }
+    if provider == Provider::Compass {
+        (heading, price_text_for_groups(&menu.prices, groups))
+    } else {
+        (heading, price)
+    }
+}
+
+/// Single-letter chip label for micro mode: the group name's first letter, then the first
+/// dish's, then a bullet for groups with neither.
+pub fn group_chip_label(group: &MenuGroup) -> String {
+    let first_letter = |text: &str| {
+        normalize_text(text)
+            .chars()
+            .find(|ch| ch.is_alphanumeric())
+            .map(|ch| ch.to_uppercase().collect::<String>())
+    };
+    first_letter(&group.name)
+        .or_else(|| group.components.first().and_then(|dish| first_letter(dish)))
+        .unwrap_or_else(|| "•".to_string())
+}
+
+pub fn menu_signature(menu: &TodayMenu) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = menu
+        .menus
+        .iter()
+        .map(|group| {
+            let mut components: Vec<String> = group
+                .components
+                .iter()
+                .map(|component| normalize_text(component).to_lowercase())
+                .filter(|component| !component.is_empty())
+                .collect();
+            components.sort();
+            components.dedup();
+            components
+        })
+        .filter(|components| !components.is_empty())
+        .collect();
+    groups.sort();
+    groups
+}
+
+/// Whether `menu` is today's and the restaurant marked it closed, for the "closedToday" text.
+pub fn is_closed_today(menu: &TodayMenu) -> bool {
+    menu.closed && menu.date_iso == local_date_key()
+}
+
+pub fn is_starred_dish(starred_dishes: &[String], dish: &str) -> bool {
+    let key = normalize_text(dish).to_lowercase();
+    !key.is_empty()
+        && starred_dishes
+            .iter()
+            .any(|entry| normalize_text(entry).to_lowercase() == key)
+}
+
+/// "G = gluteeniton" style legend line, or None for codes without a description.
+pub fn allergen_legend_line(code: &str, language: &str) -> Option<String> {
+    let key = format!("allergen{}", code.to_uppercase());
+    let description = text_for(language, &key);
+    (description != key).then(|| format!("{} = {}", code, description))
+}
+
+/// Symbols for the `allergen_icons` mode. All of them are in Segoe UI Symbol.
+const ALLERGEN_ICONS: &[(&str, &str)] = &[
+    ("VEG", "\u{1F331}"),
+    ("G", "\u{1F33E}\u{20E0}"),
+    ("ILM", "\u{1F30D}"),
+];
+
+/// The symbol shown instead of an allergen code, or None to keep the letters.
+pub fn allergen_icon(code: &str) -> Option<&'static str> {
+    ALLERGEN_ICONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, icon)| *icon)
+}
+
+/// Whether a suffix segment is one of the `allergen_icon` symbols.
+pub fn is_allergen_icon(text: &str) -> bool {
+    ALLERGEN_ICONS.iter().any(|(_, icon)| *icon == text)
+}
+
+/// Display-time casing for headings and dishes: `title` capitalizes each word except short
+/// joining words, `sentence` only the first letter. Anything else leaves the text as is.
+pub fn apply_text_case(text: &str, text_case: &str, language: &str) -> String {
+    match text_case {
+        "title" => text
+            .split(' ')
+            .enumerate()
+            .map(|(index, word)| {
+                let lower = word.to_lowercase();
+                if index > 0 && is_small_word(&lower, language) {
+                    lower
+                } else {
+                    capitalize_first_letter(&lower)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        "sentence" => capitalize_first_letter(&text.to_lowercase()),
+        _ => text.to_string(),
+    }
+}
+
+fn is_small_word(word: &str, language: &str) -> bool {
+    let words: &[&str] = match language {
+        "fi" => &["ja", "tai", "sekä", "kera", "tms"],
+        "sv" => &["och", "eller", "med", "i", "på", "av"],
+        _ => &[
+            "and", "or", "with", "in", "on", "of", "the", "a", "an", "to",
+        ],
+    };
+    words.contains(&word)
+}
+
+fn capitalize_first_letter(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut done = false;
+    for ch in text.chars() {
+        if !done && ch.is_alphabetic() {
+            out.extend(ch.to_uppercase());
+            done = true;
         } else {
-            format!("{} - {}", heading, price)
+            out.push(ch);
         }
-    } else {
-        heading
     }
+    out
+}
+
+pub fn matches_favorite_keyword(favorite_keywords: &[String], dish: &str) -> bool {
+    let dish = normalize_text(dish).to_lowercase();
+    !dish.is_empty()
+        && favorite_keywords.iter().any(|keyword| {
+            let keyword = normalize_text(keyword).to_lowercase();
+            !keyword.is_empty() && dish.contains(&keyword)
+        })
 }
 
-pub fn split_component_suffix(component: &str) -> (String, String) {
+pub fn split_component_suffix(component: &str, language: &str) -> (String, String) {
     let text = normalize_text(component);
     if text.is_empty() {
         return (String::new(), String::new());
     }
     let mut main = text.trim().to_string();
-    let mut trailing_group_tokens = extract_trailing_parenthesized_allergens(&mut main);
-    let (inline_main, mut inline_tokens) = extract_inline_allergens(&main);
+    let mut trailing_group_tokens = extract_trailing_parenthesized_allergens(&mut main, language);
+    let (inline_main, mut inline_tokens) = extract_inline_allergens(&main, language);
     if !inline_tokens.is_empty() {
         main = inline_main;
     }
@@ -165,7 +700,7 @@ enum ParenthesizedGroup {
     Invalid,
 }
 
-fn extract_trailing_parenthesized_allergens(main: &mut String) -> Vec<String> {
+fn extract_trailing_parenthesized_allergens(main: &mut String, language: &str) -> Vec<String> {
     let mut groups_rev: Vec<Vec<String>> = Vec::new();
 
     loop {
@@ -180,7 +715,7 @@ fn extract_trailing_parenthesized_allergens(main: &mut String) -> Vec<String> {
         };
         let end = trimmed.len().saturating_sub(1);
         let inside = &trimmed[start + 1..end];
-        match parse_parenthesized_group_tokens(inside) {
+        match parse_parenthesized_group_tokens(inside, language) {
             ParenthesizedGroup::Tokens(tokens) => {
                 groups_rev.push(tokens);
                 *main = trimmed[..start].trim_end().to_string();
@@ -214,7 +749,7 @@ fn find_matching_open_paren(value: &str) -> Option<usize> {
     None
 }
 
-fn parse_parenthesized_group_tokens(raw_inside: &str) -> ParenthesizedGroup {
+fn parse_parenthesized_group_tokens(raw_inside: &str, language: &str) -> ParenthesizedGroup {
     let inside = normalize_text(raw_inside);
     if inside.is_empty() {
         return ParenthesizedGroup::Empty;
@@ -232,7 +767,7 @@ fn parse_parenthesized_group_tokens(raw_inside: &str) -> ParenthesizedGroup {
         if clean.is_empty() {
             continue;
         }
-        let Some(token) = normalize_allergen_token(&clean) else {
+        let Some(token) = normalize_allergen_token(&clean, language) else {
             return ParenthesizedGroup::Invalid;
         };
         tokens.push(token);
@@ -245,7 +780,7 @@ fn parse_parenthesized_group_tokens(raw_inside: &str) -> ParenthesizedGroup {
     }
 }
 
-fn extract_inline_allergens(text: &str) -> (String, Vec<String>) {
+fn extract_inline_allergens(text: &str, language: &str) -> (String, Vec<String>) {
     let compact = normalize_text(text)
         .trim_end_matches([' ', ',', ';', ':', '.'])
         .to_string();
@@ -265,7 +800,7 @@ fn extract_inline_allergens(text: &str) -> (String, Vec<String>) {
     let mut suffix_tokens = Vec::new();
     for idx in (0..parts.len()).rev() {
         let candidate = normalize_text(&parts[idx]);
-        let Some(token) = normalize_allergen_token(&candidate) else {
+        let Some(token) = normalize_allergen_token(&candidate, language) else {
             break;
         };
         suffix_tokens.insert(0, token);
@@ -280,7 +815,7 @@ fn extract_inline_allergens(text: &str) -> (String, Vec<String>) {
         return (compact, Vec::new());
     }
 
-    while let Some((next_main, token)) = peel_last_allergen_token(&main) {
+    while let Some((next_main, token)) = peel_last_allergen_token(&main, language) {
         if next_main.is_empty() {
             break;
         }
@@ -295,12 +830,12 @@ fn extract_inline_allergens(text: &str) -> (String, Vec<String>) {
     }
 }
 
-fn peel_last_allergen_token(text: &str) -> Option<(String, String)> {
+fn peel_last_allergen_token(text: &str, language: &str) -> Option<(String, String)> {
     let trimmed = text.trim_end();
     let split_idx = trimmed.rfind(|ch: char| ch.is_whitespace())?;
     let prefix = normalize_text(&trimmed[..split_idx]);
     let candidate = normalize_text(&trimmed[split_idx + 1..]);
-    let token = normalize_allergen_token(&candidate)?;
+    let token = normalize_allergen_token(&candidate, language)?;
     if prefix.is_empty() {
         None
     } else {
@@ -308,7 +843,7 @@ fn peel_last_allergen_token(text: &str) -> Option<(String, String)> {
     }
 }
 
-fn normalize_allergen_token(token: &str) -> Option<String> {
+fn normalize_allergen_token(token: &str, language: &str) -> Option<String> {
     let clean = normalize_text(token)
         .trim_matches(['(', ')', ',', ';', ':', '.'])
         .to_string();
@@ -324,6 +859,9 @@ fn normalize_allergen_token(token: &str) -> Option<String> {
         return Some(upper);
     }
 
+    if let Some(canonical) = allergen_alias(&upper, language) {
+        return Some(canonical.to_string());
+    }
     match upper.as_str() {
         "ILM" | "VS" | "VL" => Some(upper),
         "VEG" => Some("Veg".to_string()),
@@ -331,6 +869,22 @@ fn normalize_allergen_token(token: &str) -> Option<String> {
     }
 }
 
+/// English feeds spell some diet codes differently; map them onto the Finnish codes so
+/// highlighting and the allergen legend treat both feeds alike.
+fn allergen_alias(upper: &str, language: &str) -> Option<&'static str> {
+    if language != "en" {
+        return None;
+    }
+    match upper {
+        "GF" => Some("G"),
+        "LF" => Some("L"),
+        "LL" => Some("VL"),
+        "MF" | "DF" => Some("M"),
+        "VE" | "VEGAN" => Some("Veg"),
+        _ => None,
+    }
+}
+
 fn dedupe_tokens(tokens: Vec<String>) -> Vec<String> {
     let mut out = Vec::new();
     let mut seen: Vec<String> = Vec::new();
@@ -351,16 +905,38 @@ fn clean_main_text(main: &str) -> String {
         .to_string()
 }
 
-pub fn student_price_eur(price: &str) -> Option<f32> {
-    let entries = parse_compass_price_entries(price);
-    entries
-        .into_iter()
+pub fn student_price_eur(prices: &[PriceEntry]) -> Option<f32> {
+    prices
+        .iter()
         .find(|entry| entry.group == PriceGroup::Student)
         .and_then(|entry| entry.value)
 }
 
-fn price_text_for_groups(price: &str, groups: PriceGroups) -> String {
-    let entries = parse_compass_price_entries(price);
+/// `1 234,50 €` for fi/sv (no-break space groups), `€1,234.50` otherwise.
+pub fn format_eur(value: f32, language: &str) -> String {
+    let amount = format!("{:.2}", value);
+    let (whole, cents) = amount.split_once('.').unwrap_or((&amount, "00"));
+    let (sign, digits) = match whole.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", whole),
+    };
+    let nordic = language == "fi" || language == "sv";
+    let separator = if nordic { '\u{00A0}' } else { ',' };
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    if nordic {
+        format!("{}{},{} €", sign, grouped, cents)
+    } else {
+        format!("{}€{}.{}", sign, grouped, cents)
+    }
+}
+
+fn price_text_for_groups(entries: &[PriceEntry], groups: PriceGroups) -> String {
     let mut parts = Vec::new();
     for entry in entries {
         let include = match entry.group {
@@ -369,13 +945,13 @@ fn price_text_for_groups(price: &str, groups: PriceGroups) -> String {
             PriceGroup::Guest => groups.guest,
         };
         if include {
-            parts.push(entry.text);
+            parts.push(entry.text.as_str());
         }
     }
     parts.join(" / ")
 }
 
-fn parse_compass_price_entries(price: &str) -> Vec<PriceEntry> {
+pub fn parse_compass_price_entries(price: &str) -> Vec<PriceEntry> {
     let normalized = normalize_text(price);
     if normalized.is_empty() {
         return Vec::new();
@@ -384,18 +960,47 @@ fn parse_compass_price_entries(price: &str) -> Vec<PriceEntry> {
         .into_iter()
         .map(|segment| PriceEntry {
             group: classify_compass_price_group(&segment),
-            value: parse_price_value(&segment),
+            value: parse_price_value(split_price_unit(&segment).0),
             text: segment,
         })
         .collect()
 }
 
+fn split_price_unit(segment: &str) -> (&str, &str) {
+    match segment.rfind('/') {
+        Some(idx) if is_price_unit(&segment[idx + 1..]) => {
+            (segment[..idx].trim_end(), &segment[idx..])
+        }
+        _ => (segment, ""),
+    }
+}
+
+fn is_price_unit(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    let unit = lower
+        .trim()
+        .trim_start_matches(|ch: char| ch.is_ascii_digit() || ch == ',' || ch == '.')
+        .trim();
+    matches!(
+        unit,
+        "g" | "kg" | "dl" | "l" | "annos" | "portion" | "kpl" | "pc" | "pcs"
+    )
+}
+
 fn split_compass_price_segments(price: &str) -> Vec<String> {
-    let slash_segments: Vec<String> = price
-        .split('/')
-        .map(normalize_text)
-        .filter(|segment| !segment.is_empty())
-        .collect();
+    let mut slash_segments: Vec<String> = Vec::new();
+    for segment in price.split('/').map(normalize_text) {
+        if segment.is_empty() {
+            continue;
+        }
+        match slash_segments.last_mut() {
+            Some(previous) if is_price_unit(&segment) => {
+                previous.push('/');
+                previous.push_str(&segment);
+            }
+            _ => slash_segments.push(segment),
+        }
+    }
     if slash_segments.len() > 1 {
         return slash_segments;
     }
@@ -507,12 +1112,140 @@ fn parse_price_value(text: &str) -> Option<f32> {
 
 #[cfg(test)]
 mod tests {
-    use super::split_component_suffix;
+    use super::{
+        allergen_icon, allergen_legend_line, apply_text_case, format_display_date, format_eur,
+        group_chip_label, is_allergen_icon, is_rtl_text, is_starred_dish, lunch_countdown_text,
+        matches_favorite_keyword, menu_heading, menu_signature, normalize_text,
+        parse_compass_price_entries, split_component_suffix, student_price_eur, text_for,
+        updated_text, week_number_label_for, weekday_initial, PriceGroups,
+    };
+    use crate::model::{MenuGroup, PriceGroup, TodayMenu};
+    use crate::restaurant::Provider;
+    use time::{Date, Month, Weekday};
+
+    #[test]
+    fn menu_signature_ignores_order_case_and_blanks() {
+        let group = |dishes: &[&str]| MenuGroup {
+            name: "Lounas".to_string(),
+            price: String::new(),
+            components: dishes.iter().map(|dish| dish.to_string()).collect(),
+            energy: Vec::new(),
+            prices: Vec::new(),
+        };
+        let menu = |groups: Vec<MenuGroup>| TodayMenu {
+            date_iso: "2025-05-05".to_string(),
+            lunch_time: String::new(),
+            menus: groups,
+            closed: false,
+        };
+        let signature = menu_signature(&menu(vec![
+            group(&["Lohikeitto", "ruisleipä"]),
+            group(&["Pizza"]),
+        ]));
+        assert_eq!(
+            signature,
+            vec![
+                vec!["lohikeitto".to_string(), "ruisleipä".to_string()],
+                vec!["pizza".to_string()],
+            ]
+        );
+        assert_eq!(
+            menu_signature(&menu(vec![
+                group(&[" "]),
+                group(&["PIZZA"]),
+                group(&["Ruisleipä", "  Lohikeitto ", "lohikeitto"]),
+            ])),
+            signature
+        );
+        assert!(menu_signature(&menu(vec![group(&["", " "])])).is_empty());
+    }
+
+    #[test]
+    fn allergen_legend_skips_unknown_codes() {
+        assert_eq!(
+            allergen_legend_line("Veg", "fi").as_deref(),
+            Some("Veg = vegaaninen")
+        );
+        assert_eq!(
+            allergen_legend_line("G", "en").as_deref(),
+            Some("G = gluten-free")
+        );
+        assert_eq!(allergen_legend_line("XYZ", "sv"), None);
+    }
+
+    #[test]
+    fn allergen_icons_fall_back_to_letters() {
+        assert_eq!(allergen_icon("Veg"), Some("\u{1F331}"));
+        assert_eq!(allergen_icon("g"), Some("\u{1F33E}\u{20E0}"));
+        assert_eq!(allergen_icon("ILM"), Some("\u{1F30D}"));
+        assert_eq!(allergen_icon("L"), None);
+        assert_eq!(allergen_icon("VS"), None);
+        assert!(is_allergen_icon("\u{1F331}"));
+        assert!(!is_allergen_icon("Veg"));
+    }
+
+    #[test]
+    fn chip_label_falls_back_to_first_dish_then_bullet() {
+        let group = |name: &str, components: &[&str]| MenuGroup {
+            name: name.to_string(),
+            price: String::new(),
+            components: components.iter().map(|c| c.to_string()).collect(),
+            energy: Vec::new(),
+            prices: Vec::new(),
+        };
+        assert_eq!(group_chip_label(&group("  kasvislounas", &["Soup"])), "K");
+        assert_eq!(group_chip_label(&group("", &["äyriäiskeitto"])), "Ä");
+        assert_eq!(group_chip_label(&group("", &[])), "•");
+    }
+
+    #[test]
+    fn text_case_transforms_dish_names() {
+        let dish = "BROILERIA JA PIPPURI-RAKUUNAKASTIKETTA";
+        assert_eq!(apply_text_case(dish, "asis", "fi"), dish);
+        assert_eq!(
+            apply_text_case(dish, "title", "fi"),
+            "Broileria ja Pippuri-rakuunakastiketta"
+        );
+        assert_eq!(
+            apply_text_case(dish, "sentence", "fi"),
+            "Broileria ja pippuri-rakuunakastiketta"
+        );
+        assert_eq!(
+            apply_text_case("roasted TROUT with (lemon) sauce", "title", "en"),
+            "Roasted Trout with (Lemon) Sauce"
+        );
+        assert_eq!(
+            apply_text_case("ÄYRIÄISKEITTO", "sentence", "fi"),
+            "Äyriäiskeitto"
+        );
+        assert_eq!(apply_text_case("and chips", "title", "en"), "And Chips");
+    }
+
+    #[test]
+    fn favorite_keywords_match_case_insensitive_substrings() {
+        let keywords = vec!["Lohikeitto".to_string(), "  ".to_string()];
+        assert!(matches_favorite_keyword(
+            &keywords,
+            "Kermainen lohikeitto (L, G)"
+        ));
+        assert!(!matches_favorite_keyword(&keywords, "Broilerikastike"));
+        assert!(!matches_favorite_keyword(&[], "Lohikeitto"));
+    }
+
+    #[test]
+    fn starred_dishes_match_whole_names_ignoring_case_and_spacing() {
+        let starred = vec!["Kermainen  lohikeitto".to_string(), " ".to_string()];
+        assert!(is_starred_dish(&starred, "  KERMAINEN LOHIKEITTO "));
+        assert!(!is_starred_dish(&starred, "Lohikeitto"));
+        assert!(!is_starred_dish(&starred, "  "));
+        assert!(!is_starred_dish(&[], "Kermainen lohikeitto"));
+    }
 
     #[test]
     fn extracts_compass_suffix_with_parentheses() {
         let (main, suffix) = split_component_suffix(
             "Organic tofu and vegetables in teriyaki sauce (*, A, G, ILM, L, M, Veg, VS)",
+            "en",
         );
         assert_eq!(main, "Organic tofu and vegetables in teriyaki sauce");
         assert_eq!(suffix, "(*, A, G, ILM, L, M, Veg, VS)");
@@ -522,6 +1255,7 @@ mod tests {
     fn extracts_suffix_when_newline_precedes_parentheses() {
         let (main, suffix) = split_component_suffix(
             "Roasted rainbow trout in teriyaki sauce\n (*, A, G, ILM, L, M, VS)",
+            "en",
         );
         assert_eq!(main, "Roasted rainbow trout in teriyaki sauce");
         assert_eq!(suffix, "(*, A, G, ILM, L, M, VS)");
@@ -529,34 +1263,218 @@ mod tests {
 
     #[test]
     fn extracts_inline_suffix_without_parentheses() {
-        let (main, suffix) =
-            split_component_suffix("Chili and sesame-spiced organic tofu A, ILM, L, M, Veg, VS");
+        let (main, suffix) = split_component_suffix(
+            "Chili and sesame-spiced organic tofu A, ILM, L, M, Veg, VS",
+            "en",
+        );
         assert_eq!(main, "Chili and sesame-spiced organic tofu");
         assert_eq!(suffix, "(A, ILM, L, M, Veg, VS)");
     }
 
+    #[test]
+    fn normalizes_english_diet_codes_to_finnish_codes() {
+        let (main, suffix) = split_component_suffix("Lentil curry (GF, LF, MF, Vegan)", "en");
+        assert_eq!(main, "Lentil curry");
+        assert_eq!(suffix, "(G, L, M, Veg)");
+        let (main, suffix) = split_component_suffix("Fish soup, LL, gf", "en");
+        assert_eq!(main, "Fish soup");
+        assert_eq!(suffix, "(VL, G)");
+    }
+
+    #[test]
+    fn finnish_token_set_ignores_english_codes() {
+        let (main, suffix) = split_component_suffix("Kasviskeitto (G, VL, VEG)", "fi");
+        assert_eq!(main, "Kasviskeitto");
+        assert_eq!(suffix, "(G, VL, Veg)");
+        let (main, suffix) = split_component_suffix("Kasviskeitto (GF, LF)", "fi");
+        assert_eq!(main, "Kasviskeitto (GF, LF)");
+        assert_eq!(suffix, "");
+    }
+
     #[test]
     fn removes_empty_trailing_group_and_normalizes_spacing() {
-        let (main, suffix) = split_component_suffix("Juustoista pinaattikastiketta ( A, L) ()");
+        let (main, suffix) =
+            split_component_suffix("Juustoista pinaattikastiketta ( A, L) ()", "fi");
         assert_eq!(main, "Juustoista pinaattikastiketta");
         assert_eq!(suffix, "(A, L)");
     }
 
     #[test]
     fn keeps_non_allergen_tail_as_main_text() {
-        let (main, suffix) = split_component_suffix("Juusto, edam, viipale, sk ()");
+        let (main, suffix) = split_component_suffix("Juusto, edam, viipale, sk ()", "fi");
         assert_eq!(main, "Juusto, edam, viipale, sk");
         assert_eq!(suffix, "");
     }
 
     #[test]
     fn extracts_huomen_style_suffix_with_comma_in_main() {
-        let (main, suffix) =
-            split_component_suffix("Lihapullia, pippuri-rakuunakastiketta ja kermaperunaa (G, L)");
+        let (main, suffix) = split_component_suffix(
+            "Lihapullia, pippuri-rakuunakastiketta ja kermaperunaa (G, L)",
+            "fi",
+        );
         assert_eq!(
             main,
             "Lihapullia, pippuri-rakuunakastiketta ja kermaperunaa"
         );
         assert_eq!(suffix, "(G, L)");
     }
+
+    #[test]
+    fn formats_eur_with_comma_decimals_in_finnish() {
+        assert_eq!(format_eur(2.95, "fi"), "2,95 €");
+        assert_eq!(format_eur(12.0, "fi"), "12,00 €");
+    }
+
+    #[test]
+    fn formats_eur_with_dot_decimals_in_english() {
+        assert_eq!(format_eur(2.95, "en"), "€2.95");
+        assert_eq!(format_eur(7.5, "en"), "€7.50");
+        assert_eq!(format_eur(1234.5, "fi"), "1\u{00A0}234,50 €");
+        assert_eq!(format_eur(1234.5, "sv"), "1\u{00A0}234,50 €");
+        assert_eq!(format_eur(1234.5, "en"), "€1,234.50");
+        assert_eq!(format_eur(123456.0, "en"), "€123,456.00");
+        assert_eq!(format_eur(999.99, "fi"), "999,99 €");
+    }
+
+    #[test]
+    fn labels_iso_week_number_per_language() {
+        let date = Date::from_calendar_date(2025, Month::May, 6).unwrap();
+        assert_eq!(week_number_label_for(date, "fi"), "Viikko 19");
+        assert_eq!(week_number_label_for(date, "en"), "Week 19");
+        let new_year = Date::from_calendar_date(2027, Month::January, 1).unwrap();
+        assert_eq!(week_number_label_for(new_year, "en"), "Week 53");
+    }
+
+    #[test]
+    fn counts_down_to_lunch_start_and_end() {
+        assert_eq!(
+            lunch_countdown_text("10:30-13:00", 10 * 60, "en").as_deref(),
+            Some("Lunch starts in 30 min")
+        );
+        assert_eq!(
+            lunch_countdown_text("10.30–13.00", 12 * 60 + 18, "fi").as_deref(),
+            Some("Lounas päättyy 42 min päästä")
+        );
+        assert_eq!(lunch_countdown_text("10:30-13:00", 13 * 60, "en"), None);
+        assert_eq!(lunch_countdown_text("Lunch buffet", 11 * 60, "en"), None);
+    }
+
+    #[test]
+    fn describes_when_the_menu_was_updated() {
+        let at = |day: u8, hour: u8, minute: u8| {
+            Date::from_calendar_date(2026, Month::October, day)
+                .unwrap()
+                .with_hms(hour, minute, 0)
+                .unwrap()
+                .assume_utc()
+        };
+        let now = at(16, 14, 30);
+        assert_eq!(updated_text(at(16, 14, 5), now, "en"), "Updated 25 min ago");
+        assert_eq!(updated_text(at(16, 9, 5), now, "fi"), "Päivitetty 09:05");
+        assert_eq!(
+            updated_text(at(15, 16, 40), now, "en"),
+            "Updated 15.10. 16:40"
+        );
+    }
+
+    #[test]
+    fn localizes_weekday_initial_for_tray_icon() {
+        assert_eq!(weekday_initial(Weekday::Wednesday, "fi"), 'K');
+        assert_eq!(weekday_initial(Weekday::Wednesday, "sv"), 'O');
+        assert_eq!(weekday_initial(Weekday::Wednesday, "en"), 'W');
+        assert_eq!(weekday_initial(Weekday::Friday, "fi"), 'P');
+        assert_eq!(weekday_initial(Weekday::Saturday, "sv"), 'L');
+        assert_eq!(weekday_initial(Weekday::Saturday, "en"), 'S');
+    }
+
+    #[test]
+    fn normalizes_non_breaking_spaces_to_regular_spaces() {
+        assert_eq!(
+            normalize_text("Kasvis\u{00A0}\u{00A0}lasagne\u{202F}(L)"),
+            "Kasvis lasagne (L)"
+        );
+    }
+
+    #[test]
+    fn strips_zero_width_characters_and_bom() {
+        assert_eq!(
+            normalize_text("\u{FEFF}Broileri\u{200B}kastiketta \u{200B} ja riisiä\u{2060}"),
+            "Broilerikastiketta ja riisiä"
+        );
+    }
+
+    #[test]
+    fn keeps_per_100g_unit_in_menu_heading() {
+        let menu = MenuGroup {
+            name: "Buffet".to_string(),
+            price: "1,95 €/100g".to_string(),
+            components: Vec::new(),
+            energy: Vec::new(),
+            prices: parse_compass_price_entries("1,95 €/100g"),
+        };
+        let groups = PriceGroups {
+            student: true,
+            staff: true,
+            guest: true,
+        };
+        assert_eq!(
+            menu_heading(&menu, Provider::Compass, true, groups),
+            "Buffet - 1,95 €/100g"
+        );
+        assert_eq!(
+            student_price_eur(&parse_compass_price_entries("Opiskelija 1,95 €/100g")),
+            Some(1.95)
+        );
+    }
+
+    #[test]
+    fn groups_compass_prices_once_for_headings_and_filters() {
+        let price = "Opiskelija 2,95 / Henkilökunta 5,90";
+        let prices = parse_compass_price_entries(price);
+        let groups: Vec<_> = prices.iter().map(|entry| entry.group).collect();
+        assert_eq!(groups, [PriceGroup::Student, PriceGroup::Staff]);
+        assert_eq!(prices[0].text, "Opiskelija 2,95");
+        assert_eq!(prices[1].value, Some(5.9));
+        assert_eq!(student_price_eur(&prices), Some(2.95));
+
+        let menu = MenuGroup {
+            name: "Lounas".to_string(),
+            price: price.to_string(),
+            components: Vec::new(),
+            energy: Vec::new(),
+            prices,
+        };
+        let staff_only = PriceGroups {
+            student: false,
+            staff: true,
+            guest: false,
+        };
+        assert_eq!(
+            menu_heading(&menu, Provider::Compass, true, staff_only),
+            "Lounas - Henkilökunta 5,90"
+        );
+        assert_eq!(
+            menu_heading(&menu, Provider::Antell, true, staff_only),
+            "Lounas - Opiskelija 2,95 / Henkilökunta 5,90"
+        );
+        assert!(parse_compass_price_entries("").is_empty());
+    }
+
+    #[test]
+    fn swedish_uses_day_month_year_and_own_strings() {
+        assert_eq!(format_display_date("2025-05-06", "sv"), "6.5.2025");
+        assert_eq!(text_for("sv", "fetchError"), "Uppdateringsfel");
+        assert_eq!(format_eur(2.95, "sv"), "2,95 €");
+    }
+
+    #[test]
+    fn detects_base_direction_from_first_strong_character() {
+        assert!(!is_rtl_text("Lohikeitto (L, G)"));
+        assert!(is_rtl_text("שקשוקה"));
+        assert!(is_rtl_text("حمص مع خبز"));
+        assert!(is_rtl_text("12 - فلافل"));
+        assert!(!is_rtl_text("Falafel فلافل"));
+        assert!(is_rtl_text("\u{200F}Falafel"));
+        assert!(!is_rtl_text("12,50 €"));
+    }
 }
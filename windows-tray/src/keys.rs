@@ -0,0 +1,213 @@
+use crate::log::log_line;
+use crate::settings::hotkey_virtual_key;
+use std::collections::{BTreeMap, HashMap};
+
+pub const MOD_CTRL: u32 = 1;
+pub const MOD_SHIFT: u32 = 2;
+
+/// What a key pressed in the popup does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupAction {
+    Close,
+    CycleRestaurant(i32),
+    SelectRestaurant(usize),
+    CycleDay(i32),
+    PeekNextDay,
+    OpenUrl,
+    CopyMenu,
+    StarDish,
+    TogglePrices,
+    TogglePin,
+}
+
+/// Virtual key plus `MOD_*` bits.
+pub type KeyCombo = (u32, u32);
+
+/// The popup shortcuts: the defaults below, then the `popup_keys` entries from settings
+/// (`"Shift+P": "toggle_prices"`, or `"none"` to unbind a default). Keep the "Popup keys"
+/// list in README_windows.md in step with the defaults.
+pub fn popup_keybindings(overrides: &BTreeMap<String, String>) -> HashMap<KeyCombo, PopupAction> {
+    let mut bindings = HashMap::from([
+        ((0x1B, 0), PopupAction::Close),
+        ((0x25, 0), PopupAction::CycleRestaurant(-1)),
+        ((0x41, 0), PopupAction::CycleRestaurant(-1)),
+        ((0x27, 0), PopupAction::CycleRestaurant(1)),
+        ((0x44, 0), PopupAction::CycleRestaurant(1)),
+        ((0x26, 0), PopupAction::CycleDay(-1)),
+        ((0x28, 0), PopupAction::CycleDay(1)),
+        ((0x10, 0), PopupAction::PeekNextDay),
+        ((0x4F, 0), PopupAction::OpenUrl),
+        ((0x0D, MOD_CTRL), PopupAction::OpenUrl),
+        ((0x43, MOD_CTRL), PopupAction::CopyMenu),
+        ((0x50, 0), PopupAction::TogglePrices),
+        ((0x50, MOD_CTRL), PopupAction::TogglePin),
+        ((0x53, 0), PopupAction::StarDish),
+    ]);
+    for index in 0..9 {
+        let select = PopupAction::SelectRestaurant(index as usize);
+        bindings.insert((0x31 + index, 0), select);
+        bindings.insert((0x61 + index, 0), select);
+    }
+
+    for (combo, action) in overrides {
+        let Some(key) = parse_key_combo(combo) else {
+            log_line(&format!(
+                "popup_keys entry {:?} skipped: unknown key",
+                combo
+            ));
+            continue;
+        };
+        if action.eq_ignore_ascii_case("none") {
+            bindings.remove(&key);
+            continue;
+        }
+        match parse_popup_action(action) {
+            Some(action) => {
+                bindings.insert(key, action);
+            }
+            None => log_line(&format!(
+                "popup_keys entry {:?} skipped: unknown action {:?}",
+                combo, action
+            )),
+        }
+    }
+    bindings
+}
+
+/// The action bound to `key` with exactly `modifiers` held, else the one bound to the bare key,
+/// so e.g. Shift+Right still switches restaurant unless Shift+Right has its own binding.
+pub fn popup_action(
+    bindings: &HashMap<KeyCombo, PopupAction>,
+    key: u32,
+    modifiers: u32,
+) -> Option<PopupAction> {
+    bindings
+        .get(&(key, modifiers))
+        .or_else(|| bindings.get(&(key, 0)))
+        .copied()
+}
+
+/// Whether releasing `key` should end a next-day peek.
+pub fn is_peek_key(bindings: &HashMap<KeyCombo, PopupAction>, key: u32) -> bool {
+    bindings
+        .iter()
+        .any(|(&(bound, _), &action)| bound == key && action == PopupAction::PeekNextDay)
+}
+
+/// Parses "Ctrl+Enter", "Shift+Right", "P" or "F5". Unlike the global hotkey, no modifier is
+/// needed, and arrows, Esc, Enter, Tab and a bare Shift are accepted. Alt is left out: with it
+/// held the popup gets WM_SYSKEYDOWN, not WM_KEYDOWN.
+pub fn parse_key_combo(value: &str) -> Option<KeyCombo> {
+    let mut modifiers = 0;
+    let mut key = None;
+    for part in value.split('+').map(str::trim) {
+        let name = part.to_ascii_lowercase();
+        let named = match name.as_str() {
+            "left" => Some(0x25),
+            "up" => Some(0x26),
+            "right" => Some(0x27),
+            "down" => Some(0x28),
+            "esc" | "escape" => Some(0x1B),
+            "enter" | "return" => Some(0x0D),
+            "tab" => Some(0x09),
+            "space" => Some(0x20),
+            _ => None,
+        };
+        match name.as_str() {
+            "ctrl" | "control" => modifiers |= MOD_CTRL,
+            "shift" => modifiers |= MOD_SHIFT,
+            _ if key.is_some() => return None,
+            _ => key = Some(named.or_else(|| hotkey_virtual_key(&name))?),
+        }
+    }
+    match key {
+        Some(key) => Some((key, modifiers)),
+        // A lone "Shift" binds the Shift key itself, as the peek default does.
+        None if modifiers == MOD_SHIFT => Some((0x10, 0)),
+        None => None,
+    }
+}
+
+fn parse_popup_action(value: &str) -> Option<PopupAction> {
+    let value = value.to_ascii_lowercase();
+    if let Some(index) = value.strip_prefix("restaurant_") {
+        return match index.parse::<usize>().ok()? {
+            n @ 1..=9 => Some(PopupAction::SelectRestaurant(n - 1)),
+            _ => None,
+        };
+    }
+    let action = match value.as_str() {
+        "close" => PopupAction::Close,
+        "prev_restaurant" => PopupAction::CycleRestaurant(-1),
+        "next_restaurant" => PopupAction::CycleRestaurant(1),
+        "prev_day" => PopupAction::CycleDay(-1),
+        "next_day" => PopupAction::CycleDay(1),
+        "peek_next_day" => PopupAction::PeekNextDay,
+        "open_url" => PopupAction::OpenUrl,
+        "copy_menu" => PopupAction::CopyMenu,
+        "star_dish" => PopupAction::StarDish,
+        "toggle_prices" => PopupAction::TogglePrices,
+        "toggle_pin" => PopupAction::TogglePin,
+        _ => return None,
+    };
+    Some(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_keep_the_built_in_keys() {
+        let bindings = popup_keybindings(&BTreeMap::new());
+        let action = |key, modifiers| popup_action(&bindings, key, modifiers);
+        assert_eq!(action(0x50, 0), Some(PopupAction::TogglePrices));
+        assert_eq!(action(0x50, MOD_CTRL), Some(PopupAction::TogglePin));
+        assert_eq!(action(0x53, MOD_CTRL), Some(PopupAction::StarDish));
+        assert_eq!(action(0x43, MOD_CTRL), Some(PopupAction::CopyMenu));
+        assert_eq!(action(0x43, 0), None);
+        assert_eq!(action(0x0D, 0), None);
+        assert_eq!(action(0x0D, MOD_CTRL), Some(PopupAction::OpenUrl));
+        assert_eq!(action(0x33, 0), Some(PopupAction::SelectRestaurant(2)));
+        assert_eq!(action(0x63, 0), Some(PopupAction::SelectRestaurant(2)));
+        assert_eq!(action(0x25, MOD_SHIFT), action(0x41, 0));
+        assert!(is_peek_key(&bindings, 0x10));
+    }
+
+    #[test]
+    fn settings_rebind_and_unbind_keys() {
+        let overrides = BTreeMap::from([
+            ("Ctrl+P".to_string(), "toggle_prices".to_string()),
+            ("P".to_string(), "none".to_string()),
+            ("Tab".to_string(), "Next_Restaurant".to_string()),
+            ("Ctrl+0".to_string(), "restaurant_9".to_string()),
+            ("Shift+Right".to_string(), "next_day".to_string()),
+            ("Q".to_string(), "launch_rockets".to_string()),
+            ("Ctrl+Shift".to_string(), "close".to_string()),
+        ]);
+        let bindings = popup_keybindings(&overrides);
+        let action = |key, modifiers| popup_action(&bindings, key, modifiers);
+        assert_eq!(action(0x50, 0), None);
+        assert_eq!(action(0x50, MOD_CTRL), Some(PopupAction::TogglePrices));
+        assert_eq!(action(0x09, 0), Some(PopupAction::CycleRestaurant(1)));
+        assert_eq!(
+            action(0x30, MOD_CTRL),
+            Some(PopupAction::SelectRestaurant(8))
+        );
+        assert_eq!(action(0x27, MOD_SHIFT), Some(PopupAction::CycleDay(1)));
+        assert_eq!(action(0x27, 0), Some(PopupAction::CycleRestaurant(1)));
+        assert_eq!(action(0x51, 0), None);
+    }
+
+    #[test]
+    fn parses_popup_key_combos() {
+        assert_eq!(parse_key_combo("ctrl + enter"), Some((0x0D, MOD_CTRL)));
+        assert_eq!(parse_key_combo("Shift"), Some((0x10, 0)));
+        assert_eq!(parse_key_combo("F5"), Some((0x74, 0)));
+        assert_eq!(parse_key_combo("Esc"), Some((0x1B, 0)));
+        assert_eq!(parse_key_combo("P+Q"), None);
+        assert_eq!(parse_key_combo("Ctrl"), None);
+        assert_eq!(parse_key_combo("Alt+P"), None);
+        assert_eq!(parse_key_combo("PageDown"), None);
+    }
+}
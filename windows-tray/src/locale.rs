@@ -0,0 +1,31 @@
+use windows::Win32::Globalization::GetUserDefaultUILanguage;
+
+const LANG_FINNISH: u16 = 0x0b;
+const LANG_SWEDISH: u16 = 0x1d;
+
+pub fn system_language() -> String {
+    let lang_id = unsafe { GetUserDefaultUILanguage() };
+    language_for_lang_id(lang_id).to_string()
+}
+
+fn language_for_lang_id(lang_id: u16) -> &'static str {
+    match lang_id & 0x3ff {
+        LANG_FINNISH => "fi",
+        LANG_SWEDISH => "sv",
+        _ => "en",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::language_for_lang_id;
+
+    #[test]
+    fn lang_ids_map_to_supported_languages() {
+        assert_eq!(language_for_lang_id(0x040b), "fi");
+        assert_eq!(language_for_lang_id(0x081d), "sv");
+        assert_eq!(language_for_lang_id(0x041d), "sv");
+        assert_eq!(language_for_lang_id(0x0409), "en");
+        assert_eq!(language_for_lang_id(0x0407), "en");
+    }
+}
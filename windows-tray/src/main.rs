@@ -4,11 +4,15 @@ mod antell;
 mod api;
 mod app;
 mod cache;
+mod crash;
 mod format;
+mod keys;
+mod locale;
 mod log;
 mod model;
 mod popup;
 mod restaurant;
+mod serve;
 mod settings;
 mod startup;
 mod tray;
@@ -17,12 +21,13 @@ mod winmsg;
 
 use crate::app::App;
 use crate::format::{
-    date_and_time_line, menu_heading, normalize_text, split_component_suffix, student_price_eur,
-    text_for, PriceGroups,
+    date_and_time_line, is_closed_today, is_starred_dish, menu_heading, normalize_text,
+    split_component_suffix, student_price_eur, text_for, week_number_label, PriceGroups,
 };
-use crate::restaurant::{restaurant_for_code, Provider};
-use crate::settings::load_settings;
-use crate::util::to_wstring;
+use crate::model::TodayMenu;
+use crate::restaurant::{available_restaurants, restaurant_for_code, Provider};
+use crate::settings::{load_settings, menu_language};
+use crate::util::{local_date_key, to_wstring};
 use windows::core::PCWSTR;
 use windows::Win32::Foundation::HWND;
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
@@ -32,15 +37,35 @@ use windows::Win32::UI::WindowsAndMessaging::{
 };
 
 fn main() -> anyhow::Result<()> {
+    crash::install_panic_hook();
     let args: Vec<String> = std::env::args().collect();
     let print_today = args.iter().any(|a| a == "--print-today");
+    let print_week = args.iter().any(|a| a == "--week");
+    let print_all = args.iter().any(|a| a == "--print-all");
+    let print_json = args.iter().any(|a| a == "--json");
+    let print_date = args
+        .iter()
+        .find_map(|a| a.strip_prefix("--date="))
+        .map(str::to_string);
     let no_tray = args.iter().any(|a| a == "--no-tray");
     let boot_settings = load_settings();
     log::set_enabled(boot_settings.enable_logging);
+    let pruned = cache::prune(cache::CACHE_MAX_AGE_DAYS, boot_settings.keep_history_days);
+    if pruned > 0 {
+        log::log_line(&format!("cache prune removed {} files", pruned));
+    }
 
-    if print_today {
+    if print_all {
+        ensure_console();
+        return print_all_menus_with_settings(&boot_settings, print_json);
+    }
+    if print_week {
         ensure_console();
-        return print_today_menu_with_settings(&boot_settings);
+        return print_week_menu_with_settings(&boot_settings);
+    }
+    if print_date.is_some() || print_today {
+        ensure_console();
+        return print_date_menu_with_settings(&boot_settings, print_date.as_deref());
     }
 
     unsafe {
@@ -100,14 +125,28 @@ fn main() -> anyhow::Result<()> {
 
         let app = &*app_ptr;
         app.set_hwnds(tray_hwnd, popup_hwnd);
+        popup::apply_opacity_settings(popup_hwnd, &app.snapshot().settings);
         let _ = app.load_cache_for_current();
         winmsg::schedule_timers(tray_hwnd, app.refresh_minutes());
+        winmsg::schedule_lunch_reminder_timer(
+            tray_hwnd,
+            &app.snapshot().settings.lunch_reminder_time,
+        );
+        winmsg::register_hotkey(tray_hwnd, app.snapshot().settings.hotkey.as_deref());
+        app.start_menu_server();
         app.check_stale_date_and_refresh();
         app.start_refresh();
 
         if !no_tray {
-            match tray::add_tray_icon(tray_hwnd, winmsg::WM_TRAY_CALLBACK) {
-                Ok(()) => log::log_line("tray icon added"),
+            match tray::add_tray_icon(
+                tray_hwnd,
+                winmsg::WM_TRAY_CALLBACK,
+                &app.snapshot().settings,
+            ) {
+                Ok(()) => {
+                    log::log_line("tray icon added");
+                    winmsg::refresh_tray_tooltip(app);
+                }
                 Err(err) => {
                     log::log_line(&format!("tray icon add failed: {}", err));
                     return Err(err);
@@ -120,6 +159,7 @@ fn main() -> anyhow::Result<()> {
         if no_tray {
             let state = app.snapshot();
             popup::show_popup(popup_hwnd, &state);
+            winmsg::schedule_auto_rotate_timer(popup_hwnd, app, false);
         }
 
         let mut msg = MSG::default();
@@ -145,8 +185,11 @@ fn ensure_console() {
 #[cfg(not(target_os = "windows"))]
 fn ensure_console() {}
 
-fn print_today_menu_with_settings(settings: &crate::settings::Settings) -> anyhow::Result<()> {
-    let result = api::fetch_today(settings);
+fn print_date_menu_with_settings(
+    settings: &crate::settings::Settings,
+    date: Option<&str>,
+) -> anyhow::Result<()> {
+    let result = api::fetch_week(settings);
     if !result.ok {
         eprintln!(
             "{}: {}",
@@ -156,29 +199,160 @@ fn print_today_menu_with_settings(settings: &crate::settings::Settings) -> anyho
         return Ok(());
     }
 
-    let today_menu = result.today_menu;
-    let date_line = date_and_time_line(today_menu.as_ref(), &settings.language);
+    let Some(date) = date.map(str::trim) else {
+        print_day_menu(settings, result.today_menu.as_ref());
+        return Ok(());
+    };
+    let days = &result.week_menu.days;
+    let today = result
+        .today_menu
+        .as_ref()
+        .filter(|_| date == local_date_key());
+    let day = today
+        .or_else(|| days.iter().find(|day| day.date_iso == date))
+        .filter(|day| day.closed || !day.menus.is_empty())
+        .cloned()
+        .or_else(|| history_menu_for_date(settings, date));
+    match day {
+        Some(day) => print_day_menu(settings, Some(&day)),
+        None => {
+            eprintln!("{}", text_for(&settings.language, "noMenuForDate"));
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+/// Looks `date` up in the dated cache copies kept by `keep_history_days`.
+fn history_menu_for_date(settings: &crate::settings::Settings, date: &str) -> Option<TodayMenu> {
+    let restaurant = restaurant_for_code(&settings.restaurant_code, settings);
+    let language = menu_language(settings, &restaurant.code);
+    let (provider, raw) = cache::read_restaurant_cache_for_date(&restaurant, language, date)?;
+    let parsed = api::parse_cached_payload(&raw, provider, &restaurant, language).ok()?;
+    let mut day = parsed
+        .week_menu
+        .days
+        .into_iter()
+        .find(|day| day.date_iso == date)
+        .or(parsed.today_menu)?;
+    day.date_iso = date.to_string();
+    Some(day)
+}
+
+fn print_week_menu_with_settings(settings: &crate::settings::Settings) -> anyhow::Result<()> {
+    let result = api::fetch_week(settings);
+    if !result.ok {
+        eprintln!(
+            "{}: {}",
+            text_for(&settings.language, "fetchError"),
+            result.error_message
+        );
+        return Ok(());
+    }
+
+    if result.week_menu.days.is_empty() {
+        print_day_menu(settings, result.today_menu.as_ref());
+        return Ok(());
+    }
+    for (index, day) in result.week_menu.days.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        print_day_menu(settings, Some(day));
+    }
+    Ok(())
+}
+
+/// Prints today's menu for every enabled restaurant, sharing one HTTP client. A failed
+/// restaurant gets an error line (or an `error` field with `--json`) and the rest still print.
+fn print_all_menus_with_settings(
+    settings: &crate::settings::Settings,
+    json: bool,
+) -> anyhow::Result<()> {
+    let client = api::build_client(settings)?;
+    let mut entries = serde_json::Map::new();
+    for (index, restaurant) in available_restaurants(settings).into_iter().enumerate() {
+        let mut restaurant_settings = settings.clone();
+        restaurant_settings.restaurant_code = restaurant.code.to_string();
+        let result = api::fetch_week_with_client(&restaurant_settings, &client);
+
+        if json {
+            let entry = if result.ok {
+                serve::menu_json(&restaurant.name, result.today_menu.as_ref())
+            } else {
+                serde_json::json!({
+                    "name": restaurant.name,
+                    "error": result.error_message,
+                })
+            };
+            entries.insert(restaurant.code.to_string(), entry);
+            continue;
+        }
+
+        if index > 0 {
+            println!();
+        }
+        println!("{}", restaurant.name);
+        if result.ok {
+            print_day_menu(&restaurant_settings, result.today_menu.as_ref());
+        } else {
+            println!(
+                "{}: {}",
+                text_for(&settings.language, "fetchError"),
+                result.error_message
+            );
+        }
+    }
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::Value::Object(entries))?
+        );
+    }
+    Ok(())
+}
+
+fn print_day_menu(settings: &crate::settings::Settings, menu: Option<&TodayMenu>) {
+    let mut date_line = date_and_time_line(menu, &settings.language);
+    if settings.show_week_number && !date_line.is_empty() {
+        date_line = format!(
+            "{} · {}",
+            date_line,
+            week_number_label(menu, &settings.language)
+        );
+    }
     if !date_line.is_empty() {
         println!("{}", date_line);
     }
 
-    let provider = restaurant_for_code(
-        &settings.restaurant_code,
-        settings.enable_antell_restaurants,
-    )
-    .provider;
+    let provider = restaurant_for_code(&settings.restaurant_code, settings).provider;
+    let language = menu_language(settings, &settings.restaurant_code);
     let price_groups = PriceGroups {
         student: settings.show_student_price,
         staff: settings.show_staff_price,
         guest: settings.show_guest_price,
     };
-    match &today_menu {
+    match menu {
+        Some(menu) if menu.closed => {
+            let key = if is_closed_today(menu) {
+                "closedToday"
+            } else {
+                "closed"
+            };
+            println!("{}", text_for(&settings.language, key));
+        }
         Some(menu) => {
             if !menu.menus.is_empty() {
-                for group in &menu.menus {
-                    if provider == Provider::Compass && settings.hide_expensive_student_meals {
-                        if let Some(price) = student_price_eur(&group.price) {
-                            if price > 4.0 {
+                let groups: Vec<_> = if settings.reverse_group_order {
+                    menu.menus.iter().rev().collect()
+                } else {
+                    menu.menus.iter().collect()
+                };
+                for group in groups {
+                    let compass = matches!(provider, Provider::Compass | Provider::CompassRss);
+                    if compass && settings.hide_expensive_student_meals {
+                        if let Some(price) = student_price_eur(&group.prices) {
+                            if price > settings.expensive_student_threshold_eur {
                                 continue;
                             }
                         }
@@ -192,14 +366,19 @@ fn print_today_menu_with_settings(settings: &crate::settings::Settings) -> anyho
                         if component.is_empty() {
                             continue;
                         }
-                        let (main, suffix) = split_component_suffix(&component);
+                        let (main, suffix) = split_component_suffix(&component, language);
                         if main.is_empty() {
                             continue;
                         }
+                        let marker = if is_starred_dish(&settings.starred_dishes, &main) {
+                            "★"
+                        } else {
+                            "▸"
+                        };
                         if !settings.show_allergens || suffix.is_empty() {
-                            println!("  ▸ {}", main);
+                            println!("  {} {}", marker, main);
                         } else {
-                            println!("  ▸ {} {}", main, suffix);
+                            println!("  {} {} {}", marker, main, suffix);
                         }
                     }
                 }
@@ -211,6 +390,4 @@ fn print_today_menu_with_settings(settings: &crate::settings::Settings) -> anyho
             println!("{}", text_for(&settings.language, "noMenu"));
         }
     }
-
-    Ok(())
 }
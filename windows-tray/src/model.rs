@@ -39,6 +39,12 @@ pub struct TodayMenu {
     pub date_iso: String,
     pub lunch_time: String,
     pub menus: Vec<MenuGroup>,
+    pub closed: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WeekMenu {
+    pub days: Vec<TodayMenu>,
 }
 
 #[derive(Debug, Clone)]
@@ -46,4 +52,23 @@ pub struct MenuGroup {
     pub name: String,
     pub price: String,
     pub components: Vec<String>,
+    /// Energy per component (e.g. "520 kcal"), parallel to `components`; empty when the
+    /// provider has no nutrition data.
+    pub energy: Vec<String>,
+    /// `price` split per customer group, parsed once for Compass menus; empty otherwise.
+    pub prices: Vec<PriceEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceGroup {
+    Student,
+    Staff,
+    Guest,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceEntry {
+    pub group: PriceGroup,
+    pub text: String,
+    pub value: Option<f32>,
 }
@@ -1,33 +1,56 @@
-use crate::api;
-use crate::app::{AppState, FetchStatus};
+use crate::api::{self, FetchErrorKind};
+use crate::app::{default_day_index, weekday_index_after, AppState, FetchStatus};
 use crate::cache;
 use crate::format::{
-    date_and_time_line, menu_heading, normalize_text, split_component_suffix, student_price_eur,
-    text_for, PriceGroups,
+    allergen_icon, allergen_legend_line, apply_text_case, date_and_time_line, group_chip_label,
+    is_allergen_icon, is_closed_today, is_rtl_text, is_starred_dish, lunch_countdown_text,
+    matches_favorite_keyword, menu_heading, menu_heading_parts, normalize_text,
+    split_component_suffix, student_price_eur, text_for, updated_text, week_number_label,
+    PriceGroups,
 };
-use crate::model::TodayMenu;
-use crate::restaurant::{available_restaurants, Provider, Restaurant};
-use crate::settings::Settings;
-use crate::util::to_wstring;
+use crate::model::{MenuGroup, TodayMenu};
+use crate::restaurant::{available_restaurants, restaurant_for_code, Provider, Restaurant};
+use crate::settings::{menu_language, parse_hex_color, CustomTheme, Settings};
+use crate::util::{
+    date_key_from_epoch_ms, local_date_key, local_datetime_from_epoch_ms, local_now, to_wstring,
+};
+use crate::winmsg::WM_APP_POPUP_RENDER;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Mutex, OnceLock};
-use time::{OffsetDateTime, UtcOffset};
 use windows::core::PCWSTR;
-use windows::Win32::Foundation::{COLORREF, HWND, POINT, RECT};
+use windows::Win32::Foundation::{
+    GlobalFree, COLORREF, HANDLE, HWND, LPARAM, POINT, RECT, SIZE, WPARAM,
+};
 use windows::Win32::Graphics::Gdi::{
-    BeginPaint, CreateFontW, CreateSolidBrush, DeleteObject, EndPaint, FillRect, GetDeviceCaps,
-    GetMonitorInfoW, GetTextExtentPoint32W, GetTextMetricsW, InvalidateRect, MonitorFromPoint,
-    SelectObject, SetBkMode, SetTextColor, TextOutW, HDC, HFONT, LOGPIXELSY, MONITORINFO,
-    MONITOR_DEFAULTTONEAREST, PAINTSTRUCT, TEXTMETRICW, TRANSPARENT,
+    BeginPaint, CreateCompatibleDC, CreateDIBSection, CreateFontIndirectW, CreateFontW,
+    CreateSolidBrush, DeleteDC, DeleteObject, EndPaint, ExtTextOutW, FillRect, GdiFlush, GetDC,
+    GetDeviceCaps, GetMonitorInfoW, GetObjectW, GetTextExtentPoint32W, GetTextMetricsW,
+    IntersectClipRect, InvalidateRect, MonitorFromPoint, ReleaseDC, RestoreDC, SaveDC,
+    ScreenToClient, SelectObject, SetBkMode, SetTextColor, TextOutW, AC_SRC_ALPHA, AC_SRC_OVER,
+    BITMAPINFO, BITMAPINFOHEADER, BI_RGB, BLENDFUNCTION, DIB_RGB_COLORS, ETO_RTLREADING, HDC,
+    HFONT, LOGFONTW, LOGPIXELSY, MONITORINFO, MONITOR_DEFAULTTONEAREST, PAINTSTRUCT, TEXTMETRICW,
+    TRANSPARENT,
+};
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
 };
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::System::Ole::CF_UNICODETEXT;
+use windows::Win32::UI::Input::KeyboardAndMouse::{ReleaseCapture, SetCapture};
 use windows::Win32::UI::WindowsAndMessaging::{
-    GetClientRect, GetCursorPos, GetWindowRect, KillTimer, SetTimer, SetWindowPos, ShowWindow,
-    HWND_TOPMOST, SWP_SHOWWINDOW, SW_HIDE,
+    GetClientRect, GetCursorPos, GetSystemMetrics, GetWindowLongPtrW, GetWindowRect, KillTimer,
+    PostMessageW, SetTimer, SetWindowLongPtrW, SetWindowPos, ShowWindow, SystemParametersInfoW,
+    UpdateLayeredWindow, GWL_EXSTYLE, GWL_STYLE, HWND_TOPMOST, SM_CXVIRTUALSCREEN,
+    SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SPI_GETWORKAREA, SWP_NOACTIVATE,
+    SWP_NOSIZE, SWP_NOZORDER, SWP_SHOWWINDOW, SW_HIDE, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    ULW_ALPHA, WS_CAPTION, WS_EX_LAYERED,
 };
 
 const PADDING_X: i32 = 12;
 const PADDING_Y: i32 = 10;
 const LINE_GAP: i32 = 2;
 const ANCHOR_GAP: i32 = 10;
+const PRICE_COLUMN_GAP: i32 = 12;
 const POPUP_MAX_WIDTH: i32 = 525;
 const POPUP_MIN_WIDTH: i32 = 320;
 const POPUP_MAX_CONTENT_WIDTH: i32 = POPUP_MAX_WIDTH - PADDING_X * 2;
@@ -42,9 +65,25 @@ const POPUP_OPEN_ANIM_MS: i64 = 120;
 const POPUP_CLOSE_ANIM_MS: i64 = 90;
 const POPUP_SWITCH_ANIM_MS: i64 = 120;
 const POPUP_SWITCH_OFFSET_PX: i32 = 6;
+const SCROLL_STEP_PX: i32 = 48;
+const SCROLLBAR_WIDTH: i32 = 3;
+const TELETEXT_COLUMNS: usize = 40;
+const TELETEXT_ROWS: i32 = 25;
 
 static POPUP_LINE_BUDGET_CACHE: OnceLock<Mutex<Option<PopupLineBudgetCache>>> = OnceLock::new();
 static POPUP_ANIMATION: OnceLock<Mutex<Option<PopupAnimation>>> = OnceLock::new();
+static POPUP_DISH_ROWS: OnceLock<Mutex<Vec<DishRow>>> = OnceLock::new();
+/// Base font handle -> its Segoe UI Symbol twin, for the fonts `create_fonts` handed out.
+static POPUP_SYMBOL_FONTS: OnceLock<Mutex<Vec<(isize, isize)>>> = OnceLock::new();
+static POPUP_SCROLL: OnceLock<Mutex<PopupScroll>> = OnceLock::new();
+static POPUP_MICRO_EXPANDED: AtomicBool = AtomicBool::new(false);
+// Weekdays ahead of today shown by the quick-peek; view-only, so it stays out of AppState.
+static POPUP_SELECTED_DAY_OFFSET: AtomicUsize = AtomicUsize::new(0);
+static POPUP_APPLYING_SIZE: AtomicBool = AtomicBool::new(false);
+static POPUP_TELETEXT: AtomicBool = AtomicBool::new(false);
+static POPUP_LAYERED: AtomicBool = AtomicBool::new(false);
+static POPUP_RENDER_PENDING: AtomicBool = AtomicBool::new(false);
+static POPUP_DRAG: OnceLock<Mutex<Option<PopupDrag>>> = OnceLock::new();
 
 pub const POPUP_ANIM_TIMER_ID: usize = 100;
 
@@ -55,20 +94,35 @@ struct PopupLineBudgetKey {
     theme: String,
     dpi_y: i32,
     enable_antell_restaurants: bool,
+    disabled_providers: Vec<String>,
     show_prices: bool,
+    price_column: bool,
     show_student_price: bool,
     show_staff_price: bool,
     show_guest_price: bool,
     hide_expensive_student_meals: bool,
+    expensive_student_threshold_bits: u32,
     show_allergens: bool,
+    lunch_countdown: bool,
+    allergen_icons: bool,
+    text_case: String,
+    show_energy: bool,
+    show_allergen_legend: bool,
     highlight_gluten_free: bool,
     highlight_veg: bool,
     highlight_lactose_free: bool,
+    show_week_number: bool,
+    show_stale_marker: bool,
+    reverse_group_order: bool,
+    flag_repeat_menus: bool,
+    starred_dishes: Vec<String>,
+    heading_position: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct RestaurantCacheSignature {
     code: String,
+    language: String,
     mtime_ms: i64,
 }
 
@@ -83,14 +137,50 @@ struct PopupLineBudgetCache {
 #[derive(Debug, Clone)]
 enum Line {
     Heading(String),
+    /// A group heading whose price is drawn right-aligned in a column shared by all groups.
+    HeadingWithPrice {
+        name: String,
+        price: String,
+    },
     Text(String),
+    Note(String),
     TextWithSuffixSegments {
         main: String,
         segments: Vec<(String, bool)>,
+        dish: String,
+        starred: bool,
+        favorite: bool,
     },
     Spacer,
 }
 
+/// Cursor and window origin, in screen coordinates, when a header drag started.
+#[derive(Debug, Clone, Copy)]
+struct PopupDrag {
+    cursor: POINT,
+    window: POINT,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PopupScroll {
+    offset: i32,
+    content_height: i32,
+    view_height: i32,
+}
+
+impl PopupScroll {
+    fn max_offset(&self) -> i32 {
+        (self.content_height - self.view_height).max(0)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DishRow {
+    top: i32,
+    bottom: i32,
+    dish: String,
+}
+
 #[derive(Debug, Clone)]
 enum PopupAnimationKind {
     Open {
@@ -144,6 +234,8 @@ enum PopupAnimationFrame {
 pub enum HeaderButtonAction {
     Prev,
     Next,
+    PrevDay,
+    NextDay,
     Close,
 }
 
@@ -151,7 +243,10 @@ pub enum HeaderButtonAction {
 struct HeaderLayout {
     prev: RECT,
     next: RECT,
+    prev_day: RECT,
+    next_day: RECT,
     close: RECT,
+    title_right: i32,
 }
 
 pub fn toggle_popup(hwnd: HWND, state: &AppState) {
@@ -165,54 +260,98 @@ pub fn toggle_popup(hwnd: HWND, state: &AppState) {
 pub fn show_popup(hwnd: HWND, state: &AppState) {
     unsafe {
         let (width, height) = desired_size(hwnd, state);
-        let mut cursor = POINT::default();
-        let _ = GetCursorPos(&mut cursor);
-        let (x, y) = position_near_point(width, height, cursor);
-        let _ = SetWindowPos(hwnd, HWND_TOPMOST, x, y, width, height, SWP_SHOWWINDOW);
+        let (x, y) = fixed_corner_position(state, width, height).unwrap_or_else(|| {
+            let mut cursor = POINT::default();
+            let _ = GetCursorPos(&mut cursor);
+            position_near_point(width, height, cursor)
+        });
+        place_popup(hwnd, x, y, width, height);
         begin_open_animation(hwnd, state);
-        InvalidateRect(hwnd, None, true);
+        redraw_popup(hwnd);
     }
 }
 
 pub fn show_popup_at(hwnd: HWND, state: &AppState, anchor: POINT) {
-    unsafe {
-        let (width, height) = desired_size(hwnd, state);
-        let (x, y) = position_near_point(width, height, anchor);
-        let _ = SetWindowPos(hwnd, HWND_TOPMOST, x, y, width, height, SWP_SHOWWINDOW);
-        begin_open_animation(hwnd, state);
-        InvalidateRect(hwnd, None, true);
-    }
+    let (width, height) = desired_size(hwnd, state);
+    let (x, y) = fixed_corner_position(state, width, height)
+        .unwrap_or_else(|| position_near_point(width, height, anchor));
+    place_popup(hwnd, x, y, width, height);
+    begin_open_animation(hwnd, state);
+    redraw_popup(hwnd);
 }
 
+/// Opens next to the tray icon, or wherever `popup_anchor` says when it is not `"tray"`.
 pub fn show_popup_for_tray_icon(hwnd: HWND, state: &AppState, tray_rect: RECT) {
-    unsafe {
-        let (width, height) = desired_size(hwnd, state);
-        let (x, y) = position_near_tray_rect(width, height, tray_rect);
-        let _ = SetWindowPos(hwnd, HWND_TOPMOST, x, y, width, height, SWP_SHOWWINDOW);
-        begin_open_animation(hwnd, state);
-        InvalidateRect(hwnd, None, true);
+    if state.settings.popup_anchor != "tray" {
+        show_popup(hwnd, state);
+        return;
     }
+    let (width, height) = desired_size(hwnd, state);
+    let (x, y) = position_near_tray_rect(width, height, tray_rect);
+    place_popup(hwnd, x, y, width, height);
+    begin_open_animation(hwnd, state);
+    redraw_popup(hwnd);
 }
 
 pub fn resize_popup_keep_position(hwnd: HWND, state: &AppState) {
     unsafe {
+        if POPUP_TELETEXT.load(Ordering::Relaxed) {
+            redraw_popup(hwnd);
+            return;
+        }
         let mut rect = RECT::default();
         if GetWindowRect(hwnd, &mut rect).is_err() {
             show_popup(hwnd, state);
             return;
         }
         let (width, height) = desired_size(hwnd, state);
-        let anchor = POINT {
-            x: rect.right,
-            y: rect.bottom,
+        let (x, y) = if let Some(corner) = fixed_corner_position(state, width, height) {
+            corner
+        } else if state.settings.grow_direction == "down" {
+            let anchor = POINT {
+                x: rect.right,
+                y: rect.top,
+            };
+            position_below_point(width, height, anchor)
+        } else {
+            let anchor = POINT {
+                x: rect.right,
+                y: rect.bottom,
+            };
+            position_near_point(width, height, anchor)
         };
-        let (x, y) = position_near_point(width, height, anchor);
+        place_popup(hwnd, x, y, width, height);
+        redraw_popup(hwnd);
+    }
+}
+
+/// Moves and sizes the popup; WM_SIZE sent meanwhile is ours, not a user resize.
+fn place_popup(hwnd: HWND, x: i32, y: i32, width: i32, height: i32) {
+    POPUP_APPLYING_SIZE.store(true, Ordering::Relaxed);
+    unsafe {
         let _ = SetWindowPos(hwnd, HWND_TOPMOST, x, y, width, height, SWP_SHOWWINDOW);
-        InvalidateRect(hwnd, None, true);
     }
+    POPUP_APPLYING_SIZE.store(false, Ordering::Relaxed);
+}
+
+/// Width to remember after a WM_SIZE, or None when the size came from the popup itself.
+pub fn user_resized_width(state: &AppState, width: i32) -> Option<i32> {
+    if POPUP_APPLYING_SIZE.load(Ordering::Relaxed)
+        || POPUP_TELETEXT.load(Ordering::Relaxed)
+        || micro_collapsed(state)
+        || width <= 0
+    {
+        return None;
+    }
+    let width = width.clamp(POPUP_MIN_WIDTH, POPUP_MAX_WIDTH);
+    (state.settings.popup_width_px != Some(width)).then_some(width)
 }
 
 pub fn hide_popup(hwnd: HWND) {
+    POPUP_MICRO_EXPANDED.store(false, Ordering::Relaxed);
+    POPUP_TELETEXT.store(false, Ordering::Relaxed);
+    POPUP_SELECTED_DAY_OFFSET.store(0, Ordering::Relaxed);
+    end_header_drag();
     unsafe {
         clear_animation_state(hwnd);
         let _ = KillTimer(hwnd, POPUP_ANIM_TIMER_ID);
@@ -221,11 +360,13 @@ pub fn hide_popup(hwnd: HWND) {
 }
 
 fn begin_open_animation(hwnd: HWND, state: &AppState) {
+    POPUP_TELETEXT.store(false, Ordering::Relaxed);
+    reset_scroll();
     start_animation(
         hwnd,
         POPUP_OPEN_ANIM_MS,
         PopupAnimationKind::Open {
-            lines: build_lines(state),
+            lines: build_lines(state, shown_day(state)),
             title: header_title(state),
         },
     );
@@ -243,10 +384,17 @@ fn start_animation(hwnd: HWND, duration_ms: i64, kind: PopupAnimationKind) {
     }
     unsafe {
         let _ = SetTimer(hwnd, POPUP_ANIM_TIMER_ID, POPUP_ANIM_INTERVAL_MS, None);
-        InvalidateRect(hwnd, None, true);
+        redraw_popup(hwnd);
     }
 }
 
+pub fn animation_active(hwnd: HWND) -> bool {
+    let store = POPUP_ANIMATION.get_or_init(|| Mutex::new(None));
+    store
+        .lock()
+        .is_ok_and(|guard| guard.as_ref().is_some_and(|anim| anim.hwnd == hwnd))
+}
+
 fn clear_animation_state(hwnd: HWND) {
     let store = POPUP_ANIMATION.get_or_init(|| Mutex::new(None));
     if let Ok(mut guard) = store.lock() {
@@ -301,7 +449,7 @@ pub fn begin_close_animation(hwnd: HWND, state: &AppState) {
         hwnd,
         POPUP_CLOSE_ANIM_MS,
         PopupAnimationKind::Close {
-            lines: build_lines(state),
+            lines: build_lines(state, shown_day(state)),
             title: header_title(state),
         },
     );
@@ -313,12 +461,13 @@ pub fn begin_switch_animation(
     new_state: &AppState,
     direction: i32,
 ) {
+    reset_scroll();
     start_animation(
         hwnd,
         POPUP_SWITCH_ANIM_MS,
         PopupAnimationKind::Switch {
-            old_lines: build_lines(old_state),
-            new_lines: build_lines(new_state),
+            old_lines: build_lines(old_state, old_state.selected_day),
+            new_lines: build_lines(new_state, new_state.selected_day),
             old_title: header_title(old_state),
             new_title: header_title(new_state),
             direction,
@@ -361,11 +510,14 @@ pub fn tick_animation(hwnd: HWND) {
         if finished {
             let _ = KillTimer(hwnd, POPUP_ANIM_TIMER_ID);
             if hide_after {
+                POPUP_MICRO_EXPANDED.store(false, Ordering::Relaxed);
+                POPUP_SELECTED_DAY_OFFSET.store(0, Ordering::Relaxed);
+                POPUP_TELETEXT.store(false, Ordering::Relaxed);
                 ShowWindow(hwnd, SW_HIDE);
                 return;
             }
         }
-        InvalidateRect(hwnd, None, true);
+        redraw_popup(hwnd);
     }
 }
 
@@ -376,13 +528,19 @@ pub fn header_button_at(hwnd: HWND, x: i32, y: i32) -> Option<HeaderButtonAction
             return None;
         }
         let width = rect.right - rect.left;
-        let layout = header_layout(width);
+        let layout = header_layout(width, true);
         if point_in_rect(&layout.prev, x, y) {
             return Some(HeaderButtonAction::Prev);
         }
         if point_in_rect(&layout.next, x, y) {
             return Some(HeaderButtonAction::Next);
         }
+        if point_in_rect(&layout.prev_day, x, y) {
+            return Some(HeaderButtonAction::PrevDay);
+        }
+        if point_in_rect(&layout.next_day, x, y) {
+            return Some(HeaderButtonAction::NextDay);
+        }
         if point_in_rect(&layout.close, x, y) {
             return Some(HeaderButtonAction::Close);
         }
@@ -390,6 +548,266 @@ pub fn header_button_at(hwnd: HWND, x: i32, y: i32) -> Option<HeaderButtonAction
     }
 }
 
+pub fn dish_at_screen_point(hwnd: HWND, point: POINT) -> Option<String> {
+    let mut client = point;
+    if !unsafe { ScreenToClient(hwnd, &mut client) }.as_bool() {
+        return None;
+    }
+    let store = POPUP_DISH_ROWS.get_or_init(|| Mutex::new(Vec::new()));
+    let rows = store.lock().ok()?;
+    rows.iter()
+        .find(|row| client.y >= row.top && client.y < row.bottom)
+        .map(|row| row.dish.clone())
+}
+
+/// Starts moving the popup when the header strip is pressed outside its buttons.
+pub fn begin_header_drag(hwnd: HWND, state: &AppState, x: i32, y: i32) -> bool {
+    if POPUP_TELETEXT.load(Ordering::Relaxed)
+        || micro_collapsed(state)
+        || !(0..HEADER_HEIGHT).contains(&y)
+    {
+        return false;
+    }
+    if header_button_at(hwnd, x, y).is_some() {
+        return false;
+    }
+    let mut cursor = POINT::default();
+    let mut rect = RECT::default();
+    unsafe {
+        if GetCursorPos(&mut cursor).is_err() || GetWindowRect(hwnd, &mut rect).is_err() {
+            return false;
+        }
+        SetCapture(hwnd);
+    }
+    set_popup_drag(Some(PopupDrag {
+        cursor,
+        window: POINT {
+            x: rect.left,
+            y: rect.top,
+        },
+    }));
+    true
+}
+
+pub fn drag_popup(hwnd: HWND) -> bool {
+    let Some(drag) = popup_drag() else {
+        return false;
+    };
+    let mut cursor = POINT::default();
+    unsafe {
+        if GetCursorPos(&mut cursor).is_err() {
+            return true;
+        }
+        let _ = SetWindowPos(
+            hwnd,
+            HWND(0),
+            drag.window.x + cursor.x - drag.cursor.x,
+            drag.window.y + cursor.y - drag.cursor.y,
+            0,
+            0,
+            SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+    true
+}
+
+/// Returns true when a drag was in progress, so the button release is not treated as a click.
+pub fn end_header_drag() -> bool {
+    if popup_drag().is_none() {
+        return false;
+    }
+    set_popup_drag(None);
+    unsafe {
+        let _ = ReleaseCapture();
+    }
+    true
+}
+
+pub fn header_drag_active() -> bool {
+    popup_drag().is_some()
+}
+
+fn popup_drag() -> Option<PopupDrag> {
+    let store = POPUP_DRAG.get_or_init(|| Mutex::new(None));
+    store.lock().ok().and_then(|guard| *guard)
+}
+
+fn set_popup_drag(drag: Option<PopupDrag>) {
+    let store = POPUP_DRAG.get_or_init(|| Mutex::new(None));
+    if let Ok(mut guard) = store.lock() {
+        *guard = drag;
+    }
+}
+
+fn popup_scroll() -> PopupScroll {
+    let store = POPUP_SCROLL.get_or_init(|| Mutex::new(PopupScroll::default()));
+    store.lock().map(|guard| *guard).unwrap_or_default()
+}
+
+fn update_popup_scroll(update: impl FnOnce(&mut PopupScroll)) {
+    let store = POPUP_SCROLL.get_or_init(|| Mutex::new(PopupScroll::default()));
+    if let Ok(mut guard) = store.lock() {
+        update(&mut guard);
+        guard.offset = guard.offset.clamp(0, guard.max_offset());
+    }
+}
+
+pub fn reset_scroll() {
+    update_popup_scroll(|scroll| scroll.offset = 0);
+}
+
+/// Scrolls the body by mouse-wheel `delta`; returns false when the content fits the window.
+pub fn scroll_popup(hwnd: HWND, delta: i32) -> bool {
+    let scroll = popup_scroll();
+    if scroll.max_offset() == 0 {
+        return false;
+    }
+    let step = -delta * SCROLL_STEP_PX / 120;
+    update_popup_scroll(|scroll| scroll.offset += step);
+    redraw_popup(hwnd);
+    true
+}
+
+/// Day the popup renders: the peeked weekday while a quick-peek is held, else `selected_day`.
+fn shown_day(state: &AppState) -> Option<usize> {
+    match POPUP_SELECTED_DAY_OFFSET.load(Ordering::Relaxed) {
+        0 => state.selected_day,
+        offset => weekday_index_after(&state.week_menu.days, local_now().date(), offset)
+            .or(state.selected_day),
+    }
+}
+
+/// Slides to the day `offset` weekdays ahead (0 snaps back); false if nothing changed.
+pub fn set_day_peek(hwnd: HWND, state: &AppState, offset: usize) -> bool {
+    if offset > 0
+        && weekday_index_after(&state.week_menu.days, local_now().date(), offset).is_none()
+    {
+        return false;
+    }
+    let old_day = shown_day(state);
+    let previous = POPUP_SELECTED_DAY_OFFSET.swap(offset, Ordering::Relaxed);
+    if previous == offset {
+        return false;
+    }
+    let mut old_state = state.clone();
+    old_state.selected_day = old_day;
+    let mut new_state = state.clone();
+    new_state.selected_day = shown_day(state);
+    let direction = if offset > previous { 1 } else { -1 };
+    resize_popup_keep_position(hwnd, state);
+    begin_switch_animation(hwnd, &old_state, &new_state, direction);
+    true
+}
+
+/// Drops a held quick-peek without animating, e.g. when the day is changed explicitly.
+pub fn clear_day_peek() {
+    POPUP_SELECTED_DAY_OFFSET.store(0, Ordering::Relaxed);
+}
+
+/// Covers the whole monitor under the cursor with a teletext page of the menu.
+pub fn show_teletext_fullscreen(hwnd: HWND) {
+    unsafe {
+        clear_animation_state(hwnd);
+        let _ = KillTimer(hwnd, POPUP_ANIM_TIMER_ID);
+        let mut cursor = POINT::default();
+        let _ = GetCursorPos(&mut cursor);
+        let monitor = monitor_rect_near(cursor);
+        POPUP_TELETEXT.store(true, Ordering::Relaxed);
+        reset_scroll();
+        end_header_drag();
+        place_popup(
+            hwnd,
+            monitor.left,
+            monitor.top,
+            monitor.right - monitor.left,
+            monitor.bottom - monitor.top,
+        );
+        redraw_popup(hwnd);
+    }
+}
+
+pub fn teletext_active() -> bool {
+    POPUP_TELETEXT.load(Ordering::Relaxed)
+}
+
+fn micro_collapsed(state: &AppState) -> bool {
+    state.settings.micro_mode && !POPUP_MICRO_EXPANDED.load(Ordering::Relaxed)
+}
+
+pub fn micro_expanded() -> bool {
+    POPUP_MICRO_EXPANDED.load(Ordering::Relaxed)
+}
+
+/// Switches between the micro-mode chip strip and the full popup; false if nothing changed.
+pub fn set_micro_expanded(hwnd: HWND, state: &AppState, expanded: bool) -> bool {
+    if !state.settings.micro_mode
+        || POPUP_MICRO_EXPANDED.swap(expanded, Ordering::Relaxed) == expanded
+    {
+        return false;
+    }
+    reset_scroll();
+    resize_popup_keep_position(hwnd, state);
+    true
+}
+
+fn micro_chip_labels(state: &AppState) -> Vec<String> {
+    let mut labels: Vec<String> = match displayed_menu(state, shown_day(state)) {
+        Some(menu) if !menu.closed => menu.menus.iter().map(group_chip_label).collect(),
+        _ => Vec::new(),
+    };
+    if state.settings.reverse_group_order {
+        labels.reverse();
+    }
+    if labels.is_empty() {
+        labels.push("–".to_string());
+    }
+    labels
+}
+
+fn micro_size(state: &AppState) -> (i32, i32) {
+    let count = micro_chip_labels(state).len() as i32;
+    (
+        PADDING_X * 2 + count * HEADER_BUTTON_SIZE + (count - 1) * HEADER_BUTTON_GAP,
+        PADDING_Y * 2 + HEADER_BUTTON_SIZE,
+    )
+}
+
+fn paint_micro(hdc: HDC, state: &AppState) {
+    let palette = theme_palette(&state.settings);
+    let (normal_font, bold_font, small_font, small_bold_font) = create_fonts(hdc, &state.settings);
+    let old_font = unsafe { SelectObject(hdc, bold_font) };
+    let mut left = PADDING_X;
+    for label in micro_chip_labels(state) {
+        let chip = RECT {
+            left,
+            top: PADDING_Y,
+            right: left + HEADER_BUTTON_SIZE,
+            bottom: PADDING_Y + HEADER_BUTTON_SIZE,
+        };
+        draw_header_button(
+            hdc,
+            &chip,
+            &label,
+            palette.button_bg_color,
+            palette.heading_color,
+            bold_font,
+        );
+        left = chip.right + HEADER_BUTTON_GAP;
+    }
+    store_dish_rows(Vec::new());
+    unsafe {
+        SelectObject(hdc, old_font);
+        delete_fonts([normal_font, bold_font, small_font, small_bold_font]);
+    }
+}
+
+fn store_dish_rows(rows: Vec<DishRow>) {
+    let store = POPUP_DISH_ROWS.get_or_init(|| Mutex::new(Vec::new()));
+    if let Ok(mut guard) = store.lock() {
+        *guard = rows;
+    }
+}
+
 pub fn paint_popup(hwnd: HWND, state: &AppState) {
     unsafe {
         let mut ps = PAINTSTRUCT::default();
@@ -397,24 +815,45 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
         if hdc.0 == 0 {
             return;
         }
+        // A layered popup is drawn by render_layered; just validate a stray WM_PAINT.
+        if !POPUP_LAYERED.load(Ordering::Relaxed) {
+            let mut rect = RECT::default();
+            let _ = GetClientRect(hwnd, &mut rect);
+            render_popup(hwnd, hdc, &rect, state);
+        }
+        EndPaint(hwnd, &ps);
+    }
+}
 
-        let mut rect = RECT::default();
-        let _ = GetClientRect(hwnd, &mut rect);
+/// Draws the whole popup into `hdc`; returns the bottom of the header band, or 0 when the
+/// teletext or collapsed micro view has none.
+fn render_popup(hwnd: HWND, hdc: HDC, rect: &RECT, state: &AppState) -> i32 {
+    let rect = *rect;
+    unsafe {
+        if POPUP_TELETEXT.load(Ordering::Relaxed) {
+            paint_teletext(hdc, &rect, state);
+            return 0;
+        }
         let width = rect.right - rect.left;
-        let palette = theme_palette(&state.settings.theme);
+        let palette = theme_palette(&state.settings);
         let brush = CreateSolidBrush(palette.bg_color);
         FillRect(hdc, &rect, brush);
         DeleteObject(brush);
         SetBkMode(hdc, TRANSPARENT);
+        if micro_collapsed(state) {
+            paint_micro(hdc, state);
+            return 0;
+        }
 
         let (normal_font, bold_font, small_font, small_bold_font) =
-            create_fonts(hdc, &state.settings.theme);
+            create_fonts(hdc, &state.settings);
         let _old_font = SelectObject(hdc, normal_font);
 
         let metrics = text_metrics(hdc, normal_font);
-        let line_height = metrics.tmHeight as i32 + LINE_GAP;
+        let line_height = metrics.tmHeight + LINE_GAP;
         let content_width = (width - PADDING_X * 2).max(40);
         let animation = current_animation_frame(hwnd);
+        let scroll = popup_scroll();
 
         let header_rect = RECT {
             left: rect.left,
@@ -426,7 +865,8 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
         FillRect(hdc, &header_rect, header_brush);
         DeleteObject(header_brush);
 
-        let layout = header_layout(width);
+        let show_day_selector = has_day_selector(state);
+        let layout = header_layout(width, show_day_selector);
         draw_header_button(
             hdc,
             &layout.prev,
@@ -443,6 +883,24 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
             palette.body_text_color,
             normal_font,
         );
+        if show_day_selector {
+            draw_header_button(
+                hdc,
+                &layout.prev_day,
+                "‹",
+                palette.button_bg_color,
+                palette.body_text_color,
+                normal_font,
+            );
+            draw_header_button(
+                hdc,
+                &layout.next_day,
+                "›",
+                palette.button_bg_color,
+                palette.body_text_color,
+                normal_font,
+            );
+        }
         draw_header_button(
             hdc,
             &layout.close,
@@ -452,15 +910,23 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
             normal_font,
         );
 
-        let divider_rect = RECT {
-            left: rect.left,
-            top: header_rect.bottom - 1,
-            right: rect.right,
-            bottom: header_rect.bottom,
-        };
-        let divider_brush = CreateSolidBrush(palette.divider_color);
-        FillRect(hdc, &divider_rect, divider_brush);
-        DeleteObject(divider_brush);
+        // The divider is drawn over the bottom of the header, so hiding it leaves the layout unchanged.
+        let divider_height = MulDiv(
+            state.settings.divider_thickness_px as i32,
+            GetDeviceCaps(hdc, LOGPIXELSY),
+            96,
+        );
+        if divider_height > 0 {
+            let divider_rect = RECT {
+                left: rect.left,
+                top: header_rect.bottom - divider_height,
+                right: rect.right,
+                bottom: header_rect.bottom,
+            };
+            let divider_brush = CreateSolidBrush(palette.divider_color);
+            FillRect(hdc, &divider_rect, divider_brush);
+            DeleteObject(divider_brush);
+        }
 
         if let Some(frame) = animation {
             match frame {
@@ -480,6 +946,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                     let layer_suffix = lerp_color(palette.bg_color, palette.suffix_color, progress);
                     let layer_suffix_highlight =
                         lerp_color(palette.bg_color, palette.suffix_highlight_color, progress);
+                    let layer_star = lerp_color(palette.bg_color, palette.star_color, progress);
                     draw_content_layer(
                         hdc,
                         &title,
@@ -492,6 +959,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                             header_title_color: layer_title,
                             suffix_color: layer_suffix,
                             suffix_highlight_color: layer_suffix_highlight,
+                            star_color: layer_star,
                             layout: &layout,
                             metrics: &metrics,
                             line_height,
@@ -500,6 +968,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                             small_font,
                             small_bold_font,
                             y_offset,
+                            scroll_offset: scroll.offset,
                         },
                     );
                 }
@@ -522,6 +991,8 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                         palette.suffix_highlight_color,
                         1.0 - progress,
                     );
+                    let layer_star =
+                        lerp_color(palette.bg_color, palette.star_color, 1.0 - progress);
                     draw_content_layer(
                         hdc,
                         &title,
@@ -534,6 +1005,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                             header_title_color: layer_title,
                             suffix_color: layer_suffix,
                             suffix_highlight_color: layer_suffix_highlight,
+                            star_color: layer_star,
                             layout: &layout,
                             metrics: &metrics,
                             line_height,
@@ -542,6 +1014,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                             small_font,
                             small_bold_font,
                             y_offset,
+                            scroll_offset: scroll.offset,
                         },
                     );
                 }
@@ -571,6 +1044,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                         palette.suffix_highlight_color,
                         1.0 - progress,
                     );
+                    let old_star = lerp_color(palette.bg_color, palette.star_color, 1.0 - progress);
                     let new_body_text =
                         lerp_color(palette.bg_color, palette.body_text_color, progress);
                     let new_heading = lerp_color(palette.bg_color, palette.heading_color, progress);
@@ -579,6 +1053,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                     let new_suffix = lerp_color(palette.bg_color, palette.suffix_color, progress);
                     let new_suffix_highlight =
                         lerp_color(palette.bg_color, palette.suffix_highlight_color, progress);
+                    let new_star = lerp_color(palette.bg_color, palette.star_color, progress);
                     draw_content_layer(
                         hdc,
                         &old_title,
@@ -591,6 +1066,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                             header_title_color: old_title_color,
                             suffix_color: old_suffix,
                             suffix_highlight_color: old_suffix_highlight,
+                            star_color: old_star,
                             layout: &layout,
                             metrics: &metrics,
                             line_height,
@@ -599,6 +1075,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                             small_font,
                             small_bold_font,
                             y_offset: old_offset,
+                            scroll_offset: scroll.offset,
                         },
                     );
                     draw_content_layer(
@@ -613,6 +1090,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                             header_title_color: new_title_color,
                             suffix_color: new_suffix,
                             suffix_highlight_color: new_suffix_highlight,
+                            star_color: new_star,
                             layout: &layout,
                             metrics: &metrics,
                             line_height,
@@ -621,14 +1099,15 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                             small_font,
                             small_bold_font,
                             y_offset: new_offset,
+                            scroll_offset: scroll.offset,
                         },
                     );
                 }
             }
         } else {
-            let lines = build_lines(state);
+            let lines = build_lines(state, shown_day(state));
             let title = header_title(state);
-            draw_content_layer(
+            let (dish_rows, content_height) = draw_content_layer(
                 hdc,
                 &title,
                 &lines,
@@ -640,6 +1119,7 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                     header_title_color: palette.header_title_color,
                     suffix_color: palette.suffix_color,
                     suffix_highlight_color: palette.suffix_highlight_color,
+                    star_color: palette.star_color,
                     layout: &layout,
                     metrics: &metrics,
                     line_height,
@@ -648,17 +1128,351 @@ pub fn paint_popup(hwnd: HWND, state: &AppState) {
                     small_font,
                     small_bold_font,
                     y_offset: 0,
+                    scroll_offset: scroll.offset,
                 },
             );
+            store_dish_rows(dish_rows);
+            let view_height = rect.bottom - HEADER_HEIGHT;
+            update_popup_scroll(|scroll| {
+                scroll.content_height = content_height;
+                scroll.view_height = view_height;
+            });
+            let scroll = popup_scroll();
+            if scroll.max_offset() > 0 {
+                draw_scrollbar(hdc, &rect, scroll, palette.suffix_color);
+            }
         }
 
         SelectObject(hdc, _old_font);
-        DeleteObject(normal_font);
-        DeleteObject(bold_font);
-        DeleteObject(small_font);
-        DeleteObject(small_bold_font);
-        EndPaint(hwnd, &ps);
+        delete_fonts([normal_font, bold_font, small_font, small_bold_font]);
+        header_rect.bottom
+    }
+}
+
+/// Redraws the popup: WM_PAINT for a plain window, or a posted render request for a layered
+/// one, which gets no WM_PAINT once UpdateLayeredWindow owns its contents.
+fn redraw_popup(hwnd: HWND) {
+    unsafe {
+        if !POPUP_LAYERED.load(Ordering::Relaxed) {
+            InvalidateRect(hwnd, None, true);
+        } else if !POPUP_RENDER_PENDING.swap(true, Ordering::Relaxed) {
+            let _ = PostMessageW(hwnd, WM_APP_POPUP_RENDER, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+/// Renders into a premultiplied 32-bit DIB and hands it to UpdateLayeredWindow, so the header
+/// band and the body each get their own alpha.
+pub fn render_layered(hwnd: HWND, state: &AppState) {
+    POPUP_RENDER_PENDING.store(false, Ordering::Relaxed);
+    if !POPUP_LAYERED.load(Ordering::Relaxed) {
+        return;
+    }
+    let (header_alpha, body_alpha) = popup_alphas(&state.settings).unwrap_or((255, 255));
+    unsafe {
+        let mut rect = RECT::default();
+        if GetClientRect(hwnd, &mut rect).is_err() {
+            return;
+        }
+        let width = rect.right - rect.left;
+        let height = rect.bottom - rect.top;
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let screen_dc = GetDC(HWND(0));
+        let mem_dc = CreateCompatibleDC(screen_dc);
+        ReleaseDC(HWND(0), screen_dc);
+        let info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                biHeight: -height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut bits: *mut std::ffi::c_void = std::ptr::null_mut();
+        let bitmap = match CreateDIBSection(mem_dc, &info, DIB_RGB_COLORS, &mut bits, HANDLE(0), 0)
+        {
+            Ok(bitmap) if !bits.is_null() => bitmap,
+            _ => {
+                let _ = DeleteDC(mem_dc);
+                return;
+            }
+        };
+        let old_bitmap = SelectObject(mem_dc, bitmap);
+        let header_bottom = render_popup(hwnd, mem_dc, &rect, state);
+        let _ = GdiFlush();
+
+        let pixels = std::slice::from_raw_parts_mut(bits as *mut u32, (width * height) as usize);
+        apply_region_alpha(
+            pixels,
+            width as usize,
+            header_bottom.max(0) as usize,
+            header_alpha,
+            body_alpha,
+        );
+        let size = SIZE {
+            cx: width,
+            cy: height,
+        };
+        let blend = BLENDFUNCTION {
+            BlendOp: AC_SRC_OVER as u8,
+            BlendFlags: 0,
+            SourceConstantAlpha: 255,
+            AlphaFormat: AC_SRC_ALPHA as u8,
+        };
+        let _ = UpdateLayeredWindow(
+            hwnd,
+            HDC(0),
+            None,
+            Some(&size),
+            mem_dc,
+            Some(&POINT::default()),
+            COLORREF(0),
+            Some(&blend),
+            ULW_ALPHA,
+        );
+
+        SelectObject(mem_dc, old_bitmap);
+        DeleteObject(bitmap);
+        let _ = DeleteDC(mem_dc);
+    }
+}
+
+/// GDI leaves alpha at zero for everything it draws; give rows above `header_bottom` the
+/// header alpha and the rest the body alpha, premultiplying the colors as
+/// UpdateLayeredWindow expects.
+fn apply_region_alpha(
+    pixels: &mut [u32],
+    width: usize,
+    header_bottom: usize,
+    header_alpha: u8,
+    body_alpha: u8,
+) {
+    if width == 0 {
+        return;
+    }
+    for (row, line) in pixels.chunks_mut(width).enumerate() {
+        let alpha = if row < header_bottom {
+            header_alpha
+        } else {
+            body_alpha
+        } as u32;
+        for pixel in line {
+            let scale = |shift: u32| ((*pixel >> shift) & 0xFF) * alpha / 255;
+            *pixel = (alpha << 24) | (scale(16) << 16) | (scale(8) << 8) | scale(0);
+        }
+    }
+}
+
+/// Header and body alpha from the opacity settings, or None when both are fully opaque.
+fn popup_alphas(settings: &Settings) -> Option<(u8, u8)> {
+    let alpha = |percent: u32| (percent.min(100) * 255 / 100) as u8;
+    let header = alpha(settings.popup_header_opacity);
+    let body = alpha(settings.popup_body_opacity);
+    (header < 255 || body < 255).then_some((header, body))
+}
+
+/// The `--no-tray` popup is a framed window; UpdateLayeredWindow would drop its frame.
+fn has_caption(hwnd: HWND) -> bool {
+    let style = unsafe { GetWindowLongPtrW(hwnd, GWL_STYLE) } as u32;
+    style & WS_CAPTION.0 == WS_CAPTION.0
+}
+
+/// Makes the popup layered when an opacity setting is below 100, or plain again; call it
+/// whenever settings are loaded.
+pub fn apply_opacity_settings(hwnd: HWND, settings: &Settings) {
+    let layered = popup_alphas(settings).is_some() && !has_caption(hwnd);
+    unsafe {
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE) as u32;
+        let wanted = if layered {
+            ex_style | WS_EX_LAYERED.0
+        } else {
+            ex_style & !WS_EX_LAYERED.0
+        };
+        if wanted != ex_style {
+            SetWindowLongPtrW(hwnd, GWL_EXSTYLE, wanted as isize);
+        }
+    }
+    POPUP_LAYERED.store(layered, Ordering::Relaxed);
+    redraw_popup(hwnd);
+}
+
+/// Full-screen teletext page: a fixed 40x25 grid of Consolas cells centered on the monitor,
+/// with the title on the top row and no header buttons.
+fn paint_teletext(hdc: HDC, client: &RECT, state: &AppState) {
+    let mut settings = state.settings.clone();
+    if !settings.theme.starts_with("teletext") {
+        settings.theme = "teletext1".to_string();
+    }
+    let palette = theme_palette(&settings);
+    let face = to_wstring(&theme_font_family(&settings));
+    let width = client.right - client.left;
+    let height = client.bottom - client.top;
+    unsafe {
+        let brush = CreateSolidBrush(palette.bg_color);
+        FillRect(hdc, client, brush);
+        DeleteObject(brush);
+        SetBkMode(hdc, TRANSPARENT);
+
+        let mut cell_height = (height / TELETEXT_ROWS).max(8);
+        let mut font = teletext_font(cell_height, &face);
+        let mut cell_width = text_metrics(hdc, font).tmAveCharWidth.max(1);
+        let grid_width = cell_width * TELETEXT_COLUMNS as i32;
+        if grid_width > width {
+            DeleteObject(font);
+            cell_height = (cell_height * width / grid_width).max(8);
+            font = teletext_font(cell_height, &face);
+            cell_width = text_metrics(hdc, font).tmAveCharWidth.max(1);
+        }
+        let old_font = SelectObject(hdc, font);
+        let left = client.left + (width - cell_width * TELETEXT_COLUMNS as i32).max(0) / 2;
+        let top = client.top + (height - cell_height * TELETEXT_ROWS).max(0) / 2;
+
+        let title_rect = RECT {
+            left,
+            top,
+            right: left + cell_width * TELETEXT_COLUMNS as i32,
+            bottom: top + cell_height,
+        };
+        let title_brush = CreateSolidBrush(palette.header_bg_color);
+        FillRect(hdc, &title_rect, title_brush);
+        DeleteObject(title_brush);
+        let title: String = restaurant_title(state)
+            .chars()
+            .take(TELETEXT_COLUMNS)
+            .collect();
+        let title_column = (TELETEXT_COLUMNS - title.chars().count()) / 2;
+        SetTextColor(hdc, palette.header_title_color);
+        draw_text_line(hdc, &title, left + title_column as i32 * cell_width, top);
+
+        let body_rows = (TELETEXT_ROWS - 2).max(0) as usize;
+        let mut rows = teletext_rows(&build_lines(state, shown_day(state)), &palette);
+        if rows.len() > body_rows {
+            rows.truncate(body_rows.saturating_sub(1));
+            rows.push(vec![("...".to_string(), palette.suffix_color)]);
+        }
+        for (index, row) in rows.iter().enumerate() {
+            let y = top + (index as i32 + 2) * cell_height;
+            let mut column = 0;
+            for (text, color) in row {
+                SetTextColor(hdc, *color);
+                draw_text_line(hdc, text, left + column as i32 * cell_width, y);
+                column += text.chars().count();
+            }
+        }
+
+        SelectObject(hdc, old_font);
+        DeleteObject(font);
+    }
+}
+
+fn teletext_font(cell_height: i32, face: &[u16]) -> HFONT {
+    unsafe {
+        CreateFontW(
+            cell_height,
+            0,
+            0,
+            0,
+            400,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            PCWSTR(face.as_ptr()),
+        )
+    }
+}
+
+/// Lays the body lines out on the teletext grid as rows of colored spans.
+fn teletext_rows(lines: &[Line], palette: &ThemePalette) -> Vec<Vec<(String, COLORREF)>> {
+    fn push_wrapped(rows: &mut Vec<Vec<(String, COLORREF)>>, text: &str, color: COLORREF) {
+        for row in wrap_to_columns(text, TELETEXT_COLUMNS) {
+            rows.push(vec![(row, color)]);
+        }
+    }
+
+    let mut rows = Vec::new();
+    for line in lines {
+        match line {
+            Line::Heading(text) => push_wrapped(&mut rows, text, palette.heading_color),
+            Line::HeadingWithPrice { name, price } => push_wrapped(
+                &mut rows,
+                &format!("{} - {}", name, price),
+                palette.heading_color,
+            ),
+            Line::Text(text) => push_wrapped(&mut rows, text, palette.body_text_color),
+            Line::Note(text) => push_wrapped(&mut rows, text, palette.suffix_color),
+            Line::TextWithSuffixSegments {
+                main,
+                segments,
+                starred,
+                favorite,
+                ..
+            } => {
+                let main_color = if *starred {
+                    palette.star_color
+                } else if *favorite {
+                    palette.suffix_highlight_color
+                } else {
+                    palette.body_text_color
+                };
+                let main_rows = wrap_to_columns(main, TELETEXT_COLUMNS);
+                let last_len = main_rows.last().map(|row| row.chars().count());
+                rows.extend(main_rows.into_iter().map(|row| vec![(row, main_color)]));
+                let suffix = flatten_suffix_segments(segments);
+                if suffix.is_empty() {
+                    continue;
+                }
+                match (last_len, rows.last_mut()) {
+                    (Some(len), Some(row))
+                        if len + 1 + suffix.chars().count() <= TELETEXT_COLUMNS =>
+                    {
+                        row.push((format!(" {}", suffix), palette.suffix_color));
+                    }
+                    _ => push_wrapped(&mut rows, &suffix, palette.suffix_color),
+                }
+            }
+            Line::Spacer => rows.push(Vec::new()),
+        }
+    }
+    rows
+}
+
+/// Word-wraps `text` to at most `columns` characters per row, splitting overlong words.
+fn wrap_to_columns(text: &str, columns: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut current = String::new();
+    for word in normalize_text(text).split(' ') {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > columns {
+            if !current.is_empty() {
+                rows.push(std::mem::take(&mut current));
+            }
+            rows.push(word.drain(..columns).collect());
+        }
+        let word: String = word.into_iter().collect();
+        if current.is_empty() {
+            current = word;
+        } else if current.chars().count() + 1 + word.chars().count() <= columns {
+            current.push(' ');
+            current.push_str(&word);
+        } else {
+            rows.push(std::mem::replace(&mut current, word));
+        }
+    }
+    if !current.is_empty() {
+        rows.push(current);
     }
+    rows
 }
 
 struct DrawLayerParams<'a> {
@@ -669,6 +1483,7 @@ struct DrawLayerParams<'a> {
     header_title_color: COLORREF,
     suffix_color: COLORREF,
     suffix_highlight_color: COLORREF,
+    star_color: COLORREF,
     layout: &'a HeaderLayout,
     metrics: &'a TEXTMETRICW,
     line_height: i32,
@@ -677,9 +1492,36 @@ struct DrawLayerParams<'a> {
     small_font: HFONT,
     small_bold_font: HFONT,
     y_offset: i32,
+    scroll_offset: i32,
+}
+
+fn draw_scrollbar(hdc: HDC, client: &RECT, scroll: PopupScroll, color: COLORREF) {
+    let track_top = HEADER_HEIGHT + 2;
+    let track_height = (client.bottom - track_top - 2).max(1);
+    let thumb_height = (track_height * scroll.view_height / scroll.content_height.max(1))
+        .clamp(16.min(track_height), track_height);
+    let thumb_top =
+        track_top + (track_height - thumb_height) * scroll.offset / scroll.max_offset().max(1);
+    let thumb = RECT {
+        left: client.right - SCROLLBAR_WIDTH - 2,
+        top: thumb_top,
+        right: client.right - 2,
+        bottom: thumb_top + thumb_height,
+    };
+    unsafe {
+        let brush = CreateSolidBrush(color);
+        FillRect(hdc, &thumb, brush);
+        DeleteObject(brush);
+    }
 }
 
-fn draw_content_layer(hdc: HDC, title: &str, lines: &[Line], params: DrawLayerParams<'_>) {
+/// Draws the title and body lines; returns the dish rows and the body's full height.
+fn draw_content_layer(
+    hdc: HDC,
+    title: &str,
+    lines: &[Line],
+    params: DrawLayerParams<'_>,
+) -> (Vec<DishRow>, i32) {
     unsafe {
         SelectObject(hdc, params.bold_font);
         SetTextColor(hdc, params.header_title_color);
@@ -688,16 +1530,49 @@ fn draw_content_layer(hdc: HDC, title: &str, lines: &[Line], params: DrawLayerPa
     let clipped_title = fit_text_to_width(
         hdc,
         title,
-        (params.layout.close.left - params.layout.next.right - 24).max(40),
+        (params.layout.title_right - params.layout.next.right - 24).max(40),
     );
     let title_width = text_width(hdc, &clipped_title);
     let title_x = ((params.width - title_width) / 2).max(params.layout.next.right + 12);
     let title_y = ((HEADER_HEIGHT - params.metrics.tmHeight as i32) / 2 - 1) + params.y_offset;
     draw_text_line(hdc, &clipped_title, title_x, title_y);
 
-    let mut y = HEADER_HEIGHT + PADDING_Y + params.y_offset;
+    // Keep scrolled body text out of the header.
+    let saved_dc = unsafe {
+        let saved = SaveDC(hdc);
+        IntersectClipRect(hdc, 0, HEADER_HEIGHT, params.width, i32::MAX);
+        saved
+    };
+
+    let mut dish_rows = Vec::new();
+    let body_top = HEADER_HEIGHT + PADDING_Y + params.y_offset - params.scroll_offset;
+    let mut y = body_top;
+    let price_column = price_column_width(hdc, params.bold_font, lines);
     for line in lines {
         match line {
+            Line::HeadingWithPrice { name, price } => {
+                unsafe {
+                    SelectObject(hdc, params.bold_font);
+                    SetTextColor(hdc, params.heading_color);
+                }
+                let name_width = params.content_width - price_column - PRICE_COLUMN_GAP;
+                let (text, width) = if name_width >= 40 {
+                    let price_x = PADDING_X + params.content_width - text_width(hdc, price);
+                    draw_text_line(hdc, price, price_x, y);
+                    (name.clone(), name_width)
+                } else {
+                    (format!("{} - {}", name, price), params.content_width)
+                };
+                let wrapped = wrap_text_to_width(hdc, &text, width);
+                if wrapped.is_empty() {
+                    y += params.line_height;
+                }
+                for row in wrapped {
+                    let x = aligned_text_x(hdc, &row, PADDING_X, width);
+                    draw_text_line(hdc, &row, x, y);
+                    y += params.line_height;
+                }
+            }
             Line::Heading(text) => {
                 unsafe {
                     SelectObject(hdc, params.bold_font);
@@ -708,30 +1583,51 @@ fn draw_content_layer(hdc: HDC, title: &str, lines: &[Line], params: DrawLayerPa
                     y += params.line_height;
                 } else {
                     for row in wrapped {
-                        draw_text_line(hdc, &row, PADDING_X, y);
+                        let x = aligned_text_x(hdc, &row, PADDING_X, params.content_width);
+                        draw_text_line(hdc, &row, x, y);
                         y += params.line_height;
                     }
                 }
             }
-            Line::Text(text) => {
+            Line::Text(text) | Line::Note(text) => {
+                let color = if matches!(line, Line::Note(_)) {
+                    params.suffix_color
+                } else {
+                    params.body_text_color
+                };
                 unsafe {
                     SelectObject(hdc, params.normal_font);
-                    SetTextColor(hdc, params.body_text_color);
+                    SetTextColor(hdc, color);
                 }
                 let wrapped = wrap_text_to_width(hdc, text, params.content_width);
                 if wrapped.is_empty() {
                     y += params.line_height;
                 } else {
                     for row in wrapped {
-                        draw_text_line(hdc, &row, PADDING_X, y);
+                        let x = aligned_text_x(hdc, &row, PADDING_X, params.content_width);
+                        draw_text_line(hdc, &row, x, y);
                         y += params.line_height;
                     }
                 }
             }
-            Line::TextWithSuffixSegments { main, segments } => {
+            Line::TextWithSuffixSegments {
+                main,
+                segments,
+                dish,
+                starred,
+                favorite,
+            } => {
+                let row_top = y;
+                let main_color = if *starred {
+                    params.star_color
+                } else if *favorite {
+                    params.suffix_highlight_color
+                } else {
+                    params.body_text_color
+                };
                 unsafe {
                     SelectObject(hdc, params.normal_font);
-                    SetTextColor(hdc, params.body_text_color);
+                    SetTextColor(hdc, main_color);
                 }
                 let styled_width = text_with_suffix_width(
                     hdc,
@@ -749,18 +1645,25 @@ fn draw_content_layer(hdc: HDC, title: &str, lines: &[Line], params: DrawLayerPa
                         } else {
                             params.small_font
                         };
-                        unsafe {
-                            SelectObject(hdc, font);
-                        }
-                        suffix_width += text_width(hdc, segment);
+                        suffix_width += segment_width(hdc, font, segment);
                     }
-                    let max_main = (params.content_width - suffix_width - 4).max(24);
+                    let max_main = if segments.is_empty() {
+                        params.content_width
+                    } else {
+                        (params.content_width - suffix_width - 4).max(24)
+                    };
                     unsafe {
                         SelectObject(hdc, params.normal_font);
+                        SetTextColor(hdc, main_color);
                     }
                     let clipped_main = fit_text_to_width(hdc, main, max_main);
                     let main_width = text_width(hdc, &clipped_main);
-                    draw_text_line(hdc, &clipped_main, PADDING_X, y);
+                    let main_x = if segments.is_empty() {
+                        aligned_text_x(hdc, &clipped_main, PADDING_X, params.content_width)
+                    } else {
+                        PADDING_X
+                    };
+                    draw_text_line(hdc, &clipped_main, main_x, y);
                     if !segments.is_empty() {
                         let suffix_x = PADDING_X + main_width + 4;
                         if suffix_x < (PADDING_X + params.content_width) {
@@ -777,6 +1680,11 @@ fn draw_content_layer(hdc: HDC, title: &str, lines: &[Line], params: DrawLayerPa
                         }
                     }
                     y += params.line_height;
+                    dish_rows.push(DishRow {
+                        top: row_top,
+                        bottom: y,
+                        dish: dish.clone(),
+                    });
                     continue;
                 }
                 unsafe {
@@ -787,7 +1695,8 @@ fn draw_content_layer(hdc: HDC, title: &str, lines: &[Line], params: DrawLayerPa
                     y += params.line_height;
                 } else {
                     for row in wrapped_main {
-                        draw_text_line(hdc, &row, PADDING_X, y);
+                        let x = aligned_text_x(hdc, &row, PADDING_X, params.content_width);
+                        draw_text_line(hdc, &row, x, y);
                         y += params.line_height;
                     }
                 }
@@ -827,12 +1736,22 @@ fn draw_content_layer(hdc: HDC, title: &str, lines: &[Line], params: DrawLayerPa
                         }
                     }
                 }
+                dish_rows.push(DishRow {
+                    top: row_top,
+                    bottom: y,
+                    dish: dish.clone(),
+                });
             }
             Line::Spacer => {
                 y += params.line_height / 2;
             }
         }
     }
+    unsafe {
+        RestoreDC(hdc, saved_dc);
+    }
+    dish_rows.retain(|row| row.bottom > HEADER_HEIGHT);
+    (dish_rows, y - body_top + PADDING_Y * 2)
 }
 
 fn measure_lines_layout(
@@ -847,22 +1766,36 @@ fn measure_lines_layout(
     let wrap_width = wrap_content_width.max(40);
     let mut required_content_width = 0;
     let mut wrapped_line_count = 0usize;
+    let price_column = price_column_width(hdc, bold_font, lines);
 
     for line in lines {
         match line {
+            Line::HeadingWithPrice { name, price } => {
+                let width = text_width_with_font(hdc, bold_font, name);
+                required_content_width =
+                    required_content_width.max(width + PRICE_COLUMN_GAP + price_column);
+                let name_width = wrap_width - price_column - PRICE_COLUMN_GAP;
+                let rows = if name_width >= 40 {
+                    wrapped_line_count_for_text(hdc, bold_font, name, name_width)
+                } else {
+                    let inline = format!("{} - {}", name, price);
+                    wrapped_line_count_for_text(hdc, bold_font, &inline, wrap_width)
+                };
+                wrapped_line_count += rows.max(1);
+            }
             Line::Heading(text) => {
                 let width = text_width_with_font(hdc, bold_font, text);
                 required_content_width = required_content_width.max(width);
                 let rows = wrapped_line_count_for_text(hdc, bold_font, text, wrap_width);
                 wrapped_line_count += rows.max(1);
             }
-            Line::Text(text) => {
+            Line::Text(text) | Line::Note(text) => {
                 let width = text_width_with_font(hdc, normal_font, text);
                 required_content_width = required_content_width.max(width);
                 let rows = wrapped_line_count_for_text(hdc, normal_font, text, wrap_width);
                 wrapped_line_count += rows.max(1);
             }
-            Line::TextWithSuffixSegments { main, segments } => {
+            Line::TextWithSuffixSegments { main, segments, .. } => {
                 let styled_width = text_with_suffix_width(
                     hdc,
                     normal_font,
@@ -905,6 +1838,20 @@ fn measure_lines_layout(
     }
 }
 
+/// Width of the shared price column: the widest price among the `HeadingWithPrice` lines.
+fn price_column_width(hdc: HDC, bold_font: HFONT, lines: &[Line]) -> i32 {
+    lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::HeadingWithPrice { price, .. } => {
+                Some(text_width_with_font(hdc, bold_font, price))
+            }
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
 fn wrapped_line_count_for_text(hdc: HDC, font: HFONT, text: &str, max_width: i32) -> usize {
     let wrapped = wrap_text_to_width_with_font(hdc, font, text, max_width);
     wrapped.len()
@@ -973,19 +1920,16 @@ fn wrap_text_to_width(hdc: HDC, text: &str, max_width: i32) -> Vec<String> {
 }
 
 fn split_long_token_to_width(hdc: HDC, token: &str, max_width: i32) -> Vec<String> {
+    let chars: Vec<char> = token.chars().collect();
+    let widths: Vec<i32> = chars
+        .iter()
+        .map(|ch| text_width(hdc, &ch.to_string()))
+        .collect();
     let mut rows = Vec::new();
-    let mut current = String::new();
-    for ch in token.chars() {
-        let mut candidate = current.clone();
-        candidate.push(ch);
-        if !current.is_empty() && text_width(hdc, &candidate) > max_width {
-            rows.push(current.clone());
-            current.clear();
-        }
-        current.push(ch);
-    }
-    if !current.is_empty() {
-        rows.push(current);
+    let mut start = 0;
+    for end in token_row_breaks(&chars, &widths, max_width) {
+        rows.push(chars[start..end].iter().collect());
+        start = end;
     }
     if rows.is_empty() {
         rows.push(token.to_string());
@@ -993,6 +1937,38 @@ fn split_long_token_to_width(hdc: HDC, token: &str, max_width: i32) -> Vec<Strin
     rows
 }
 
+/// Characters a long token may break after before falling back to any character.
+const TOKEN_BREAK_AFTER: &[char] = &[
+    '-', '\u{2010}', '\u{2013}', '\u{2014}', '/', '\\', ',', ';', ':', '|', '+', ')',
+];
+
+/// Row end indices for a token whose characters are `widths` wide: each row takes as many
+/// characters as fit in `max_width`, cut back to just after the last separator in it if there
+/// is one. Every row keeps at least one character.
+fn token_row_breaks(chars: &[char], widths: &[i32], max_width: i32) -> Vec<usize> {
+    let mut breaks = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut fit = start + 1;
+        let mut width = widths[start];
+        while fit < chars.len() && width + widths[fit] <= max_width {
+            width += widths[fit];
+            fit += 1;
+        }
+        let end = if fit == chars.len() {
+            fit
+        } else {
+            (start + 1..fit)
+                .rev()
+                .find(|&index| TOKEN_BREAK_AFTER.contains(&chars[index - 1]))
+                .unwrap_or(fit)
+        };
+        breaks.push(end);
+        start = end;
+    }
+    breaks
+}
+
 fn text_width_with_font(hdc: HDC, font: HFONT, text: &str) -> i32 {
     unsafe {
         let old = SelectObject(hdc, font);
@@ -1018,11 +1994,70 @@ fn text_with_suffix_width(
     let mut suffix_width = 0;
     for (segment, bold) in segments {
         let font = if *bold { small_bold_font } else { small_font };
-        suffix_width += text_width_with_font(hdc, font, segment);
+        suffix_width += segment_width(hdc, font, segment);
     }
     main_width + suffix_width + 4
 }
 
+/// Plain-text version of the popup contents, titled like the popup header.
+fn menu_plain_text(state: &AppState) -> String {
+    let mut out = vec![restaurant_title(state)];
+    for line in build_lines(state, shown_day(state)) {
+        match line {
+            Line::Heading(text) | Line::Text(text) | Line::Note(text) => out.push(text),
+            Line::HeadingWithPrice { name, price } => out.push(format!("{} - {}", name, price)),
+            Line::TextWithSuffixSegments { main, segments, .. } => {
+                let suffix = flatten_suffix_segments(&segments);
+                if suffix.is_empty() {
+                    out.push(main);
+                } else {
+                    out.push(format!("{} {}", main, suffix));
+                }
+            }
+            Line::Spacer => out.push(String::new()),
+        }
+    }
+    out.join("\r\n")
+}
+
+pub fn copy_menu_to_clipboard(hwnd: HWND, state: &AppState) -> bool {
+    copy_text_to_clipboard(hwnd, &menu_plain_text(state))
+}
+
+pub fn copy_text_to_clipboard(hwnd: HWND, text: &str) -> bool {
+    let text = to_wstring(text);
+    let bytes = text.len() * std::mem::size_of::<u16>();
+    unsafe {
+        if OpenClipboard(hwnd).is_err() {
+            return false;
+        }
+        let _ = EmptyClipboard();
+        let copied = match GlobalAlloc(GMEM_MOVEABLE, bytes) {
+            Ok(memory) => {
+                let target = GlobalLock(memory) as *mut u16;
+                if target.is_null() {
+                    let _ = GlobalFree(memory);
+                    false
+                } else {
+                    std::ptr::copy_nonoverlapping(text.as_ptr(), target, text.len());
+                    let _ = GlobalUnlock(memory);
+                    // The clipboard owns the memory once SetClipboardData succeeds.
+                    let handle = HANDLE(memory.0 as isize);
+                    if SetClipboardData(CF_UNICODETEXT.0 as u32, handle).is_ok() {
+                        true
+                    } else {
+                        let _ = GlobalFree(memory);
+                        false
+                    }
+                }
+            }
+            Err(_) => false,
+        };
+        let _ = CloseClipboard();
+        copied
+    }
+}
+
 fn flatten_suffix_segments(segments: &[(String, bool)]) -> String {
     let mut out = String::new();
     for (segment, _) in segments {
@@ -1045,12 +2080,83 @@ fn draw_text_segments(
     for (text, bold) in segments {
         let font = if *bold { bold_font } else { normal_font };
         let color = if *bold { highlight_color } else { normal_color };
+        let draw_font = if is_allergen_icon(text) {
+            symbol_font_for(font)
+        } else {
+            font
+        };
         unsafe {
-            SelectObject(hdc, font);
+            SelectObject(hdc, draw_font);
             SetTextColor(hdc, color);
         }
         draw_text_line(hdc, text, cursor, y);
         cursor += text_width(hdc, text);
+        if draw_font != font {
+            unsafe {
+                SelectObject(hdc, font);
+            }
+        }
+    }
+}
+
+/// Width of one suffix segment; allergen icons are measured in the symbol font they are drawn in.
+fn segment_width(hdc: HDC, font: HFONT, text: &str) -> i32 {
+    if is_allergen_icon(text) {
+        text_width_with_font(hdc, symbol_font_for(font), text)
+    } else {
+        text_width_with_font(hdc, font, text)
+    }
+}
+
+/// The Segoe UI Symbol twin `create_fonts` made for `font`, or `font` itself when icons are off.
+fn symbol_font_for(font: HFONT) -> HFONT {
+    let store = POPUP_SYMBOL_FONTS.get_or_init(|| Mutex::new(Vec::new()));
+    store
+        .lock()
+        .ok()
+        .and_then(|fonts| {
+            fonts
+                .iter()
+                .find(|(base, _)| *base == font.0)
+                .map(|(_, symbol)| HFONT(*symbol))
+        })
+        .unwrap_or(font)
+}
+
+/// Deletes fonts from `create_fonts` along with their symbol twins.
+fn delete_fonts(fonts: [HFONT; 4]) {
+    let store = POPUP_SYMBOL_FONTS.get_or_init(|| Mutex::new(Vec::new()));
+    let mut symbols = Vec::new();
+    if let Ok(mut registered) = store.lock() {
+        registered.retain(|(base, symbol)| {
+            let owned = fonts.iter().any(|font| font.0 == *base);
+            if owned {
+                symbols.push(HFONT(*symbol));
+            }
+            !owned
+        });
+    }
+    unsafe {
+        for font in fonts.into_iter().chain(symbols) {
+            DeleteObject(font);
+        }
+    }
+}
+
+/// `font` with its size and weight but in Segoe UI Symbol, which has the allergen icons that
+/// the theme fonts (Segoe UI, Consolas, custom ones) may lack.
+fn symbol_font_like(font: HFONT) -> HFONT {
+    unsafe {
+        let mut logfont = LOGFONTW::default();
+        GetObjectW(
+            font,
+            std::mem::size_of::<LOGFONTW>() as i32,
+            Some(&mut logfont as *mut LOGFONTW as *mut _),
+        );
+        let face: Vec<u16> = "Segoe UI Symbol".encode_utf16().collect();
+        logfont.lfFaceName = [0; 32];
+        logfont.lfFaceName[..face.len()].copy_from_slice(&face);
+        CreateFontIndirectW(&logfont)
     }
 }
 
@@ -1059,11 +2165,33 @@ fn draw_text_line(hdc: HDC, text: &str, x: i32, y: i32) {
     unsafe {
         if wide.len() > 1 {
             let slice = &wide[..wide.len() - 1];
-            let _ = TextOutW(hdc, x, y, slice);
+            if is_rtl_text(text) {
+                let _ = ExtTextOutW(
+                    hdc,
+                    x,
+                    y,
+                    ETO_RTLREADING,
+                    None,
+                    PCWSTR(wide.as_ptr()),
+                    slice.len() as u32,
+                    None,
+                );
+            } else {
+                let _ = TextOutW(hdc, x, y, slice);
+            }
         }
     }
 }
 
+/// X for a row of `max_width` starting at `left`: right-to-left text is right-aligned.
+fn aligned_text_x(hdc: HDC, text: &str, left: i32, max_width: i32) -> i32 {
+    if is_rtl_text(text) {
+        left + (max_width - text_width(hdc, text)).max(0)
+    } else {
+        left
+    }
+}
+
 fn fit_text_to_width(hdc: HDC, text: &str, max_width: i32) -> String {
     let clean = normalize_text(text);
     if clean.is_empty() || max_width <= 0 {
@@ -1117,7 +2245,7 @@ fn draw_header_button(
     draw_text_line(hdc, label, x, y);
 }
 
-fn header_layout(width: i32) -> HeaderLayout {
+fn header_layout(width: i32, show_day_selector: bool) -> HeaderLayout {
     let top = (HEADER_HEIGHT - HEADER_BUTTON_SIZE) / 2;
     let prev = RECT {
         left: PADDING_X,
@@ -1137,11 +2265,45 @@ fn header_layout(width: i32) -> HeaderLayout {
         right: width - PADDING_X,
         bottom: top + HEADER_BUTTON_SIZE,
     };
-    HeaderLayout { prev, next, close }
+    let next_day = RECT {
+        left: close.left - HEADER_BUTTON_GAP * 2 - HEADER_BUTTON_SIZE,
+        top,
+        right: close.left - HEADER_BUTTON_GAP * 2,
+        bottom: top + HEADER_BUTTON_SIZE,
+    };
+    let prev_day = RECT {
+        left: next_day.left - HEADER_BUTTON_GAP - HEADER_BUTTON_SIZE,
+        top,
+        right: next_day.left - HEADER_BUTTON_GAP,
+        bottom: top + HEADER_BUTTON_SIZE,
+    };
+    let title_right = if show_day_selector {
+        prev_day.left
+    } else {
+        close.left
+    };
+    HeaderLayout {
+        prev,
+        next,
+        prev_day,
+        next_day,
+        close,
+        title_right,
+    }
 }
 
+/// Title drawn in the popup header; a pinned popup gets a pin in front.
 fn header_title(state: &AppState) -> String {
-    let list = available_restaurants(state.settings.enable_antell_restaurants);
+    let title = restaurant_title(state);
+    if state.settings.pin_popup {
+        format!("📌 {}", title)
+    } else {
+        title
+    }
+}
+
+fn restaurant_title(state: &AppState) -> String {
+    let list = available_restaurants(&state.settings);
     if list.is_empty() {
         return "Compass Lunch".to_string();
     }
@@ -1176,12 +2338,15 @@ fn text_width(hdc: HDC, text: &str) -> i32 {
 }
 
 fn desired_size(hwnd: HWND, state: &AppState) -> (i32, i32) {
+    if micro_collapsed(state) {
+        return micro_size(state);
+    }
     unsafe {
         let hdc = windows::Win32::Graphics::Gdi::GetDC(hwnd);
         let dpi_y = GetDeviceCaps(hdc, LOGPIXELSY);
         let (normal_font, bold_font, small_font, small_bold_font) =
-            create_fonts(hdc, &state.settings.theme);
-        let current_lines = build_lines(state);
+            create_fonts(hdc, &state.settings);
+        let current_lines = build_lines(state, shown_day(state));
         let current_metrics = measure_lines_layout(
             hdc,
             normal_font,
@@ -1200,10 +2365,13 @@ fn desired_size(hwnd: HWND, state: &AppState) -> (i32, i32) {
             small_bold_font,
             dpi_y,
         );
-        let target_content_width = budget
-            .max_content_width_px
-            .unwrap_or(current_metrics.required_content_width)
-            .clamp(POPUP_MIN_CONTENT_WIDTH, POPUP_MAX_CONTENT_WIDTH);
+        let target_content_width = match state.settings.popup_width_px {
+            Some(width) => width - PADDING_X * 2,
+            None => budget
+                .max_content_width_px
+                .unwrap_or(current_metrics.required_content_width),
+        }
+        .clamp(POPUP_MIN_CONTENT_WIDTH, POPUP_MAX_CONTENT_WIDTH);
         let current_wrapped_metrics = measure_lines_layout(
             hdc,
             normal_font,
@@ -1224,22 +2392,31 @@ fn desired_size(hwnd: HWND, state: &AppState) -> (i32, i32) {
         let line_height = metrics.tmHeight as i32 + LINE_GAP;
         let height = HEADER_HEIGHT + (target_lines as i32 * line_height) + PADDING_Y * 2;
         let width = (target_content_width + PADDING_X * 2).clamp(POPUP_MIN_WIDTH, POPUP_MAX_WIDTH);
-        DeleteObject(normal_font);
-        DeleteObject(bold_font);
-        DeleteObject(small_font);
-        DeleteObject(small_bold_font);
+        delete_fonts([normal_font, bold_font, small_font, small_bold_font]);
         windows::Win32::Graphics::Gdi::ReleaseDC(hwnd, hdc);
 
-        (width, height.max(HEADER_HEIGHT + 120))
+        let mut anchor = POINT::default();
+        let mut window_rect = RECT::default();
+        if is_visible(hwnd) && GetWindowRect(hwnd, &mut window_rect).is_ok() {
+            anchor = POINT {
+                x: window_rect.right,
+                y: window_rect.bottom,
+            };
+        } else {
+            let _ = GetCursorPos(&mut anchor);
+        }
+        let work_area = work_area_near(anchor);
+        let max_height = (work_area.bottom - work_area.top).max(HEADER_HEIGHT + 120);
+        (width, height.max(HEADER_HEIGHT + 120).min(max_height))
     }
 }
 
-fn create_fonts(hdc: HDC, theme: &str) -> (HFONT, HFONT, HFONT, HFONT) {
+fn create_fonts(hdc: HDC, settings: &Settings) -> (HFONT, HFONT, HFONT, HFONT) {
     unsafe {
         let dpi = GetDeviceCaps(hdc, LOGPIXELSY);
         let height_normal = -MulDiv(12, dpi, 72);
         let height_small = -MulDiv(10, dpi, 72);
-        let face = to_wstring(theme_font_family(theme));
+        let face = to_wstring(&theme_font_family(settings));
 
         let normal = CreateFontW(
             height_normal,
@@ -1305,14 +2482,34 @@ fn create_fonts(hdc: HDC, theme: &str) -> (HFONT, HFONT, HFONT, HFONT) {
             0,
             PCWSTR(face.as_ptr()),
         );
+        if shows_allergen_icons(settings) {
+            let store = POPUP_SYMBOL_FONTS.get_or_init(|| Mutex::new(Vec::new()));
+            if let Ok(mut registered) = store.lock() {
+                for font in [normal, bold, small, small_bold] {
+                    registered.push((font.0, symbol_font_like(font).0));
+                }
+            }
+        }
         (normal, bold, small, small_bold)
     }
 }
 
-fn build_lines(state: &AppState) -> Vec<Line> {
+fn displayed_menu(state: &AppState, day_index: Option<usize>) -> Option<&TodayMenu> {
+    let days = &state.week_menu.days;
+    day_index
+        .or_else(|| default_day_index(days, &local_date_key()))
+        .and_then(|index| days.get(index))
+        .or(state.today_menu.as_ref())
+}
+
+fn has_day_selector(state: &AppState) -> bool {
+    state.week_menu.days.len() > 1
+}
+
+fn build_lines(state: &AppState, day_index: Option<usize>) -> Vec<Line> {
     let mut lines = Vec::new();
 
-    if state.stale_date {
+    if state.stale_date && state.settings.show_stale_marker {
         lines.push(Line::Heading("[STALE]".to_string()));
     }
 
@@ -1325,45 +2522,73 @@ fn build_lines(state: &AppState) -> Vec<Line> {
         lines.push(Line::Text(text_for(&state.settings.language, "loading")));
     }
 
-    let date_line = date_and_time_line(state.today_menu.as_ref(), &state.settings.language);
+    let displayed_menu = displayed_menu(state, day_index);
+    let mut date_line = date_and_time_line(displayed_menu, &state.settings.language);
+    if state.settings.show_week_number && !date_line.is_empty() {
+        date_line = format!(
+            "{} · {}",
+            date_line,
+            week_number_label(displayed_menu, &state.settings.language)
+        );
+    }
+    if state.settings.lunch_countdown && !date_line.is_empty() {
+        if let Some(countdown) = displayed_menu
+            .filter(|menu| menu.date_iso == local_date_key())
+            .and_then(|menu| {
+                let now = local_now();
+                let now_minutes = now.hour() as i32 * 60 + now.minute() as i32;
+                lunch_countdown_text(&menu.lunch_time, now_minutes, &state.settings.language)
+            })
+        {
+            date_line = format!("{} · {}", date_line, countdown);
+        }
+    }
     if !date_line.is_empty() {
         lines.push(Line::Heading(date_line));
     }
 
-    match &state.today_menu {
-        Some(menu) => {
-            if !menu.menus.is_empty() {
-                let price_groups = PriceGroups {
-                    student: state.settings.show_student_price,
-                    staff: state.settings.show_staff_price,
-                    guest: state.settings.show_guest_price,
+    let has_menu = displayed_menu.is_some_and(|menu| menu.closed || !menu.menus.is_empty());
+    match displayed_menu {
+        Some(menu) if has_menu => {
+            if menu.closed {
+                let key = if is_closed_today(menu) {
+                    "closedToday"
+                } else {
+                    "closed"
                 };
-                append_menus(
-                    &mut lines,
-                    menu,
-                    state.provider,
-                    state.settings.show_prices,
-                    price_groups,
-                    state.settings.show_allergens,
-                    state.settings.highlight_gluten_free,
-                    state.settings.highlight_veg,
-                    state.settings.highlight_lactose_free,
-                    state.settings.hide_expensive_student_meals,
-                );
-            } else if state.status != FetchStatus::Loading {
-                lines.push(Line::Text(text_for(&state.settings.language, "noMenu")));
+                lines.push(Line::Text(text_for(&state.settings.language, key)));
+            } else {
+                let is_today = state
+                    .today_menu
+                    .as_ref()
+                    .is_some_and(|today| today.date_iso == menu.date_iso);
+                // Antell has no weekend lists; the API layer substitutes Friday's.
+                if state.provider == Provider::Antell && menu.date_iso < local_date_key() {
+                    lines.push(Line::Note(text_for(&state.settings.language, "fridayMenu")));
+                }
+                if is_today && state.same_as_previous_day {
+                    lines.push(Line::Note(text_for(
+                        &state.settings.language,
+                        "sameAsYesterday",
+                    )));
+                }
+                let codes = append_menus(&mut lines, menu, state.provider, &state.settings);
+                append_allergen_legend(&mut lines, &codes, &state.settings);
             }
         }
-        None => {
-            if state.status != FetchStatus::Loading {
-                lines.push(Line::Text(text_for(&state.settings.language, "noMenu")));
+        _ => {
+            if let Some(message) = no_menu_message(state) {
+                lines.push(Line::Text(message));
             }
         }
     }
 
-    if state.status == FetchStatus::Stale {
+    if has_menu && state.status == FetchStatus::Stale {
         lines.push(Line::Spacer);
-        let stale_key = if state.stale_network_error {
+        let timed_out = state.error_kind == FetchErrorKind::Timeout;
+        let stale_key = if state.stale_network_error && timed_out {
+            "staleTimeout"
+        } else if state.stale_network_error {
             "staleNetwork"
         } else {
             "stale"
@@ -1371,7 +2596,7 @@ fn build_lines(state: &AppState) -> Vec<Line> {
         lines.push(Line::Text(text_for(&state.settings.language, stale_key)));
     }
 
-    if !state.error_message.is_empty() && state.status != FetchStatus::Ok {
+    if has_menu && !state.error_message.is_empty() && state.status != FetchStatus::Ok {
         lines.push(Line::Text(format!(
             "{}: {}",
             text_for(&state.settings.language, "fetchError"),
@@ -1379,9 +2604,51 @@ fn build_lines(state: &AppState) -> Vec<Line> {
         )));
     }
 
+    if has_menu && state.status != FetchStatus::Loading {
+        let restaurant = restaurant_for_code(&state.settings.restaurant_code, &state.settings);
+        let language = menu_language(&state.settings, &restaurant.code);
+        if let Some(updated) = cache::cache_mtime_ms(
+            cache::cached_provider(&restaurant, language),
+            &restaurant.code,
+            language,
+        )
+        .and_then(local_datetime_from_epoch_ms)
+        {
+            let now = local_now();
+            lines.push(Line::Note(updated_text(
+                updated,
+                now,
+                &state.settings.language,
+            )));
+        }
+    }
+
     lines
 }
 
+/// The one line shown when the day has no menu. After a failed update there is no cached menu
+/// to fall back on, so the failure replaces both "no menu" and the stale notice.
+fn no_menu_message(state: &AppState) -> Option<String> {
+    let language = &state.settings.language;
+    let key = match state.status {
+        FetchStatus::Loading => return None,
+        FetchStatus::Stale | FetchStatus::Error if state.error_kind == FetchErrorKind::Timeout => {
+            "noMenuTimeout"
+        }
+        FetchStatus::Stale | FetchStatus::Error if state.error_kind.is_network() => "noMenuOffline",
+        FetchStatus::Stale | FetchStatus::Error if !state.error_message.is_empty() => {
+            return Some(format!(
+                "{}: {}",
+                text_for(language, "fetchError"),
+                state.error_message
+            ));
+        }
+        FetchStatus::Stale => "noMenuUpdateFailed",
+        _ => "noMenu",
+    };
+    Some(text_for(language, key))
+}
+
 #[derive(Debug, Clone, Copy)]
 struct CachedLayoutBudget {
     max_wrapped_lines: Option<usize>,
@@ -1403,7 +2670,7 @@ fn popup_cached_layout_budget(
     small_bold_font: HFONT,
     dpi_y: i32,
 ) -> CachedLayoutBudget {
-    let today_key = local_today_key();
+    let today_key = local_date_key();
     let key = line_budget_key(&state.settings, &today_key, dpi_y);
     let signatures = cache_signatures(&state.settings);
     if let Some(budget) = cached_line_budget(&key, &signatures) {
@@ -1430,26 +2697,41 @@ fn line_budget_key(settings: &Settings, today_key: &str, dpi_y: i32) -> PopupLin
         theme: settings.theme.clone(),
         dpi_y,
         enable_antell_restaurants: settings.enable_antell_restaurants,
+        disabled_providers: settings.disabled_providers.clone(),
         show_prices: settings.show_prices,
+        price_column: settings.price_column,
         show_student_price: settings.show_student_price,
         show_staff_price: settings.show_staff_price,
         show_guest_price: settings.show_guest_price,
         hide_expensive_student_meals: settings.hide_expensive_student_meals,
+        expensive_student_threshold_bits: settings.expensive_student_threshold_eur.to_bits(),
         show_allergens: settings.show_allergens,
+        lunch_countdown: settings.lunch_countdown,
+        allergen_icons: settings.allergen_icons,
+        text_case: settings.text_case.clone(),
+        show_energy: settings.show_energy,
+        show_allergen_legend: settings.show_allergen_legend,
         highlight_gluten_free: settings.highlight_gluten_free,
         highlight_veg: settings.highlight_veg,
         highlight_lactose_free: settings.highlight_lactose_free,
+        show_week_number: settings.show_week_number,
+        show_stale_marker: settings.show_stale_marker,
+        reverse_group_order: settings.reverse_group_order,
+        flag_repeat_menus: settings.flag_repeat_menus,
+        starred_dishes: settings.starred_dishes.clone(),
+        heading_position: settings.heading_position.clone(),
     }
 }
 
 fn cache_signatures(settings: &Settings) -> Vec<RestaurantCacheSignature> {
     let mut signatures = Vec::new();
-    for restaurant in available_restaurants(settings.enable_antell_restaurants) {
-        let mtime_ms =
-            cache::cache_mtime_ms(restaurant.provider, restaurant.code, &settings.language)
-                .unwrap_or(-1);
+    for restaurant in available_restaurants(settings) {
+        let language = menu_language(settings, &restaurant.code);
+        let provider = cache::cached_provider(&restaurant, language);
+        let mtime_ms = cache::cache_mtime_ms(provider, &restaurant.code, language).unwrap_or(-1);
         signatures.push(RestaurantCacheSignature {
             code: restaurant.code.to_string(),
+            language: language.to_string(),
             mtime_ms,
         });
     }
@@ -1502,30 +2784,26 @@ fn max_today_cached_layout_budget(
     let mut max_wrapped_lines: Option<usize> = None;
     let mut max_content_width_px: Option<i32> = None;
 
-    for restaurant in available_restaurants(settings.enable_antell_restaurants) {
-        let raw = match cache::read_cache(restaurant.provider, restaurant.code, &settings.language)
-        {
+    for restaurant in available_restaurants(settings) {
+        let language = menu_language(settings, &restaurant.code);
+        let provider = cache::cached_provider(&restaurant, language);
+        let raw = match cache::read_cache(provider, &restaurant.code, language) {
             Some(payload) => payload,
             None => continue,
         };
 
-        let parsed = match api::parse_cached_payload(
-            &raw,
-            restaurant.provider,
-            restaurant,
-            &settings.language,
-        ) {
+        let parsed = match api::parse_cached_payload(&raw, provider, &restaurant, language) {
             Ok(value) => value,
             Err(_) => continue,
         };
 
-        if !parsed.ok || !is_today_valid_cache(&parsed, restaurant, settings, today_key) {
+        if !parsed.ok || !is_today_valid_cache(&parsed, &restaurant, settings, today_key) {
             continue;
         }
 
         let candidate_state =
-            popup_state_from_cached_result(settings, restaurant, &parsed, today_key);
-        let candidate_lines = build_lines(&candidate_state);
+            popup_state_from_cached_result(settings, &restaurant, &parsed, today_key);
+        let candidate_lines = build_lines(&candidate_state, None);
         let metrics = measure_lines_layout(
             hdc,
             normal_font,
@@ -1555,13 +2833,14 @@ fn max_today_cached_layout_budget(
 
 fn is_today_valid_cache(
     parsed: &api::FetchOutput,
-    restaurant: Restaurant,
+    restaurant: &Restaurant,
     settings: &Settings,
     today_key: &str,
 ) -> bool {
     match restaurant.provider {
-        Provider::Antell => {
-            cache::cache_mtime_ms(restaurant.provider, restaurant.code, &settings.language)
+        Provider::Antell | Provider::SodexoJson => {
+            let language = menu_language(settings, &restaurant.code);
+            cache::cache_mtime_ms(restaurant.provider, &restaurant.code, language)
                 .and_then(date_key_from_epoch_ms)
                 .is_some_and(|date| date == today_key)
         }
@@ -1571,7 +2850,7 @@ fn is_today_valid_cache(
 
 fn popup_state_from_cached_result(
     settings: &Settings,
-    restaurant: Restaurant,
+    restaurant: &Restaurant,
     parsed: &api::FetchOutput,
     today_key: &str,
 ) -> AppState {
@@ -1581,8 +2860,12 @@ fn popup_state_from_cached_result(
         parsed.restaurant_name.clone()
     };
 
+    // Point the candidate at its own restaurant so per-restaurant lines (like the footer) match.
+    let mut settings = settings.clone();
+    settings.restaurant_code = restaurant.code.to_string();
+
     AppState {
-        settings: settings.clone(),
+        settings,
         status: if parsed.ok {
             FetchStatus::Ok
         } else {
@@ -1590,169 +2873,303 @@ fn popup_state_from_cached_result(
         },
         loading_started_epoch_ms: 0,
         error_message: parsed.error_message.clone(),
+        error_kind: parsed.error_kind,
         stale_network_error: false,
         today_menu: parsed.today_menu.clone(),
+        week_menu: parsed.week_menu.clone(),
+        selected_day: None,
         restaurant_name,
         restaurant_url: parsed.restaurant_url.clone(),
         raw_payload: String::new(),
-        provider: restaurant.provider,
+        provider: parsed.provider,
         payload_date: parsed.payload_date.clone(),
         stale_date: !parsed.payload_date.is_empty() && parsed.payload_date != today_key,
+        same_as_previous_day: false,
     }
 }
 
-fn local_today_key() -> String {
-    let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
-    let date = now.date();
-    format!(
-        "{:04}-{:02}-{:02}",
-        date.year(),
-        date.month() as u8,
-        date.day()
-    )
-}
-
-fn date_key_from_epoch_ms(ms: i64) -> Option<String> {
-    if ms <= 0 {
-        return None;
-    }
-
-    let secs = ms / 1000;
-    let nanos = ((ms % 1000) * 1_000_000) as u32;
-    let mut dt = OffsetDateTime::from_unix_timestamp(secs).ok()?;
-    dt = dt.replace_nanosecond(nanos).ok()?;
-    let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
-    let local = dt.to_offset(offset);
-    let date = local.date();
-    Some(format!(
-        "{:04}-{:02}-{:02}",
-        date.year(),
-        date.month() as u8,
-        date.day()
-    ))
-}
-
-fn position_near_point(width: i32, height: i32, point: POINT) -> (i32, i32) {
+fn work_area_near(point: POINT) -> RECT {
     unsafe {
         let monitor = MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST);
         let mut info = MONITORINFO::default();
         info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
-        let mut work_area = RECT::default();
-        if GetMonitorInfoW(monitor, &mut info).as_bool() {
-            work_area = info.rcWork;
+        if GetMonitorInfoW(monitor, &mut info).as_bool() && !is_empty_rect(&info.rcWork) {
+            return info.rcWork;
         }
 
-        let mut x = point.x - width;
-        let mut y = point.y - height;
-        if x < work_area.left {
-            x = work_area.left;
-        }
-        if y < work_area.top {
-            y = work_area.top;
-        }
-        if x + width > work_area.right {
-            x = work_area.right - width;
-        }
-        if y + height > work_area.bottom {
-            y = work_area.bottom - height;
+        let mut primary = RECT::default();
+        if SystemParametersInfoW(
+            SPI_GETWORKAREA,
+            0,
+            Some(&mut primary as *mut RECT as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+        .is_ok()
+            && !is_empty_rect(&primary)
+        {
+            return primary;
         }
 
-        (x, y)
+        let left = GetSystemMetrics(SM_XVIRTUALSCREEN);
+        let top = GetSystemMetrics(SM_YVIRTUALSCREEN);
+        RECT {
+            left,
+            top,
+            right: left + GetSystemMetrics(SM_CXVIRTUALSCREEN).max(1),
+            bottom: top + GetSystemMetrics(SM_CYVIRTUALSCREEN).max(1),
+        }
     }
 }
 
-fn position_near_tray_rect(width: i32, height: i32, tray_rect: RECT) -> (i32, i32) {
+/// Full bounds of the monitor nearest `point`, taskbar included.
+fn monitor_rect_near(point: POINT) -> RECT {
     unsafe {
-        let center = POINT {
-            x: (tray_rect.left + tray_rect.right) / 2,
-            y: (tray_rect.top + tray_rect.bottom) / 2,
+        let monitor = MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
         };
-        let monitor = MonitorFromPoint(center, MONITOR_DEFAULTTONEAREST);
-        let mut info = MONITORINFO::default();
-        info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
-        let mut work_area = RECT::default();
-        if GetMonitorInfoW(monitor, &mut info).as_bool() {
-            work_area = info.rcWork;
+        if GetMonitorInfoW(monitor, &mut info).as_bool() && !is_empty_rect(&info.rcMonitor) {
+            return info.rcMonitor;
         }
+    }
+    work_area_near(point)
+}
 
-        let mut x = tray_rect.right - width;
-        let mut y = tray_rect.top - height - ANCHOR_GAP;
+fn is_empty_rect(rect: &RECT) -> bool {
+    rect.right <= rect.left || rect.bottom <= rect.top
+}
 
-        if y < work_area.top {
-            y = tray_rect.bottom + ANCHOR_GAP;
-        }
-        if y + height > work_area.bottom {
-            y = (tray_rect.top - height - ANCHOR_GAP).max(work_area.top);
-        }
+fn position_near_point(width: i32, height: i32, point: POINT) -> (i32, i32) {
+    let work_area = work_area_near(point);
 
-        if x < work_area.left {
-            x = work_area.left;
-        }
-        if x + width > work_area.right {
-            x = work_area.right - width;
-        }
-        if y < work_area.top {
-            y = work_area.top;
-        }
-        if y + height > work_area.bottom {
-            y = work_area.bottom - height;
-        }
+    let mut x = point.x - width;
+    let mut y = point.y - height;
+    if x < work_area.left {
+        x = work_area.left;
+    }
+    if y < work_area.top {
+        y = work_area.top;
+    }
+    if x + width > work_area.right {
+        x = work_area.right - width;
+    }
+    if y + height > work_area.bottom {
+        y = work_area.bottom - height;
+    }
+
+    (x, y)
+}
+
+/// Like `position_near_point`, but keeps the top edge at `point.y` so the popup grows down.
+fn position_below_point(width: i32, height: i32, point: POINT) -> (i32, i32) {
+    let work_area = work_area_near(point);
+
+    let mut x = point.x - width;
+    let mut y = point.y;
+    if x < work_area.left {
+        x = work_area.left;
+    }
+    if x + width > work_area.right {
+        x = work_area.right - width;
+    }
+    if y + height > work_area.bottom {
+        y = work_area.bottom - height;
+    }
+    if y < work_area.top {
+        y = work_area.top;
+    }
+
+    (x, y)
+}
+
+/// Top-right or bottom-right corner of the primary monitor's work area, when `popup_anchor`
+/// pins the popup there; `None` for the cursor and tray anchors.
+fn fixed_corner_position(state: &AppState, width: i32, height: i32) -> Option<(i32, i32)> {
+    let top = match state.settings.popup_anchor.as_str() {
+        "fixed_top_right" => true,
+        "fixed_bottom_right" => false,
+        _ => return None,
+    };
+    // The virtual-screen origin is the primary monitor's top-left corner.
+    let work_area = work_area_near(POINT { x: 0, y: 0 });
+    let x = (work_area.right - width - ANCHOR_GAP).max(work_area.left);
+    let y = if top {
+        work_area.top + ANCHOR_GAP
+    } else {
+        (work_area.bottom - height - ANCHOR_GAP).max(work_area.top)
+    };
+    Some((x, y))
+}
+
+fn position_near_tray_rect(width: i32, height: i32, tray_rect: RECT) -> (i32, i32) {
+    let center = POINT {
+        x: (tray_rect.left + tray_rect.right) / 2,
+        y: (tray_rect.top + tray_rect.bottom) / 2,
+    };
+    let work_area = work_area_near(center);
+
+    let mut x = tray_rect.right - width;
+    let mut y = tray_rect.top - height - ANCHOR_GAP;
+
+    if y < work_area.top {
+        y = tray_rect.bottom + ANCHOR_GAP;
+    }
+    if y + height > work_area.bottom {
+        y = (tray_rect.top - height - ANCHOR_GAP).max(work_area.top);
+    }
 
-        (x, y)
+    if x < work_area.left {
+        x = work_area.left;
+    }
+    if x + width > work_area.right {
+        x = work_area.right - width;
+    }
+    if y < work_area.top {
+        y = work_area.top;
     }
+    if y + height > work_area.bottom {
+        y = work_area.bottom - height;
+    }
+
+    (x, y)
 }
 
+/// Appends the menu groups; returns the allergen codes seen, in first-seen order.
 fn append_menus(
     lines: &mut Vec<Line>,
     menu: &TodayMenu,
     provider: Provider,
-    show_prices: bool,
-    price_groups: PriceGroups,
-    show_allergens: bool,
-    highlight_gluten_free: bool,
-    highlight_veg: bool,
-    highlight_lactose_free: bool,
-    hide_expensive_student_meals: bool,
-) {
-    for group in &menu.menus {
-        if provider == Provider::Compass && hide_expensive_student_meals {
-            if let Some(price) = student_price_eur(&group.price) {
-                if price > 4.0 {
+    settings: &Settings,
+) -> Vec<String> {
+    let mut codes: Vec<String> = Vec::new();
+    let price_groups = PriceGroups {
+        student: settings.show_student_price,
+        staff: settings.show_staff_price,
+        guest: settings.show_guest_price,
+    };
+    let groups: Vec<&MenuGroup> = if settings.reverse_group_order {
+        menu.menus.iter().rev().collect()
+    } else {
+        menu.menus.iter().collect()
+    };
+    // Dish text is in the restaurant's menu language; only labels use the UI language.
+    let language = menu_language(settings, &settings.restaurant_code);
+    let headings_below = settings.heading_position == "below";
+    let allergen_icons = shows_allergen_icons(settings);
+    let mut first_group = true;
+    for group in groups {
+        let compass = matches!(provider, Provider::Compass | Provider::CompassRss);
+        if compass && settings.hide_expensive_student_meals {
+            if let Some(price) = student_price_eur(&group.prices) {
+                if price > settings.expensive_student_threshold_eur {
                     continue;
                 }
             }
         }
 
-        let heading = menu_heading(group, provider, show_prices, price_groups);
-        lines.push(Line::Heading(heading));
-        for component in &group.components {
+        let (name, price) = menu_heading_parts(group, provider, settings.show_prices, price_groups);
+        let heading = if settings.price_column && !price.is_empty() {
+            Line::HeadingWithPrice {
+                name: apply_text_case(&name, &settings.text_case, language),
+                price,
+            }
+        } else {
+            Line::Heading(apply_text_case(
+                &menu_heading(group, provider, settings.show_prices, price_groups),
+                &settings.text_case,
+                language,
+            ))
+        };
+        if headings_below {
+            if !first_group {
+                lines.push(Line::Spacer);
+            }
+        } else {
+            lines.push(heading.clone());
+        }
+        first_group = false;
+        for (index, component) in group.components.iter().enumerate() {
             let component = normalize_text(component);
             if component.is_empty() {
                 continue;
             }
-            let (main, suffix) = split_component_suffix(&component);
+            let (main, suffix) = split_component_suffix(&component, language);
             if main.is_empty() {
                 continue;
             }
-            let main_text = main;
-            if !show_allergens {
-                lines.push(Line::Text(format!("▸ {}", main_text)));
-            } else if !suffix.is_empty() {
-                let segments = build_suffix_segments(
+            for code in suffix.split(|ch: char| !ch.is_alphabetic()) {
+                if !code.is_empty() && !codes.iter().any(|seen| seen.eq_ignore_ascii_case(code)) {
+                    codes.push(code.to_string());
+                }
+            }
+            let starred = is_starred_dish(&settings.starred_dishes, &main);
+            let favorite = settings.highlight_favorites
+                && matches_favorite_keyword(&settings.favorite_keywords, &main);
+            let segments = if settings.show_allergens && !suffix.is_empty() {
+                build_suffix_segments(
                     &suffix,
-                    highlight_gluten_free,
-                    highlight_veg,
-                    highlight_lactose_free,
-                );
-                lines.push(Line::TextWithSuffixSegments {
-                    main: format!("▸ {}", main_text),
-                    segments,
-                });
+                    settings.highlight_gluten_free,
+                    settings.highlight_veg,
+                    settings.highlight_lactose_free,
+                    allergen_icons,
+                )
             } else {
-                lines.push(Line::Text(format!("▸ {}", main_text)));
-            }
+                Vec::new()
+            };
+            let marker = if starred { "★" } else { "▸" };
+            let energy = group
+                .energy
+                .get(index)
+                .filter(|energy| settings.show_energy && !energy.is_empty())
+                .map(|energy| format!(" · {}", energy))
+                .unwrap_or_default();
+            let display = apply_text_case(&main, &settings.text_case, language);
+            lines.push(Line::TextWithSuffixSegments {
+                main: format!("{} {}{}", marker, display, energy),
+                segments,
+                dish: main,
+                starred,
+                favorite,
+            });
+        }
+        if headings_below {
+            lines.push(heading);
         }
     }
+    codes
+}
+
+fn append_allergen_legend(lines: &mut Vec<Line>, codes: &[String], settings: &Settings) {
+    if !settings.show_allergens || !settings.show_allergen_legend {
+        return;
+    }
+    let entries: Vec<String> = codes
+        .iter()
+        .filter_map(|code| {
+            let line = allergen_legend_line(code, &settings.language)?;
+            Some(
+                match allergen_icon(code).filter(|_| shows_allergen_icons(settings)) {
+                    Some(icon) => format!("{} {}", icon, line),
+                    None => line,
+                },
+            )
+        })
+        .collect();
+    if entries.is_empty() {
+        return;
+    }
+    lines.push(Line::Spacer);
+    lines.push(Line::Heading(text_for(
+        &settings.language,
+        "allergenLegend",
+    )));
+    lines.extend(entries.into_iter().map(Line::Note));
+}
+
+/// Teletext keeps the letters: the symbols do not fit its fixed character grid.
+fn shows_allergen_icons(settings: &Settings) -> bool {
+    settings.allergen_icons && !matches!(settings.theme.as_str(), "teletext1" | "teletext2")
 }
 
 fn build_suffix_segments(
@@ -1760,6 +3177,7 @@ fn build_suffix_segments(
     highlight_gluten_free: bool,
     highlight_veg: bool,
     highlight_lactose_free: bool,
+    icons: bool,
 ) -> Vec<(String, bool)> {
     let mut segments = Vec::new();
     let mut current = String::new();
@@ -1773,7 +3191,11 @@ fn build_suffix_segments(
         let highlight = (upper == "G" && highlight_gluten_free)
             || (upper == "VEG" && highlight_veg)
             || (upper == "L" && highlight_lactose_free);
-        out.push((token.to_string(), highlight));
+        let shown = icons
+            .then(|| allergen_icon(token))
+            .flatten()
+            .unwrap_or(token);
+        out.push((shown.to_string(), highlight));
     };
 
     for ch in suffix.chars() {
@@ -1844,12 +3266,20 @@ struct ThemePalette {
     header_title_color: COLORREF,
     suffix_color: COLORREF,
     suffix_highlight_color: COLORREF,
+    star_color: COLORREF,
     header_bg_color: COLORREF,
     button_bg_color: COLORREF,
     divider_color: COLORREF,
 }
 
-fn theme_palette(theme: &str) -> ThemePalette {
+fn theme_palette(settings: &Settings) -> ThemePalette {
+    if settings.theme == "custom" {
+        return custom_palette(settings.custom_theme.as_ref());
+    }
+    named_palette(&settings.theme)
+}
+
+fn named_palette(theme: &str) -> ThemePalette {
     match theme {
         "light" => ThemePalette {
             bg_color: COLORREF(0x00FFFFFF),
@@ -1858,6 +3288,7 @@ fn theme_palette(theme: &str) -> ThemePalette {
             header_title_color: COLORREF(0x00000000),
             suffix_color: COLORREF(0x00808080),
             suffix_highlight_color: COLORREF(0x00808080),
+            star_color: rgb(176, 120, 0),
             header_bg_color: COLORREF(0x00F3F3F3),
             button_bg_color: COLORREF(0x00DDDDDD),
             divider_color: COLORREF(0x00C9C9C9),
@@ -1869,6 +3300,7 @@ fn theme_palette(theme: &str) -> ThemePalette {
             header_title_color: COLORREF(0x00FFFFFF),
             suffix_color: COLORREF(0x00E7C7A7),
             suffix_highlight_color: COLORREF(0x00E7C7A7),
+            star_color: rgb(255, 214, 102),
             header_bg_color: COLORREF(0x00733809),
             button_bg_color: COLORREF(0x00804A1A),
             divider_color: COLORREF(0x00834D1F),
@@ -1880,6 +3312,7 @@ fn theme_palette(theme: &str) -> ThemePalette {
             header_title_color: COLORREF(0x0000D000),
             suffix_color: COLORREF(0x00009000),
             suffix_highlight_color: COLORREF(0x0000D000),
+            star_color: rgb(160, 255, 160),
             header_bg_color: COLORREF(0x000B1A0B),
             button_bg_color: COLORREF(0x00142D14),
             divider_color: COLORREF(0x00142D14),
@@ -1891,6 +3324,7 @@ fn theme_palette(theme: &str) -> ThemePalette {
             header_title_color: rgb(255, 255, 0),
             suffix_color: rgb(0, 255, 0),
             suffix_highlight_color: rgb(255, 0, 255),
+            star_color: rgb(255, 255, 0),
             header_bg_color: rgb(0, 0, 180),
             button_bg_color: rgb(0, 0, 140),
             divider_color: rgb(255, 0, 0),
@@ -1902,6 +3336,7 @@ fn theme_palette(theme: &str) -> ThemePalette {
             header_title_color: rgb(0, 96, 255),
             suffix_color: rgb(0, 255, 150),
             suffix_highlight_color: rgb(255, 255, 0),
+            star_color: rgb(255, 128, 0),
             header_bg_color: rgb(0, 215, 0),
             button_bg_color: rgb(0, 145, 0),
             divider_color: rgb(255, 0, 255),
@@ -1913,6 +3348,7 @@ fn theme_palette(theme: &str) -> ThemePalette {
             header_title_color: COLORREF(0x00FFFFFF),
             suffix_color: COLORREF(0x00B0B0B0),
             suffix_highlight_color: COLORREF(0x00B0B0B0),
+            star_color: rgb(255, 200, 60),
             header_bg_color: COLORREF(0x00101010),
             button_bg_color: COLORREF(0x00202020),
             divider_color: COLORREF(0x00202020),
@@ -1920,14 +3356,57 @@ fn theme_palette(theme: &str) -> ThemePalette {
     }
 }
 
+fn custom_palette(custom: Option<&CustomTheme>) -> ThemePalette {
+    let fallback = named_palette("dark");
+    let Some(custom) = custom else {
+        return fallback;
+    };
+    let pick = |value: &Option<String>, default: COLORREF| {
+        value
+            .as_deref()
+            .and_then(parse_hex_color)
+            .map(|(r, g, b)| rgb(r, g, b))
+            .unwrap_or(default)
+    };
+    ThemePalette {
+        bg_color: pick(&custom.bg_color, fallback.bg_color),
+        body_text_color: pick(&custom.body_text_color, fallback.body_text_color),
+        heading_color: pick(&custom.heading_color, fallback.heading_color),
+        header_title_color: pick(&custom.header_title_color, fallback.header_title_color),
+        suffix_color: pick(&custom.suffix_color, fallback.suffix_color),
+        suffix_highlight_color: pick(
+            &custom.suffix_highlight_color,
+            fallback.suffix_highlight_color,
+        ),
+        star_color: pick(&custom.star_color, fallback.star_color),
+        header_bg_color: pick(&custom.header_bg_color, fallback.header_bg_color),
+        button_bg_color: pick(&custom.button_bg_color, fallback.button_bg_color),
+        divider_color: pick(&custom.divider_color, fallback.divider_color),
+    }
+}
+
+/// Background and text colors for the weekday badge on the tray icon.
+pub fn tray_badge_colors(settings: &Settings) -> (COLORREF, COLORREF) {
+    let palette = theme_palette(settings);
+    (palette.header_bg_color, palette.header_title_color)
+}
+
 fn rgb(r: u8, g: u8, b: u8) -> COLORREF {
     COLORREF((r as u32) | ((g as u32) << 8) | ((b as u32) << 16))
 }
 
-fn theme_font_family(theme: &str) -> &'static str {
-    match theme {
-        "teletext1" | "teletext2" => "Consolas",
-        _ => "Segoe UI",
+fn theme_font_family(settings: &Settings) -> String {
+    match settings.theme.as_str() {
+        "teletext1" | "teletext2" => "Consolas".to_string(),
+        "custom" => settings
+            .custom_theme
+            .as_ref()
+            .and_then(|custom| custom.font_family.as_deref())
+            .map(str::trim)
+            .filter(|family| !family.is_empty())
+            .unwrap_or("Segoe UI")
+            .to_string(),
+        _ => "Segoe UI".to_string(),
     }
 }
 
@@ -1939,3 +3418,301 @@ fn is_visible(hwnd: HWND) -> bool {
 fn MulDiv(n_number: i32, n_numerator: i32, n_denominator: i32) -> i32 {
     ((n_number as i64 * n_numerator as i64) / n_denominator as i64) as i32
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_region_alpha, build_lines, build_suffix_segments, menu_plain_text, token_row_breaks,
+        Line,
+    };
+    use crate::api::FetchErrorKind;
+    use crate::app::{AppState, FetchStatus};
+    use crate::model::{MenuGroup, TodayMenu, WeekMenu};
+    use crate::restaurant::Provider;
+    use crate::settings::Settings;
+    use crate::util::local_date_key;
+    use std::collections::BTreeMap;
+
+    fn state(status: FetchStatus, error_kind: FetchErrorKind, error_message: &str) -> AppState {
+        AppState {
+            settings: Settings {
+                language: "en".to_string(),
+                ..Settings::default()
+            },
+            status,
+            loading_started_epoch_ms: 0,
+            error_message: error_message.to_string(),
+            error_kind,
+            stale_network_error: error_kind.is_network(),
+            today_menu: None,
+            week_menu: WeekMenu::default(),
+            selected_day: None,
+            restaurant_name: String::new(),
+            restaurant_url: String::new(),
+            raw_payload: String::new(),
+            provider: Provider::Compass,
+            payload_date: String::new(),
+            stale_date: false,
+            same_as_previous_day: false,
+        }
+    }
+
+    fn texts(state: &AppState) -> Vec<String> {
+        build_lines(state, None)
+            .into_iter()
+            .filter_map(|line| match line {
+                Line::Text(text) => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn missing_menu_without_failure_says_no_menu() {
+        let expected = vec!["No lunch menu available for today.".to_string()];
+        assert_eq!(
+            texts(&state(FetchStatus::Ok, FetchErrorKind::None, "")),
+            expected
+        );
+        assert_eq!(
+            texts(&state(FetchStatus::Idle, FetchErrorKind::None, "")),
+            expected
+        );
+        assert!(texts(&state(FetchStatus::Loading, FetchErrorKind::None, "")).is_empty());
+    }
+
+    #[test]
+    fn missing_menu_after_failure_is_one_message() {
+        assert_eq!(
+            texts(&state(FetchStatus::Stale, FetchErrorKind::Network, "dns")),
+            vec!["Offline and no saved menu to show.".to_string()]
+        );
+        assert_eq!(
+            texts(&state(
+                FetchStatus::Error,
+                FetchErrorKind::Timeout,
+                "timed out"
+            )),
+            vec!["Update timed out and no saved menu to show.".to_string()]
+        );
+        assert_eq!(
+            texts(&state(
+                FetchStatus::Error,
+                FetchErrorKind::HttpStatus(500),
+                "HTTP 500"
+            )),
+            vec!["Fetch error: HTTP 500".to_string()]
+        );
+        assert_eq!(
+            texts(&state(FetchStatus::Stale, FetchErrorKind::Parse, "")),
+            vec!["Update failed and no saved menu to show.".to_string()]
+        );
+    }
+
+    #[test]
+    fn stale_notice_follows_a_shown_menu() {
+        let mut state = state(FetchStatus::Stale, FetchErrorKind::Network, "dns");
+        state.today_menu = Some(TodayMenu {
+            date_iso: local_date_key(),
+            lunch_time: String::new(),
+            menus: vec![MenuGroup {
+                name: "Lunch".to_string(),
+                price: String::new(),
+                components: vec!["Soup".to_string()],
+                energy: Vec::new(),
+                prices: Vec::new(),
+            }],
+            closed: false,
+        });
+        let texts = texts(&state);
+        assert!(texts.contains(&"Offline. Showing last cached menu.".to_string()));
+        assert!(texts.contains(&"Fetch error: dns".to_string()));
+    }
+
+    #[test]
+    fn price_column_splits_headings_that_have_a_price() {
+        let group = |name: &str, price: &str| MenuGroup {
+            name: name.to_string(),
+            price: price.to_string(),
+            components: vec!["Soup".to_string()],
+            energy: Vec::new(),
+            prices: Vec::new(),
+        };
+        let mut state = state(FetchStatus::Ok, FetchErrorKind::None, "");
+        state.provider = Provider::Antell;
+        state.settings.show_prices = true;
+        state.settings.price_column = true;
+        state.today_menu = Some(TodayMenu {
+            date_iso: local_date_key(),
+            lunch_time: String::new(),
+            menus: vec![group("Lunch", "12,70 €"), group("Dessert", "")],
+            closed: false,
+        });
+        let headings: Vec<String> = build_lines(&state, None)
+            .into_iter()
+            .filter_map(|line| match line {
+                Line::HeadingWithPrice { name, price } => Some(format!("{}|{}", name, price)),
+                Line::Heading(text) if text == "Dessert" => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(headings, vec!["Lunch|12,70 €", "Dessert"]);
+    }
+
+    #[test]
+    fn dish_text_uses_the_restaurant_menu_language() {
+        let mut state = state(FetchStatus::Ok, FetchErrorKind::None, "");
+        state.settings.language = "fi".to_string();
+        state.settings.language_overrides =
+            BTreeMap::from([("0437".to_string(), "en".to_string())]);
+        state.settings.show_allergen_legend = true;
+        state.today_menu = Some(TodayMenu {
+            date_iso: local_date_key(),
+            lunch_time: String::new(),
+            menus: vec![MenuGroup {
+                name: "Lunch".to_string(),
+                price: String::new(),
+                components: vec!["Chicken curry (GF, VE)".to_string()],
+                energy: Vec::new(),
+                prices: Vec::new(),
+            }],
+            closed: false,
+        });
+        let lines = build_lines(&state, None);
+        let suffixes: Vec<String> = lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::TextWithSuffixSegments { segments, .. } => {
+                    Some(segments.iter().map(|(text, _)| text.as_str()).collect())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(suffixes, vec!["(G, Veg)"]);
+        assert!(lines
+            .iter()
+            .any(|line| matches!(line, Line::Heading(text) if text == "Merkinnät")));
+        assert!(lines
+            .iter()
+            .any(|line| matches!(line, Line::Note(note) if note.starts_with("G = "))));
+    }
+
+    #[test]
+    fn plain_text_lists_every_group_for_the_clipboard() {
+        let mut state = state(FetchStatus::Ok, FetchErrorKind::None, "");
+        state.today_menu = Some(TodayMenu {
+            date_iso: local_date_key(),
+            lunch_time: String::new(),
+            menus: vec![
+                MenuGroup {
+                    name: "Lunch".to_string(),
+                    price: String::new(),
+                    components: vec!["Chicken curry (G, L)".to_string(), "Rice".to_string()],
+                    energy: Vec::new(),
+                    prices: Vec::new(),
+                },
+                MenuGroup {
+                    name: "Soup".to_string(),
+                    price: String::new(),
+                    components: vec!["Salmon soup".to_string()],
+                    energy: Vec::new(),
+                    prices: Vec::new(),
+                },
+            ],
+            closed: false,
+        });
+        let text = menu_plain_text(&state);
+        let lines: Vec<&str> = text.split("\r\n").collect();
+        assert!(lines[0].starts_with("Snellmania ("), "{}", lines[0]);
+        // lines[1] is today's date heading.
+        assert_eq!(
+            lines[2..],
+            [
+                "Lunch",
+                "▸ Chicken curry (G, L)",
+                "▸ Rice",
+                "Soup",
+                "▸ Salmon soup"
+            ]
+        );
+    }
+
+    #[test]
+    fn starred_dishes_get_the_star_marker() {
+        let mut state = state(FetchStatus::Ok, FetchErrorKind::None, "");
+        state.settings.starred_dishes = vec!["chicken curry".to_string()];
+        state.today_menu = Some(TodayMenu {
+            date_iso: local_date_key(),
+            lunch_time: String::new(),
+            menus: vec![MenuGroup {
+                name: "Lunch".to_string(),
+                price: String::new(),
+                components: vec!["Chicken Curry (G)".to_string(), "Rice".to_string()],
+                energy: Vec::new(),
+                prices: Vec::new(),
+            }],
+            closed: false,
+        });
+        let dishes: Vec<(String, bool)> = build_lines(&state, None)
+            .into_iter()
+            .filter_map(|line| match line {
+                Line::TextWithSuffixSegments { main, starred, .. } => Some((main, starred)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            dishes,
+            vec![
+                ("★ Chicken Curry".to_string(), true),
+                ("▸ Rice".to_string(), false)
+            ]
+        );
+    }
+
+    #[test]
+    fn allergen_icons_replace_known_codes_only() {
+        let shown = |icons| {
+            build_suffix_segments("(G, L, Veg)", true, false, false, icons)
+                .into_iter()
+                .map(|(text, _)| text)
+                .collect::<String>()
+        };
+        assert_eq!(shown(false), "(G, L, Veg)");
+        assert_eq!(shown(true), "(\u{1F33E}\u{20E0}, L, \u{1F331})");
+        let highlighted = build_suffix_segments("G", true, false, false, true);
+        assert_eq!(highlighted, vec![("\u{1F33E}\u{20E0}".to_string(), true)]);
+    }
+
+    #[test]
+    fn long_tokens_break_after_separators() {
+        let rows = |token: &str, max_width| {
+            let chars: Vec<char> = token.chars().collect();
+            let mut start = 0;
+            token_row_breaks(&chars, &vec![10; chars.len()], max_width)
+                .into_iter()
+                .map(|end| {
+                    let row: String = chars[start..end].iter().collect();
+                    start = end;
+                    row
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            rows("pippuri-rakuunakastiketta", 150),
+            vec!["pippuri-", "rakuunakastiket", "ta"]
+        );
+        assert_eq!(rows("kana/kasvis", 80), vec!["kana/", "kasvis"]);
+        assert_eq!(rows("kukkakaali", 40), vec!["kukk", "akaa", "li"]);
+        assert_eq!(rows("abc", 5), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn region_alpha_premultiplies_per_band() {
+        let mut pixels = vec![0x00FF_8040; 6];
+        apply_region_alpha(&mut pixels, 2, 1, 255, 128);
+        assert_eq!(&pixels[..2], &[0xFFFF_8040, 0xFFFF_8040]);
+        assert_eq!(&pixels[2..], &[0x8080_4020; 4]);
+        apply_region_alpha(&mut pixels, 0, 1, 0, 0);
+        assert_eq!(pixels[0], 0xFFFF_8040);
+    }
+}
@@ -1,109 +1,283 @@
+use crate::log::log_line;
+use crate::settings::{settings_dir, Settings};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fs;
+use std::sync::OnceLock;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Provider {
     Compass,
     CompassRss,
     Antell,
     HuomenJson,
+    SodexoJson,
+    UnicaJson,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Restaurant {
-    pub code: &'static str,
-    pub name: &'static str,
+    pub code: Cow<'static, str>,
+    pub name: Cow<'static, str>,
     pub provider: Provider,
-    pub antell_slug: Option<&'static str>,
-    pub rss_cost_number: Option<&'static str>,
-    pub huomen_api_base: Option<&'static str>,
-    pub url: Option<&'static str>,
+    pub antell_slug: Option<Cow<'static, str>>,
+    pub cost_number: Option<Cow<'static, str>>,
+    /// RSS cost number of the same restaurant, tried when its Compass JSON feed fails.
+    pub fallback_rss: Option<Cow<'static, str>>,
+    pub huomen_api_base: Option<Cow<'static, str>>,
+    pub sodexo_meal_id: Option<Cow<'static, str>>,
+    pub unica_restaurant_id: Option<Cow<'static, str>>,
+    pub url: Option<Cow<'static, str>>,
 }
 
 const CORE_RESTAURANTS: [Restaurant; 5] = [
     Restaurant {
-        code: "0437",
-        name: "Snellmania",
+        code: Cow::Borrowed("0437"),
+        name: Cow::Borrowed("Snellmania"),
         provider: Provider::Compass,
         antell_slug: None,
-        rss_cost_number: None,
+        cost_number: None,
+        fallback_rss: None,
         huomen_api_base: None,
+        sodexo_meal_id: None,
+        unica_restaurant_id: None,
         url: None,
     },
     Restaurant {
-        code: "snellari-rss",
-        name: "Cafe Snellari",
+        code: Cow::Borrowed("snellari-rss"),
+        name: Cow::Borrowed("Cafe Snellari"),
         provider: Provider::CompassRss,
         antell_slug: None,
-        rss_cost_number: Some("4370"),
+        cost_number: Some(Cow::Borrowed("4370")),
+        fallback_rss: None,
         huomen_api_base: None,
-        url: Some(
-            "https://www.compass-group.fi/ravintolat-ja-ruokalistat/foodco/kaupungit/kuopio/cafe-snellari/",
-        ),
+        sodexo_meal_id: None,
+        unica_restaurant_id: None,
+        url: Some(Cow::Borrowed("https://www.compass-group.fi/ravintolat-ja-ruokalistat/foodco/kaupungit/kuopio/cafe-snellari/")),
     },
     Restaurant {
-        code: "0436",
-        name: "Canthia",
+        code: Cow::Borrowed("0436"),
+        name: Cow::Borrowed("Canthia"),
         provider: Provider::Compass,
         antell_slug: None,
-        rss_cost_number: None,
+        cost_number: None,
+        fallback_rss: None,
         huomen_api_base: None,
+        sodexo_meal_id: None,
+        unica_restaurant_id: None,
         url: None,
     },
     Restaurant {
-        code: "0439",
-        name: "Tietoteknia",
+        code: Cow::Borrowed("0439"),
+        name: Cow::Borrowed("Tietoteknia"),
         provider: Provider::Compass,
         antell_slug: None,
-        rss_cost_number: None,
+        cost_number: None,
+        fallback_rss: None,
         huomen_api_base: None,
+        sodexo_meal_id: None,
+        unica_restaurant_id: None,
         url: None,
     },
     Restaurant {
-        code: "huomen-bioteknia",
-        name: "Hyvä Huomen Bioteknia",
+        code: Cow::Borrowed("huomen-bioteknia"),
+        name: Cow::Borrowed("Hyvä Huomen Bioteknia"),
         provider: Provider::HuomenJson,
         antell_slug: None,
-        rss_cost_number: None,
-        huomen_api_base: Some(
-            "https://europe-west1-luncher-7cf76.cloudfunctions.net/api/v1/week/a96b7ccf-2c3d-432a-8504-971dbb6d55d3/active",
-        ),
-        url: Some("https://hyvahuomen.fi/bioteknia/"),
+        cost_number: None,
+        fallback_rss: None,
+        huomen_api_base: Some(Cow::Borrowed("https://europe-west1-luncher-7cf76.cloudfunctions.net/api/v1/week/a96b7ccf-2c3d-432a-8504-971dbb6d55d3/active")),
+        sodexo_meal_id: None,
+        unica_restaurant_id: None,
+        url: Some(Cow::Borrowed("https://hyvahuomen.fi/bioteknia/")),
     },
 ];
 
 const ANTELL_RESTAURANTS: [Restaurant; 2] = [
     Restaurant {
-        code: "antell-round",
-        name: "Antell Round",
+        code: Cow::Borrowed("antell-round"),
+        name: Cow::Borrowed("Antell Round"),
         provider: Provider::Antell,
-        antell_slug: Some("round"),
-        rss_cost_number: None,
+        antell_slug: Some(Cow::Borrowed("round")),
+        cost_number: None,
+        fallback_rss: None,
         huomen_api_base: None,
-        url: Some("https://antell.fi/lounas/kuopio/round/"),
+        sodexo_meal_id: None,
+        unica_restaurant_id: None,
+        url: Some(Cow::Borrowed("https://antell.fi/lounas/kuopio/round/")),
     },
     Restaurant {
-        code: "antell-highway",
-        name: "Antell Highway",
+        code: Cow::Borrowed("antell-highway"),
+        name: Cow::Borrowed("Antell Highway"),
         provider: Provider::Antell,
-        antell_slug: Some("highway"),
-        rss_cost_number: None,
+        antell_slug: Some(Cow::Borrowed("highway")),
+        cost_number: None,
+        fallback_rss: None,
         huomen_api_base: None,
-        url: Some("https://antell.fi/lounas/kuopio/highway/"),
+        sodexo_meal_id: None,
+        unica_restaurant_id: None,
+        url: Some(Cow::Borrowed("https://antell.fi/lounas/kuopio/highway/")),
     },
 ];
 
-pub fn available_restaurants(enable_antell: bool) -> Vec<Restaurant> {
+pub fn available_restaurants(settings: &Settings) -> Vec<Restaurant> {
     let mut list = Vec::new();
     list.extend_from_slice(&CORE_RESTAURANTS);
-    if enable_antell {
+    if settings.enable_antell_restaurants {
         list.extend_from_slice(&ANTELL_RESTAURANTS);
     }
+    list.extend_from_slice(custom_restaurants());
+    list.retain(|r| !provider_disabled(settings, r.provider));
     list
 }
 
-pub fn restaurant_for_code(code: &str, enable_antell: bool) -> Restaurant {
-    let list = available_restaurants(enable_antell);
+pub fn provider_disabled(settings: &Settings, provider: Provider) -> bool {
+    settings
+        .disabled_providers
+        .iter()
+        .any(|key| key.eq_ignore_ascii_case(provider_key(provider)))
+}
+
+/// Looks up `code`, falling back to the first available restaurant when it is unknown or its
+/// provider is disabled.
+pub fn restaurant_for_code(code: &str, settings: &Settings) -> Restaurant {
+    let list = available_restaurants(settings);
+    let fallback = list
+        .first()
+        .cloned()
+        .unwrap_or_else(|| CORE_RESTAURANTS[0].clone());
     list.into_iter()
         .find(|r| r.code == code)
-        .unwrap_or(CORE_RESTAURANTS[0])
+        .unwrap_or(fallback)
+}
+
+#[derive(Deserialize)]
+struct CustomRestaurantEntry {
+    code: String,
+    name: String,
+    provider: String,
+    cost_number: Option<String>,
+    fallback_rss: Option<String>,
+    huomen_api_base: Option<String>,
+    url: Option<String>,
+}
+
+/// Restaurants from `restaurants.json`, loaded once on first use.
+pub fn custom_restaurants() -> &'static [Restaurant] {
+    static CUSTOM: OnceLock<Vec<Restaurant>> = OnceLock::new();
+    CUSTOM.get_or_init(load_custom_restaurants)
+}
+
+fn load_custom_restaurants() -> Vec<Restaurant> {
+    let path = settings_dir().join("restaurants.json");
+    let data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(_) => return Vec::new(),
+    };
+    let entries: Vec<serde_json::Value> = match serde_json::from_str(&data) {
+        Ok(entries) => entries,
+        Err(err) => {
+            log_line(&format!("restaurants.json ignored: {}", err));
+            return Vec::new();
+        }
+    };
+
+    let mut list: Vec<Restaurant> = Vec::new();
+    for (index, value) in entries.into_iter().enumerate() {
+        let entry = serde_json::from_value::<CustomRestaurantEntry>(value)
+            .map_err(|err| err.to_string())
+            .and_then(|entry| custom_restaurant(entry, &list));
+        match entry {
+            Ok(restaurant) => list.push(restaurant),
+            Err(err) => log_line(&format!(
+                "restaurants.json entry {} skipped: {}",
+                index, err
+            )),
+        }
+    }
+    list
+}
+
+/// Validates one `restaurants.json` entry; `custom` holds the entries accepted before it.
+fn custom_restaurant(
+    entry: CustomRestaurantEntry,
+    custom: &[Restaurant],
+) -> Result<Restaurant, String> {
+    let code = entry.code.trim().to_string();
+    let name = entry.name.trim().to_string();
+    if code.is_empty() || name.is_empty() {
+        return Err("code and name are required".to_string());
+    }
+    let duplicate = CORE_RESTAURANTS
+        .iter()
+        .chain(ANTELL_RESTAURANTS.iter())
+        .chain(custom.iter())
+        .any(|r| r.code == code);
+    if duplicate {
+        return Err(format!("duplicate code {}", code));
+    }
+    let cost_number = entry
+        .cost_number
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let url = entry
+        .url
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map(Cow::Owned);
+
+    let mut restaurant = Restaurant {
+        code: Cow::Owned(code),
+        name: Cow::Owned(name),
+        provider: Provider::Compass,
+        antell_slug: None,
+        cost_number: None,
+        fallback_rss: None,
+        huomen_api_base: None,
+        sodexo_meal_id: None,
+        unica_restaurant_id: None,
+        url,
+    };
+    match entry.provider.to_ascii_lowercase().as_str() {
+        "compass" => {
+            restaurant.cost_number = cost_number.map(Cow::Owned);
+            restaurant.fallback_rss = entry
+                .fallback_rss
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .map(Cow::Owned);
+        }
+        "compass-rss" => {
+            restaurant.provider = Provider::CompassRss;
+            restaurant.cost_number = Some(Cow::Owned(
+                cost_number.ok_or("cost_number is required for compass-rss")?,
+            ));
+        }
+        "huomen-json" => {
+            restaurant.provider = Provider::HuomenJson;
+            restaurant.huomen_api_base = Some(Cow::Owned(
+                entry
+                    .huomen_api_base
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+                    .ok_or("huomen_api_base is required for huomen-json")?,
+            ));
+        }
+        "sodexo-json" => {
+            restaurant.provider = Provider::SodexoJson;
+            restaurant.sodexo_meal_id = Some(Cow::Owned(
+                cost_number.ok_or("cost_number is required for sodexo-json")?,
+            ));
+        }
+        "unica-json" => {
+            restaurant.provider = Provider::UnicaJson;
+            restaurant.unica_restaurant_id = Some(Cow::Owned(
+                cost_number.ok_or("cost_number is required for unica-json")?,
+            ));
+        }
+        other => return Err(format!("unsupported provider {}", other)),
+    }
+    Ok(restaurant)
 }
 
 pub fn provider_key(provider: Provider) -> &'static str {
@@ -112,5 +286,79 @@ pub fn provider_key(provider: Provider) -> &'static str {
         Provider::CompassRss => "compass-rss",
         Provider::Antell => "antell",
         Provider::HuomenJson => "huomen-json",
+        Provider::SodexoJson => "sodexo-json",
+        Provider::UnicaJson => "unica-json",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{custom_restaurant, CustomRestaurantEntry, Provider};
+
+    fn entry(code: &str, provider: &str, cost_number: Option<&str>) -> CustomRestaurantEntry {
+        CustomRestaurantEntry {
+            code: code.to_string(),
+            name: "Test".to_string(),
+            provider: provider.to_string(),
+            cost_number: cost_number.map(str::to_string),
+            fallback_rss: None,
+            huomen_api_base: None,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn custom_restaurant_rejects_invalid_entries() {
+        let cases = [
+            ("unsupported provider", entry("custom", "antell", Some("1"))),
+            (
+                "compass-rss without cost_number",
+                entry("custom", "compass-rss", None),
+            ),
+            (
+                "sodexo-json without cost_number",
+                entry("custom", "sodexo-json", None),
+            ),
+            (
+                "unica-json without cost_number",
+                entry("custom", "unica-json", Some(" ")),
+            ),
+            (
+                "huomen-json without huomen_api_base",
+                entry("custom", "huomen-json", None),
+            ),
+            (
+                "duplicate of a built-in code",
+                entry("0437", "compass", None),
+            ),
+            (
+                "duplicate of an Antell code",
+                entry("antell-highway", "compass", None),
+            ),
+            ("empty code", entry(" ", "compass", None)),
+        ];
+        for (label, case) in cases {
+            assert!(custom_restaurant(case, &[]).is_err(), "{}", label);
+        }
+    }
+
+    #[test]
+    fn custom_restaurant_accepts_valid_entries() {
+        let compass = custom_restaurant(entry("custom", "Compass", Some(" 1234 ")), &[]).unwrap();
+        assert_eq!(compass.provider, Provider::Compass);
+        assert_eq!(compass.cost_number.as_deref(), Some("1234"));
+
+        let huomen = custom_restaurant(
+            CustomRestaurantEntry {
+                huomen_api_base: Some("https://example.invalid/week".to_string()),
+                ..entry("custom-huomen", "huomen-json", None)
+            },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(huomen.provider, Provider::HuomenJson);
+
+        let taken = [compass];
+        assert!(custom_restaurant(entry("custom", "compass-rss", Some("1")), &taken).is_err());
     }
 }
@@ -0,0 +1,193 @@
+use crate::app::App;
+use crate::log::log_line;
+use crate::model::TodayMenu;
+use crate::restaurant::restaurant_for_code;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Budget for reading a whole request, so a slow client can't hold up the accept loop (and
+/// `shutdown`) for longer than this.
+const REQUEST_DEADLINE: Duration = Duration::from_secs(2);
+/// Request line plus headers; anything longer is cut off.
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// The `serve_port` endpoint: answers `GET /today.json` on 127.0.0.1 from a background thread.
+pub struct MenuServer {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl MenuServer {
+    /// Binds 127.0.0.1:`port` and serves the shown restaurant's menu from `app.snapshot()`.
+    pub fn start(port: u16, app: &'static App) -> std::io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let thread = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                match stream {
+                    Ok(stream) => {
+                        if let Err(err) = serve_connection(stream, app) {
+                            log_line(&format!("menu server request failed: {}", err));
+                        }
+                    }
+                    Err(err) => log_line(&format!("menu server accept failed: {}", err)),
+                }
+            }
+        });
+        Ok(Self { port, stop, thread })
+    }
+
+    /// Stops accepting and waits for the thread, so `app` is no longer read afterwards.
+    pub fn shutdown(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wakes the blocking accept so the loop sees the flag.
+        let _ = TcpStream::connect((Ipv4Addr::LOCALHOST, self.port));
+        let _ = self.thread.join();
+    }
+}
+
+/// Reads from `stream` with each read's timeout shrunk to what is left until `deadline`.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+fn serve_connection(stream: TcpStream, app: &App) -> std::io::Result<()> {
+    let deadline_reader = DeadlineReader {
+        stream: &stream,
+        deadline: Instant::now() + REQUEST_DEADLINE,
+    };
+    let mut reader = BufReader::new(deadline_reader.take(MAX_REQUEST_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers; the request body, if any, is ignored.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let response = http_response(&request_line, || {
+        let state = app.snapshot();
+        let name = if state.restaurant_name.is_empty() {
+            restaurant_for_code(&state.settings.restaurant_code, &state.settings)
+                .name
+                .to_string()
+        } else {
+            state.restaurant_name.clone()
+        };
+        menu_json(&name, state.today_menu.as_ref())
+    });
+    (&stream).write_all(response.as_bytes())
+}
+
+/// Full HTTP/1.1 response for `request_line`; `menu` is only called for `GET /today.json`.
+fn http_response(request_line: &str, menu: impl FnOnce() -> serde_json::Value) -> String {
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+    let (status, body) = match (method, path) {
+        ("GET", "/today.json") => ("200 OK", menu().to_string()),
+        ("GET", _) => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            r#"{"error":"method not allowed"}"#.to_string(),
+        ),
+    };
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// Restaurant name, date and menu groups (name, price, components) as a JSON object.
+pub fn menu_json(name: &str, menu: Option<&TodayMenu>) -> serde_json::Value {
+    let Some(menu) = menu else {
+        return serde_json::json!({ "name": name, "menus": [] });
+    };
+    let menus: Vec<_> = menu
+        .menus
+        .iter()
+        .map(|group| {
+            serde_json::json!({
+                "name": group.name,
+                "price": group.price,
+                "components": group.components,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "name": name,
+        "date": menu.date_iso,
+        "lunch_time": menu.lunch_time,
+        "closed": menu.closed,
+        "menus": menus,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{http_response, DeadlineReader};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{Ipv4Addr, TcpListener, TcpStream};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn slow_client_runs_out_of_request_time() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        client.write_all(b"GET /today.json").unwrap();
+
+        let started = Instant::now();
+        let mut reader = BufReader::new(DeadlineReader {
+            stream: &stream,
+            deadline: started + Duration::from_millis(200),
+        });
+        let mut line = String::new();
+        assert!(reader.read_line(&mut line).is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn routes_only_get_today_json() {
+        let menu = || serde_json::json!({ "name": "Snellmania", "menus": [] });
+
+        let ok = http_response("GET /today.json HTTP/1.1\r\n", menu);
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(ok.ends_with(r#"{"menus":[],"name":"Snellmania"}"#));
+        assert!(ok.contains("Content-Length: 32\r\n"));
+
+        let query = http_response("GET /today.json?x=1 HTTP/1.1\r\n", menu);
+        assert!(query.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let missing = http_response("GET /week.json HTTP/1.1\r\n", || unreachable!());
+        assert!(missing.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        let post = http_response("POST /today.json HTTP/1.1\r\n", || unreachable!());
+        assert!(post.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(!ok.contains("Access-Control-Allow-Origin"));
+    }
+}
@@ -1,25 +1,109 @@
+use crate::util::write_atomic;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use windows::Win32::System::LibraryLoader::GetModuleFileNameW;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN,
+};
+
+pub const DEFAULT_HOTKEY: &str = "Win+Shift+L";
+/// Shortest auto-refresh interval; smaller `refresh_minutes` values are raised to it.
+pub const MIN_REFRESH_MINUTES: u32 = 15;
+/// Lowest popup opacity percent; anything fainter is unreadable over a busy desktop.
+pub const MIN_POPUP_OPACITY: u32 = 20;
+/// Intervals with their own Auto refresh menu item; anything else shows as Custom.
+pub const REFRESH_PRESETS: [u32; 4] = [0, 60, 240, 1440];
+const MAX_HISTORY_DAYS: u32 = 90;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub restaurant_code: String,
     pub language: String,
+    #[serde(skip)]
+    pub auto_language: bool,
     pub refresh_minutes: u32,
+    pub prefetch_batch_size: u32,
+    pub fetch_timeout_secs: u32,
+    pub max_retries: u32,
+    pub keep_history_days: u32,
+    pub popup_width_px: Option<i32>,
     pub show_prices: bool,
+    pub price_column: bool,
     pub show_student_price: bool,
     pub show_staff_price: bool,
     pub show_guest_price: bool,
     pub hide_expensive_student_meals: bool,
+    pub expensive_student_threshold_eur: f32,
     pub theme: String,
+    pub custom_theme: Option<CustomTheme>,
+    pub divider_thickness_px: u32,
     pub show_allergens: bool,
     pub highlight_gluten_free: bool,
     pub highlight_veg: bool,
     pub highlight_lactose_free: bool,
+    pub show_week_number: bool,
+    pub show_alternate_names: bool,
+    pub lunch_countdown: bool,
+    pub refresh_on_click_open: bool,
+    pub refresh_on_hover_open: bool,
+    pub show_energy: bool,
+    pub highlight_favorites: bool,
+    pub micro_mode: bool,
+    pub show_allergen_legend: bool,
+    pub allergen_icons: bool,
+    pub show_stale_marker: bool,
+    pub reverse_group_order: bool,
+    pub swap_click_buttons: bool,
+    pub invert_wheel: bool,
+    pub close_popup_on_open_url: bool,
+    pub pin_popup: bool,
+    pub open_on_hover: bool,
+    pub notify_daily_menu: bool,
+    pub hover_open_ms: u32,
+    pub hover_close_grace_ms: u32,
+    pub auto_rotate_seconds: u32,
+    pub auto_hide_seconds: u32,
+    pub popup_header_opacity: u32,
+    pub popup_body_opacity: u32,
+    pub lunch_reminder_time: String,
+    pub hotkey: Option<String>,
+    pub serve_port: Option<u16>,
+    pub flag_repeat_menus: bool,
+    pub heading_position: String,
+    pub grow_direction: String,
+    pub popup_anchor: String,
+    pub text_case: String,
+    pub starred_dishes: Vec<String>,
+    pub favorite_keywords: Vec<String>,
+    pub restaurant_urls: BTreeMap<String, String>,
+    pub restaurant_names: BTreeMap<String, String>,
+    pub popup_keys: BTreeMap<String, String>,
+    pub language_overrides: BTreeMap<String, String>,
+    pub disabled_providers: Vec<String>,
     pub enable_antell_restaurants: bool,
     pub enable_logging: bool,
     pub last_updated_epoch_ms: i64,
+    pub last_notified_epoch_ms: i64,
+    pub last_favorite_notified_epoch_ms: i64,
+}
+
+/// Colors for the "custom" theme as `#RRGGBB` strings; missing fields use the dark palette.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomTheme {
+    pub bg_color: Option<String>,
+    pub body_text_color: Option<String>,
+    pub heading_color: Option<String>,
+    pub header_title_color: Option<String>,
+    pub suffix_color: Option<String>,
+    pub suffix_highlight_color: Option<String>,
+    pub star_color: Option<String>,
+    pub header_bg_color: Option<String>,
+    pub button_bg_color: Option<String>,
+    pub divider_color: Option<String>,
+    pub font_family: Option<String>,
 }
 
 impl Default for Settings {
@@ -27,20 +111,71 @@ impl Default for Settings {
         Self {
             restaurant_code: "0437".to_string(),
             language: "fi".to_string(),
+            auto_language: false,
             refresh_minutes: 1440,
+            prefetch_batch_size: 0,
+            fetch_timeout_secs: 10,
+            max_retries: 6,
+            keep_history_days: 0,
+            popup_width_px: None,
             show_prices: false,
+            price_column: false,
             show_student_price: true,
             show_staff_price: true,
             show_guest_price: false,
             hide_expensive_student_meals: false,
+            expensive_student_threshold_eur: 4.0,
             theme: "dark".to_string(),
+            custom_theme: None,
+            divider_thickness_px: 1,
             show_allergens: true,
             highlight_gluten_free: false,
             highlight_veg: false,
             highlight_lactose_free: false,
+            show_week_number: false,
+            show_alternate_names: false,
+            lunch_countdown: false,
+            refresh_on_click_open: false,
+            refresh_on_hover_open: false,
+            show_energy: false,
+            highlight_favorites: true,
+            micro_mode: false,
+            show_allergen_legend: false,
+            allergen_icons: false,
+            show_stale_marker: true,
+            reverse_group_order: false,
+            swap_click_buttons: false,
+            invert_wheel: false,
+            close_popup_on_open_url: false,
+            pin_popup: false,
+            open_on_hover: false,
+            notify_daily_menu: false,
+            hover_open_ms: 600,
+            hover_close_grace_ms: 400,
+            auto_rotate_seconds: 0,
+            auto_hide_seconds: 0,
+            popup_header_opacity: 100,
+            popup_body_opacity: 100,
+            lunch_reminder_time: String::new(),
+            hotkey: None,
+            serve_port: None,
+            flag_repeat_menus: false,
+            heading_position: "above".to_string(),
+            grow_direction: "up".to_string(),
+            popup_anchor: "tray".to_string(),
+            text_case: "asis".to_string(),
+            starred_dishes: Vec::new(),
+            favorite_keywords: Vec::new(),
+            restaurant_urls: BTreeMap::new(),
+            restaurant_names: BTreeMap::new(),
+            popup_keys: BTreeMap::new(),
+            language_overrides: BTreeMap::new(),
+            disabled_providers: Vec::new(),
             enable_antell_restaurants: true,
             enable_logging: false,
             last_updated_epoch_ms: 0,
+            last_notified_epoch_ms: 0,
+            last_favorite_notified_epoch_ms: 0,
         }
     }
 }
@@ -54,19 +189,67 @@ pub fn settings_path() -> PathBuf {
     settings_dir().join("settings.json")
 }
 
+pub fn exe_dir() -> Option<PathBuf> {
+    let mut buffer = [0u16; 260];
+    let len = unsafe { GetModuleFileNameW(None, &mut buffer) } as usize;
+    if len == 0 {
+        return None;
+    }
+    let exe = String::from_utf16_lossy(&buffer[..len]);
+    Some(PathBuf::from(exe).parent()?.to_path_buf())
+}
+
 pub fn load_settings() -> Settings {
     let path = settings_path();
     match fs::read_to_string(&path) {
         Ok(data) => decode_settings(&data).unwrap_or_default(),
-        Err(_) => Settings::default(),
+        Err(_) => seed_default_settings(),
+    }
+}
+
+/// First run: seed the user settings from a `default-settings.json` shipped next to the exe.
+fn seed_default_settings() -> Settings {
+    let seeded = exe_dir()
+        .and_then(|dir| fs::read_to_string(dir.join("default-settings.json")).ok())
+        .and_then(|data| decode_settings(&data).ok());
+    match seeded {
+        Some(settings) => {
+            let _ = save_settings(&settings);
+            settings
+        }
+        None => Settings::default(),
+    }
+}
+
+/// Re-reads settings.json, for edits made while the app runs. None if it is missing or invalid.
+pub fn reload_settings_file() -> Option<Settings> {
+    let data = fs::read_to_string(settings_path()).ok()?;
+    decode_settings(&data).ok()
+}
+
+/// 0 keeps auto-refresh off; other values are at least `MIN_REFRESH_MINUTES`.
+pub fn clamp_refresh_minutes(minutes: u32) -> u32 {
+    if minutes == 0 {
+        0
+    } else {
+        minutes.max(MIN_REFRESH_MINUTES)
     }
 }
 
+/// Opacity percent limited to `MIN_POPUP_OPACITY..=100`.
+pub fn clamp_popup_opacity(percent: u32) -> u32 {
+    percent.clamp(MIN_POPUP_OPACITY, 100)
+}
+
 pub fn save_settings(settings: &Settings) -> anyhow::Result<()> {
     let dir = settings_dir();
     fs::create_dir_all(&dir)?;
-    let data = serde_json::to_string_pretty(settings)?;
-    fs::write(dir.join("settings.json"), data)?;
+    let mut persisted = settings.clone();
+    if persisted.auto_language {
+        persisted.language = "auto".to_string();
+    }
+    let data = serde_json::to_string_pretty(&persisted)?;
+    write_atomic(&dir.join("settings.json"), data)?;
     Ok(())
 }
 
@@ -75,20 +258,71 @@ struct RawSettings {
     restaurant_code: Option<String>,
     language: Option<String>,
     refresh_minutes: Option<u32>,
+    prefetch_batch_size: Option<u32>,
+    fetch_timeout_secs: Option<u32>,
+    max_retries: Option<u32>,
+    keep_history_days: Option<u32>,
+    popup_width_px: Option<i32>,
     show_prices: Option<bool>,
+    price_column: Option<bool>,
     show_student_price: Option<bool>,
     show_staff_price: Option<bool>,
     show_guest_price: Option<bool>,
     hide_expensive_student_meals: Option<bool>,
+    expensive_student_threshold_eur: Option<f32>,
     theme: Option<String>,
     dark_mode: Option<bool>,
+    custom_theme: Option<CustomTheme>,
+    divider_thickness_px: Option<u32>,
     show_allergens: Option<bool>,
     hide_allergens: Option<bool>,
     highlight_gluten_free: Option<bool>,
     highlight_veg: Option<bool>,
     highlight_lactose_free: Option<bool>,
+    show_week_number: Option<bool>,
+    show_alternate_names: Option<bool>,
+    lunch_countdown: Option<bool>,
+    refresh_on_click_open: Option<bool>,
+    refresh_on_hover_open: Option<bool>,
+    refresh_on_open: Option<bool>,
+    show_energy: Option<bool>,
+    highlight_favorites: Option<bool>,
+    micro_mode: Option<bool>,
+    show_allergen_legend: Option<bool>,
+    allergen_icons: Option<bool>,
+    show_stale_marker: Option<bool>,
+    reverse_group_order: Option<bool>,
+    swap_click_buttons: Option<bool>,
+    invert_wheel: Option<bool>,
+    close_popup_on_open_url: Option<bool>,
+    pin_popup: Option<bool>,
+    open_on_hover: Option<bool>,
+    notify_daily_menu: Option<bool>,
+    hover_open_ms: Option<u32>,
+    hover_close_grace_ms: Option<u32>,
+    auto_rotate_seconds: Option<u32>,
+    auto_hide_seconds: Option<u32>,
+    popup_header_opacity: Option<u32>,
+    popup_body_opacity: Option<u32>,
+    lunch_reminder_time: Option<String>,
+    hotkey: Option<String>,
+    serve_port: Option<u16>,
+    flag_repeat_menus: Option<bool>,
+    heading_position: Option<String>,
+    grow_direction: Option<String>,
+    popup_anchor: Option<String>,
+    text_case: Option<String>,
+    starred_dishes: Option<Vec<String>>,
+    favorite_keywords: Option<Vec<String>>,
+    restaurant_urls: Option<BTreeMap<String, String>>,
+    restaurant_names: Option<BTreeMap<String, String>>,
+    popup_keys: Option<BTreeMap<String, String>>,
+    language_overrides: Option<BTreeMap<String, String>>,
+    disabled_providers: Option<Vec<String>>,
     enable_logging: Option<bool>,
     last_updated_epoch_ms: Option<i64>,
+    last_notified_epoch_ms: Option<i64>,
+    last_favorite_notified_epoch_ms: Option<i64>,
 }
 
 fn decode_settings(data: &str) -> anyhow::Result<Settings> {
@@ -100,6 +334,16 @@ fn decode_settings(data: &str) -> anyhow::Result<Settings> {
             .unwrap_or(defaults.show_allergens)
     });
 
+    let auto_language = raw
+        .language
+        .as_deref()
+        .is_some_and(|value| value.eq_ignore_ascii_case("auto"));
+    let language = if auto_language {
+        crate::locale::system_language()
+    } else {
+        raw.language.unwrap_or(defaults.language)
+    };
+
     let theme = raw
         .theme
         .as_deref()
@@ -117,9 +361,26 @@ fn decode_settings(data: &str) -> anyhow::Result<Settings> {
 
     Ok(Settings {
         restaurant_code: raw.restaurant_code.unwrap_or(defaults.restaurant_code),
-        language: raw.language.unwrap_or(defaults.language),
-        refresh_minutes: raw.refresh_minutes.unwrap_or(defaults.refresh_minutes),
+        language,
+        auto_language,
+        refresh_minutes: clamp_refresh_minutes(
+            raw.refresh_minutes.unwrap_or(defaults.refresh_minutes),
+        ),
+        prefetch_batch_size: raw
+            .prefetch_batch_size
+            .unwrap_or(defaults.prefetch_batch_size),
+        fetch_timeout_secs: raw
+            .fetch_timeout_secs
+            .unwrap_or(defaults.fetch_timeout_secs)
+            .clamp(3, 60),
+        max_retries: raw.max_retries.unwrap_or(defaults.max_retries),
+        keep_history_days: raw
+            .keep_history_days
+            .unwrap_or(defaults.keep_history_days)
+            .min(MAX_HISTORY_DAYS),
+        popup_width_px: raw.popup_width_px.filter(|width| *width > 0),
         show_prices: raw.show_prices.unwrap_or(defaults.show_prices),
+        price_column: raw.price_column.unwrap_or(defaults.price_column),
         show_student_price: raw
             .show_student_price
             .unwrap_or(defaults.show_student_price),
@@ -128,7 +389,16 @@ fn decode_settings(data: &str) -> anyhow::Result<Settings> {
         hide_expensive_student_meals: raw
             .hide_expensive_student_meals
             .unwrap_or(defaults.hide_expensive_student_meals),
+        expensive_student_threshold_eur: raw
+            .expensive_student_threshold_eur
+            .filter(|value| value.is_finite() && *value > 0.0)
+            .unwrap_or(defaults.expensive_student_threshold_eur),
         theme,
+        custom_theme: raw.custom_theme,
+        divider_thickness_px: raw
+            .divider_thickness_px
+            .unwrap_or(defaults.divider_thickness_px)
+            .min(8),
         show_allergens,
         highlight_gluten_free: raw
             .highlight_gluten_free
@@ -137,15 +407,162 @@ fn decode_settings(data: &str) -> anyhow::Result<Settings> {
         highlight_lactose_free: raw
             .highlight_lactose_free
             .unwrap_or(defaults.highlight_lactose_free),
+        show_week_number: raw.show_week_number.unwrap_or(defaults.show_week_number),
+        show_alternate_names: raw
+            .show_alternate_names
+            .unwrap_or(defaults.show_alternate_names),
+        lunch_countdown: raw.lunch_countdown.unwrap_or(defaults.lunch_countdown),
+        refresh_on_click_open: raw
+            .refresh_on_click_open
+            .or(raw.refresh_on_open)
+            .unwrap_or(defaults.refresh_on_click_open),
+        refresh_on_hover_open: raw
+            .refresh_on_hover_open
+            .unwrap_or(defaults.refresh_on_hover_open),
+        show_energy: raw.show_energy.unwrap_or(defaults.show_energy),
+        highlight_favorites: raw
+            .highlight_favorites
+            .unwrap_or(defaults.highlight_favorites),
+        micro_mode: raw.micro_mode.unwrap_or(defaults.micro_mode),
+        show_allergen_legend: raw
+            .show_allergen_legend
+            .unwrap_or(defaults.show_allergen_legend),
+        allergen_icons: raw.allergen_icons.unwrap_or(defaults.allergen_icons),
+        show_stale_marker: raw.show_stale_marker.unwrap_or(defaults.show_stale_marker),
+        reverse_group_order: raw
+            .reverse_group_order
+            .unwrap_or(defaults.reverse_group_order),
+        swap_click_buttons: raw
+            .swap_click_buttons
+            .unwrap_or(defaults.swap_click_buttons),
+        invert_wheel: raw.invert_wheel.unwrap_or(defaults.invert_wheel),
+        close_popup_on_open_url: raw
+            .close_popup_on_open_url
+            .unwrap_or(defaults.close_popup_on_open_url),
+        pin_popup: raw.pin_popup.unwrap_or(defaults.pin_popup),
+        open_on_hover: raw.open_on_hover.unwrap_or(defaults.open_on_hover),
+        notify_daily_menu: raw.notify_daily_menu.unwrap_or(defaults.notify_daily_menu),
+        hover_open_ms: raw.hover_open_ms.unwrap_or(defaults.hover_open_ms),
+        hover_close_grace_ms: raw
+            .hover_close_grace_ms
+            .unwrap_or(defaults.hover_close_grace_ms),
+        auto_rotate_seconds: raw
+            .auto_rotate_seconds
+            .unwrap_or(defaults.auto_rotate_seconds),
+        auto_hide_seconds: raw.auto_hide_seconds.unwrap_or(defaults.auto_hide_seconds),
+        popup_header_opacity: clamp_popup_opacity(
+            raw.popup_header_opacity
+                .unwrap_or(defaults.popup_header_opacity),
+        ),
+        popup_body_opacity: clamp_popup_opacity(
+            raw.popup_body_opacity
+                .unwrap_or(defaults.popup_body_opacity),
+        ),
+        lunch_reminder_time: raw
+            .lunch_reminder_time
+            .map(|value| value.trim().to_string())
+            .unwrap_or(defaults.lunch_reminder_time),
+        hotkey: raw.hotkey.or(defaults.hotkey),
+        serve_port: raw.serve_port.filter(|port| *port > 0),
+        flag_repeat_menus: raw.flag_repeat_menus.unwrap_or(defaults.flag_repeat_menus),
+        heading_position: raw
+            .heading_position
+            .as_deref()
+            .map(normalize_heading_position)
+            .unwrap_or(defaults.heading_position),
+        grow_direction: raw
+            .grow_direction
+            .as_deref()
+            .map(normalize_grow_direction)
+            .unwrap_or(defaults.grow_direction),
+        popup_anchor: raw
+            .popup_anchor
+            .as_deref()
+            .map(normalize_popup_anchor)
+            .unwrap_or(defaults.popup_anchor),
+        text_case: raw
+            .text_case
+            .as_deref()
+            .map(normalize_text_case)
+            .unwrap_or(defaults.text_case),
+        starred_dishes: raw.starred_dishes.unwrap_or(defaults.starred_dishes),
+        favorite_keywords: raw.favorite_keywords.unwrap_or(defaults.favorite_keywords),
+        restaurant_urls: raw.restaurant_urls.unwrap_or(defaults.restaurant_urls),
+        restaurant_names: raw.restaurant_names.unwrap_or(defaults.restaurant_names),
+        popup_keys: raw.popup_keys.unwrap_or(defaults.popup_keys),
+        language_overrides: raw
+            .language_overrides
+            .map(normalize_language_overrides)
+            .unwrap_or(defaults.language_overrides),
+        disabled_providers: raw
+            .disabled_providers
+            .unwrap_or(defaults.disabled_providers),
         // Antell is always enabled; keep the field for backward-compatible settings serialization.
         enable_antell_restaurants: true,
         enable_logging: raw.enable_logging.unwrap_or(defaults.enable_logging),
         last_updated_epoch_ms: raw
             .last_updated_epoch_ms
             .unwrap_or(defaults.last_updated_epoch_ms),
+        last_notified_epoch_ms: raw
+            .last_notified_epoch_ms
+            .unwrap_or(defaults.last_notified_epoch_ms),
+        last_favorite_notified_epoch_ms: raw
+            .last_favorite_notified_epoch_ms
+            .unwrap_or(defaults.last_favorite_notified_epoch_ms),
     })
 }
 
+/// Menu language used to fetch and cache `code`: its `language_overrides` entry, else the
+/// UI language.
+pub fn menu_language<'a>(settings: &'a Settings, code: &str) -> &'a str {
+    settings
+        .language_overrides
+        .get(code)
+        .map(String::as_str)
+        .unwrap_or(&settings.language)
+}
+
+fn normalize_language_overrides(overrides: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    overrides
+        .into_iter()
+        .map(|(code, language)| (code.trim().to_string(), language.trim().to_lowercase()))
+        .filter(|(code, language)| !code.is_empty() && !language.is_empty())
+        .collect()
+}
+
+pub fn normalize_heading_position(value: &str) -> String {
+    if value.eq_ignore_ascii_case("below") {
+        "below".to_string()
+    } else {
+        "above".to_string()
+    }
+}
+
+pub fn normalize_grow_direction(value: &str) -> String {
+    if value.eq_ignore_ascii_case("down") {
+        "down".to_string()
+    } else {
+        "up".to_string()
+    }
+}
+
+pub fn normalize_popup_anchor(value: &str) -> String {
+    match value.to_ascii_lowercase().as_str() {
+        "cursor" => "cursor".to_string(),
+        "fixed_top_right" => "fixed_top_right".to_string(),
+        "fixed_bottom_right" => "fixed_bottom_right".to_string(),
+        _ => "tray".to_string(),
+    }
+}
+
+pub fn normalize_text_case(value: &str) -> String {
+    match value.to_ascii_lowercase().as_str() {
+        "title" => "title".to_string(),
+        "sentence" => "sentence".to_string(),
+        _ => "asis".to_string(),
+    }
+}
+
 pub fn normalize_theme(value: &str) -> String {
     match value.to_ascii_lowercase().as_str() {
         "light" => "light".to_string(),
@@ -154,6 +571,118 @@ pub fn normalize_theme(value: &str) -> String {
         "green" => "green".to_string(),
         "teletext1" => "teletext1".to_string(),
         "teletext2" => "teletext2".to_string(),
+        "custom" => "custom".to_string(),
         _ => "dark".to_string(),
     }
 }
+
+pub fn parse_hex_color(value: &str) -> Option<(u8, u8, u8)> {
+    let hex = value.trim().strip_prefix('#')?;
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Parses a combo like "Win+Shift+L" into `RegisterHotKey` modifiers and a virtual key.
+/// The key may be a letter, a digit, F1-F24 or Space, and needs at least one modifier.
+pub fn parse_hotkey(value: &str) -> Option<(HOT_KEY_MODIFIERS, u32)> {
+    let mut modifiers = MOD_NOREPEAT;
+    let mut key = None;
+    for part in value.split('+').map(str::trim) {
+        match part.to_ascii_lowercase().as_str() {
+            "win" => modifiers |= MOD_WIN,
+            "shift" => modifiers |= MOD_SHIFT,
+            "ctrl" | "control" => modifiers |= MOD_CONTROL,
+            "alt" => modifiers |= MOD_ALT,
+            "space" if key.is_none() => key = Some(0x20),
+            name if key.is_none() => key = Some(hotkey_virtual_key(name)?),
+            _ => return None,
+        }
+    }
+    if modifiers == MOD_NOREPEAT {
+        return None;
+    }
+    Some((modifiers, key?))
+}
+
+pub fn hotkey_virtual_key(name: &str) -> Option<u32> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) if ch.is_ascii_alphanumeric() => Some(ch.to_ascii_uppercase() as u32),
+        (Some('f'), Some(_)) => match name[1..].parse::<u32>().ok()? {
+            n @ 1..=24 => Some(0x6F + n),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hotkey_combos() {
+        assert_eq!(
+            parse_hotkey(DEFAULT_HOTKEY),
+            Some((MOD_NOREPEAT | MOD_WIN | MOD_SHIFT, 'L' as u32))
+        );
+        assert_eq!(
+            parse_hotkey("ctrl + alt + f9"),
+            Some((MOD_NOREPEAT | MOD_CONTROL | MOD_ALT, 0x78))
+        );
+        assert_eq!(
+            parse_hotkey("Alt+Space"),
+            Some((MOD_NOREPEAT | MOD_ALT, 0x20))
+        );
+        assert_eq!(parse_hotkey("L"), None);
+        assert_eq!(parse_hotkey("Win+Shift"), None);
+        assert_eq!(parse_hotkey("Win+L+K"), None);
+        assert_eq!(parse_hotkey("Win+F25"), None);
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_hex_color("#1e1e2e"), Some((0x1e, 0x1e, 0x2e)));
+        assert_eq!(parse_hex_color(" #FFA500 "), Some((255, 165, 0)));
+        assert_eq!(parse_hex_color("1e1e2e"), None);
+        assert_eq!(parse_hex_color("#12345"), None);
+        assert_eq!(parse_hex_color("#zzzzzz"), None);
+    }
+
+    #[test]
+    fn decodes_partial_custom_theme() {
+        let custom: CustomTheme =
+            serde_json::from_str(r##"{"bg_color":"#1e1e2e","font_family":"Cascadia Code"}"##)
+                .unwrap();
+        assert_eq!(custom.bg_color.as_deref(), Some("#1e1e2e"));
+        assert_eq!(custom.heading_color, None);
+        assert_eq!(custom.font_family.as_deref(), Some("Cascadia Code"));
+        assert_eq!(normalize_theme("Custom"), "custom");
+    }
+
+    #[test]
+    fn refresh_minutes_keep_a_floor() {
+        assert_eq!(clamp_refresh_minutes(90), 90);
+        assert_eq!(clamp_refresh_minutes(5), MIN_REFRESH_MINUTES);
+        assert_eq!(clamp_refresh_minutes(0), 0);
+        assert!(!REFRESH_PRESETS.contains(&clamp_refresh_minutes(30)));
+    }
+
+    #[test]
+    fn language_overrides_apply_per_restaurant() {
+        let overrides = BTreeMap::from([
+            (" 0437 ".to_string(), " EN ".to_string()),
+            ("3105".to_string(), String::new()),
+        ]);
+        let settings = Settings {
+            language_overrides: normalize_language_overrides(overrides),
+            ..Settings::default()
+        };
+        assert_eq!(menu_language(&settings, "0437"), "en");
+        assert_eq!(menu_language(&settings, "3105"), "fi");
+        assert_eq!(menu_language(&settings, "antell-highway"), "fi");
+    }
+}
@@ -1,19 +1,32 @@
-use crate::app::AppState;
+use crate::app::{AppState, FetchStatus};
+use crate::format::{format_eur, split_component_suffix, text_for, weekday_initial};
 use crate::log::log_line;
-use crate::util::to_wstring;
+use crate::popup::tray_badge_colors;
+use crate::restaurant::{custom_restaurants, provider_disabled, restaurant_for_code, Provider};
+use crate::settings::{exe_dir, menu_language, Settings, REFRESH_PRESETS};
+use crate::util::{local_now, to_wstring};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicIsize, Ordering};
 use windows::core::PCWSTR;
-use windows::Win32::Foundation::{HWND, LPARAM, POINT, RECT, WPARAM};
-use windows::Win32::System::LibraryLoader::{GetModuleFileNameW, GetModuleHandleW};
+use windows::Win32::Foundation::{BOOL, HANDLE, HWND, LPARAM, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    CreateBitmap, CreateCompatibleDC, CreateDIBSection, CreateFontW, CreateSolidBrush, DeleteDC,
+    DeleteObject, DrawTextW, FillRect, GdiFlush, GetDC, ReleaseDC, SelectObject, SetBkMode,
+    SetTextColor, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, DT_CENTER, DT_SINGLELINE,
+    DT_VCENTER, TRANSPARENT,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::Shell::{
-    Shell_NotifyIconGetRect, Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD,
-    NIM_DELETE, NIM_SETVERSION, NOTIFYICONDATAW, NOTIFYICONIDENTIFIER, NOTIFYICON_VERSION_4,
+    Shell_NotifyIconGetRect, Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP,
+    NIIF_INFO, NIM_ADD, NIM_DELETE, NIM_MODIFY, NIM_SETVERSION, NOTIFYICONDATAW,
+    NOTIFYICONIDENTIFIER, NOTIFYICON_VERSION_4,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
-    AppendMenuW, CreatePopupMenu, GetCursorPos, LoadIconW, LoadImageW, PostMessageW,
-    SetForegroundWindow, TrackPopupMenu, HICON, HMENU, IMAGE_ICON, LR_DEFAULTSIZE, LR_LOADFROMFILE,
-    MF_CHECKED, MF_DISABLED, MF_GRAYED, MF_POPUP, MF_SEPARATOR, MF_STRING, TPM_LEFTALIGN,
-    TPM_RIGHTBUTTON, WM_NULL,
+    AppendMenuW, CreateIconIndirect, CreatePopupMenu, DestroyIcon, DrawIconEx, GetCursorPos,
+    GetSystemMetrics, LoadIconW, LoadImageW, PostMessageW, SetForegroundWindow, TrackPopupMenu,
+    DI_NORMAL, HICON, HMENU, ICONINFO, IMAGE_ICON, LR_DEFAULTSIZE, LR_LOADFROMFILE, MF_CHECKED,
+    MF_DISABLED, MF_GRAYED, MF_POPUP, MF_SEPARATOR, MF_STRING, SM_CXSMICON, SM_CYSMICON,
+    TPM_LEFTALIGN, TPM_RIGHTBUTTON, WM_NULL,
 };
 
 pub const CMD_RESTAURANT_0437: u16 = 2001;
@@ -23,8 +36,12 @@ pub const CMD_RESTAURANT_SNELLARI_RSS: u16 = 2004;
 pub const CMD_RESTAURANT_HUOMEN_BIOTEKNIA: u16 = 2005;
 pub const CMD_RESTAURANT_ANTELL_HIGHWAY: u16 = 2006;
 pub const CMD_RESTAURANT_ANTELL_ROUND: u16 = 2007;
+pub const CMD_RESTAURANT_CUSTOM_FIRST: u16 = 2010;
+pub const CMD_RESTAURANT_CUSTOM_LAST: u16 = 2099;
 pub const CMD_LANGUAGE_FI: u16 = 2101;
 pub const CMD_LANGUAGE_EN: u16 = 2102;
+pub const CMD_LANGUAGE_AUTO: u16 = 2103;
+pub const CMD_LANGUAGE_SV: u16 = 2104;
 pub const CMD_TOGGLE_SHOW_PRICES: u16 = 2201;
 pub const CMD_TOGGLE_SHOW_ALLERGENS: u16 = 2202;
 pub const CMD_TOGGLE_HIGHLIGHT_G: u16 = 2203;
@@ -33,7 +50,6 @@ pub const CMD_TOGGLE_HIGHLIGHT_L: u16 = 2205;
 pub const CMD_TOGGLE_SHOW_STUDENT_PRICE: u16 = 2206;
 pub const CMD_TOGGLE_SHOW_STAFF_PRICE: u16 = 2207;
 pub const CMD_TOGGLE_SHOW_GUEST_PRICE: u16 = 2208;
-pub const CMD_TOGGLE_HIDE_EXPENSIVE_STUDENT: u16 = 2209;
 pub const CMD_THEME_LIGHT: u16 = 2211;
 pub const CMD_THEME_DARK: u16 = 2212;
 pub const CMD_THEME_BLUE: u16 = 2213;
@@ -43,17 +59,54 @@ pub const CMD_TOGGLE_LOGGING: u16 = 2216;
 pub const CMD_OPEN_APPDATA_DIR: u16 = 2217;
 pub const CMD_THEME_TELETEXT1: u16 = 2218;
 pub const CMD_THEME_TELETEXT2: u16 = 2219;
+pub const CMD_TOGGLE_SHOW_WEEK_NUMBER: u16 = 2220;
+pub const CMD_TOGGLE_SHOW_STALE_MARKER: u16 = 2221;
+pub const CMD_TOGGLE_REVERSE_GROUP_ORDER: u16 = 2222;
+pub const CMD_TOGGLE_SWAP_CLICK_BUTTONS: u16 = 2223;
+pub const CMD_TOGGLE_HEADING_POSITION: u16 = 2224;
+pub const CMD_TOGGLE_FLAG_REPEAT_MENUS: u16 = 2225;
+pub const CMD_TOGGLE_OPEN_ON_HOVER: u16 = 2226;
+pub const CMD_TOGGLE_NOTIFY: u16 = 2227;
+pub const CMD_TOGGLE_LUNCH_COUNTDOWN: u16 = 2228;
+pub const CMD_TOGGLE_REFRESH_ON_CLICK_OPEN: u16 = 2229;
+pub const CMD_TOGGLE_SHOW_ENERGY: u16 = 2230;
+pub const CMD_TOGGLE_HIGHLIGHT_FAVORITES: u16 = 2231;
+pub const CMD_TOGGLE_MICRO_MODE: u16 = 2232;
+pub const CMD_SHOW_ALLERGEN_LEGEND: u16 = 2233;
+pub const CMD_TOGGLE_GROW_DIRECTION: u16 = 2234;
+pub const CMD_THEME_CUSTOM: u16 = 2235;
+pub const CMD_COPY_RAW_PAYLOAD: u16 = 2236;
+pub const CMD_TOGGLE_REFRESH_ON_HOVER_OPEN: u16 = 2237;
+pub const CMD_TOGGLE_ALTERNATE_NAMES: u16 = 2238;
+pub const CMD_TOGGLE_CLOSE_ON_OPEN_URL: u16 = 2239;
+pub const CMD_TELETEXT_FULLSCREEN: u16 = 2240;
+pub const CMD_TOGGLE_INVERT_WHEEL: u16 = 2241;
+pub const CMD_ANCHOR_CURSOR: u16 = 2242;
+pub const CMD_ANCHOR_TRAY: u16 = 2243;
+pub const CMD_ANCHOR_TOP_RIGHT: u16 = 2244;
+pub const CMD_ANCHOR_BOTTOM_RIGHT: u16 = 2245;
+pub const CMD_TOGGLE_PRICE_COLUMN: u16 = 2246;
+pub const CMD_TOGGLE_PIN: u16 = 2247;
+pub const CMD_TOGGLE_ALLERGEN_ICONS: u16 = 2248;
 pub const CMD_REFRESH_NOW: u16 = 2301;
 pub const CMD_REFRESH_OFF: u16 = 2400;
 pub const CMD_REFRESH_60: u16 = 2401;
 pub const CMD_REFRESH_240: u16 = 2402;
 pub const CMD_REFRESH_1440: u16 = 2403;
+pub const CMD_REFRESH_CUSTOM: u16 = 2404;
+pub const CMD_THRESHOLD_OFF: u16 = 2500;
+pub const CMD_THRESHOLD_350: u16 = 2501;
+pub const CMD_THRESHOLD_400: u16 = 2502;
+pub const CMD_THRESHOLD_500: u16 = 2503;
 pub const CMD_QUIT: u16 = 2999;
 const TRAY_ICON_ID: u32 = 1;
 
-pub fn add_tray_icon(hwnd: HWND, callback_message: u32) -> anyhow::Result<()> {
+static BASE_ICON: AtomicIsize = AtomicIsize::new(0);
+static DYNAMIC_ICON: AtomicIsize = AtomicIsize::new(0);
+
+pub fn add_tray_icon(hwnd: HWND, callback_message: u32, settings: &Settings) -> anyhow::Result<()> {
+    let icon = tray_icon_for(settings);
     unsafe {
-        let icon = load_icon();
         let mut data = NOTIFYICONDATAW::default();
         data.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as u32;
         data.hWnd = hwnd;
@@ -61,12 +114,7 @@ pub fn add_tray_icon(hwnd: HWND, callback_message: u32) -> anyhow::Result<()> {
         data.uFlags = NIF_MESSAGE | NIF_ICON | NIF_TIP;
         data.uCallbackMessage = callback_message;
         data.hIcon = icon;
-        let tip = to_wstring("Compass Lunch");
-        let mut sz_tip = [0u16; 128];
-        for (idx, ch) in tip.iter().enumerate().take(sz_tip.len() - 1) {
-            sz_tip[idx] = *ch;
-        }
-        data.szTip = sz_tip;
+        copy_truncated(&mut data.szTip, "Compass Lunch");
 
         let ok = Shell_NotifyIconW(NIM_ADD, &mut data).as_bool();
         if !ok {
@@ -75,9 +123,125 @@ pub fn add_tray_icon(hwnd: HWND, callback_message: u32) -> anyhow::Result<()> {
         data.Anonymous.uVersion = NOTIFYICON_VERSION_4;
         let _ = Shell_NotifyIconW(NIM_SETVERSION, &mut data);
     }
+    remember_dynamic_icon(icon);
     Ok(())
 }
 
+/// Redraws the weekday badge, e.g. after the day rolls over.
+pub fn update_tray_icon(hwnd: HWND, settings: &Settings) {
+    let icon = tray_icon_for(settings);
+    unsafe {
+        let data = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: TRAY_ICON_ID,
+            uFlags: NIF_ICON,
+            hIcon: icon,
+            ..Default::default()
+        };
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &data);
+    }
+    remember_dynamic_icon(icon);
+}
+
+/// Shows today's first dish in the tooltip, e.g. after a fetch completes.
+pub fn update_tooltip(hwnd: HWND, state: &AppState) {
+    unsafe {
+        let mut data = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: TRAY_ICON_ID,
+            uFlags: NIF_TIP,
+            ..Default::default()
+        };
+        copy_truncated(&mut data.szTip, &fit_tooltip(&tooltip_text(state)));
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &data);
+    }
+}
+
+/// "Snellmania · Lohikeitto": the restaurant and its first dish, or the loading or no-menu text.
+fn tooltip_text(state: &AppState) -> String {
+    let name = if state.restaurant_name.is_empty() {
+        restaurant_for_code(&state.settings.restaurant_code, &state.settings)
+            .name
+            .to_string()
+    } else {
+        state.restaurant_name.clone()
+    };
+    let language = menu_language(&state.settings, &state.settings.restaurant_code);
+    let first_dish = state.today_menu.as_ref().and_then(|menu| {
+        menu.menus
+            .iter()
+            .flat_map(|group| group.components.iter())
+            .map(|component| split_component_suffix(component, language).0)
+            .find(|dish| !dish.is_empty())
+    });
+    let detail = match first_dish {
+        Some(dish) => dish,
+        None if state.status == FetchStatus::Loading => {
+            text_for(&state.settings.language, "loading")
+        }
+        None if state.status == FetchStatus::Error => {
+            text_for(&state.settings.language, "fetchError")
+        }
+        None => text_for(&state.settings.language, "noMenu"),
+    };
+    format!("{} · {}", name, detail)
+}
+
+/// Shortens `text` to fit `szTip` (127 UTF-16 units plus the terminator), ending in "…".
+fn fit_tooltip(text: &str) -> String {
+    const MAX_UNITS: usize = 127;
+    if text.encode_utf16().count() <= MAX_UNITS {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut units = 1;
+    for ch in text.chars() {
+        units += ch.len_utf16();
+        if units > MAX_UNITS {
+            break;
+        }
+        out.push(ch);
+    }
+    format!("{}…", out.trim_end())
+}
+
+pub fn show_balloon(hwnd: HWND, title: &str, text: &str) {
+    unsafe {
+        let mut data = NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: hwnd,
+            uID: TRAY_ICON_ID,
+            uFlags: NIF_INFO,
+            dwInfoFlags: NIIF_INFO,
+            ..Default::default()
+        };
+        copy_truncated(&mut data.szInfoTitle, title);
+        copy_truncated(&mut data.szInfo, text);
+        let _ = Shell_NotifyIconW(NIM_MODIFY, &data);
+    }
+}
+
+/// Copies `value` into a fixed, NUL-terminated UTF-16 buffer such as `szTip`, cutting it short
+/// at a character boundary so a surrogate pair is never split.
+fn copy_truncated(target: &mut [u16], value: &str) {
+    let Some(capacity) = target.len().checked_sub(1) else {
+        return;
+    };
+    let mut len = 0;
+    let mut units = [0u16; 2];
+    for ch in value.chars() {
+        let encoded = ch.encode_utf16(&mut units);
+        if len + encoded.len() > capacity {
+            break;
+        }
+        target[len..len + encoded.len()].copy_from_slice(encoded);
+        len += encoded.len();
+    }
+    target[len] = 0;
+}
+
 pub fn remove_tray_icon(hwnd: HWND) {
     unsafe {
         let mut data = NOTIFYICONDATAW::default();
@@ -86,6 +250,144 @@ pub fn remove_tray_icon(hwnd: HWND) {
         data.uID = TRAY_ICON_ID;
         let _ = Shell_NotifyIconW(NIM_DELETE, &mut data);
     }
+    remember_dynamic_icon(HICON(0));
+}
+
+fn tray_icon_for(settings: &Settings) -> HICON {
+    let now = local_now();
+    let initial = weekday_initial(now.weekday(), &settings.language);
+    let icon = build_dynamic_icon(initial, settings);
+    if icon.0 == 0 {
+        log_line("dynamic tray icon failed; using static icon");
+        return base_icon();
+    }
+    icon
+}
+
+/// Keeps the shown dynamic icon alive and destroys the one it replaces.
+fn remember_dynamic_icon(icon: HICON) {
+    let dynamic = if icon.0 == BASE_ICON.load(Ordering::Relaxed) {
+        0
+    } else {
+        icon.0
+    };
+    let old = DYNAMIC_ICON.swap(dynamic, Ordering::Relaxed);
+    if old != 0 && old != dynamic {
+        unsafe {
+            let _ = DestroyIcon(HICON(old));
+        }
+    }
+}
+
+fn base_icon() -> HICON {
+    let cached = BASE_ICON.load(Ordering::Relaxed);
+    if cached != 0 {
+        return HICON(cached);
+    }
+    let icon = load_icon();
+    BASE_ICON.store(icon.0, Ordering::Relaxed);
+    icon
+}
+
+pub fn build_dynamic_icon(weekday_char: char, settings: &Settings) -> HICON {
+    unsafe {
+        let width = GetSystemMetrics(SM_CXSMICON).max(16);
+        let height = GetSystemMetrics(SM_CYSMICON).max(16);
+        let screen_dc = GetDC(HWND(0));
+        let mem_dc = CreateCompatibleDC(screen_dc);
+
+        let info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                biHeight: -height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut bits: *mut std::ffi::c_void = std::ptr::null_mut();
+        let color = match CreateDIBSection(mem_dc, &info, DIB_RGB_COLORS, &mut bits, HANDLE(0), 0) {
+            Ok(bitmap) if !bits.is_null() => bitmap,
+            _ => {
+                let _ = DeleteDC(mem_dc);
+                ReleaseDC(HWND(0), screen_dc);
+                return HICON(0);
+            }
+        };
+        let old_bitmap = SelectObject(mem_dc, color);
+        let _ = DrawIconEx(mem_dc, 0, 0, base_icon(), width, height, 0, None, DI_NORMAL);
+
+        // Badge in the lower-right corner, leaving most of the base icon visible.
+        let badge = RECT {
+            left: width * 3 / 8,
+            top: height * 3 / 8,
+            right: width,
+            bottom: height,
+        };
+        let (badge_bg, badge_text) = tray_badge_colors(settings);
+        let brush = CreateSolidBrush(badge_bg);
+        FillRect(mem_dc, &badge, brush);
+        let face = to_wstring("Segoe UI");
+        let font = CreateFontW(
+            -(badge.bottom - badge.top),
+            0,
+            0,
+            0,
+            700,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            PCWSTR(face.as_ptr()),
+        );
+        let old_font = SelectObject(mem_dc, font);
+        SetBkMode(mem_dc, TRANSPARENT);
+        SetTextColor(mem_dc, badge_text);
+        let mut text: Vec<u16> = weekday_char.encode_utf16(&mut [0u16; 2]).to_vec();
+        let mut text_rect = badge;
+        DrawTextW(
+            mem_dc,
+            &mut text,
+            &mut text_rect,
+            DT_CENTER | DT_VCENTER | DT_SINGLELINE,
+        );
+        let _ = GdiFlush();
+
+        // GDI leaves alpha at zero for what it draws; make the badge opaque.
+        let pixels = std::slice::from_raw_parts_mut(bits as *mut u32, (width * height) as usize);
+        for y in badge.top..badge.bottom {
+            for x in badge.left..badge.right {
+                pixels[(y * width + x) as usize] |= 0xFF00_0000;
+            }
+        }
+
+        SelectObject(mem_dc, old_font);
+        SelectObject(mem_dc, old_bitmap);
+        let mask = CreateBitmap(width, height, 1, 1, None);
+        let icon_info = ICONINFO {
+            fIcon: BOOL(1),
+            xHotspot: 0,
+            yHotspot: 0,
+            hbmMask: mask,
+            hbmColor: color,
+        };
+        let icon = CreateIconIndirect(&icon_info).unwrap_or_default();
+
+        let _ = DeleteObject(mask);
+        let _ = DeleteObject(color);
+        let _ = DeleteObject(font);
+        let _ = DeleteObject(brush);
+        let _ = DeleteDC(mem_dc);
+        ReleaseDC(HWND(0), screen_dc);
+        icon
+    }
 }
 
 pub fn tray_icon_rect(hwnd: HWND) -> Option<RECT> {
@@ -143,14 +445,7 @@ fn load_icon_from_file(path: &Path) -> Option<HICON> {
 }
 
 fn find_icon_path() -> Option<PathBuf> {
-    let mut buffer = [0u16; 260];
-    let len = unsafe { GetModuleFileNameW(None, &mut buffer) } as usize;
-    if len == 0 {
-        return None;
-    }
-    let exe = String::from_utf16_lossy(&buffer[..len]);
-    let exe_path = PathBuf::from(exe);
-    let exe_dir = exe_path.parent()?.to_path_buf();
+    let exe_dir = exe_dir()?;
 
     let candidates = [
         exe_dir.join("assets").join("icon.ico"),
@@ -196,78 +491,142 @@ pub fn show_context_menu(hwnd: HWND, state: &AppState) {
     }
 }
 
+/// Appends the `restaurant_names` override in parentheses when alternate names are on.
+fn restaurant_label(settings: &Settings, code: &str, name: &str) -> String {
+    if !settings.show_alternate_names {
+        return name.to_string();
+    }
+    match settings.restaurant_names.get(code).map(|alt| alt.trim()) {
+        Some(alt) if !alt.is_empty() && alt != name => format!("{} ({})", name, alt),
+        _ => name.to_string(),
+    }
+}
+
 fn build_context_menu(state: &AppState) -> HMENU {
+    let t = |key: &str| text_for(&state.settings.language, key);
     unsafe {
         let menu = CreatePopupMenu().expect("CreatePopupMenu");
 
+        let refresh_status = match state.settings.refresh_minutes {
+            0 => t("menuAutoRefreshStatusOff"),
+            minutes => t("menuAutoRefreshEvery").replace("{}", &minutes.to_string()),
+        };
+        disabled_menu_item(menu, &refresh_status);
+        let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+
         let restaurant_menu = CreatePopupMenu().expect("CreatePopupMenu");
-        append_menu_item(
-            restaurant_menu,
-            CMD_RESTAURANT_0437,
-            "Snellmania",
-            state.settings.restaurant_code == "0437",
-        );
-        append_menu_item(
-            restaurant_menu,
-            CMD_RESTAURANT_SNELLARI_RSS,
-            "Snellari",
-            state.settings.restaurant_code == "snellari-rss",
-        );
-        append_menu_item(
-            restaurant_menu,
-            CMD_RESTAURANT_0436,
-            "Canthia",
-            state.settings.restaurant_code == "0436",
-        );
-        append_menu_item(
-            restaurant_menu,
-            CMD_RESTAURANT_0439,
-            "Tietoteknia",
-            state.settings.restaurant_code == "0439",
-        );
-        append_menu_item(
-            restaurant_menu,
-            CMD_RESTAURANT_HUOMEN_BIOTEKNIA,
-            "Hyvä Huomen",
-            state.settings.restaurant_code == "huomen-bioteknia",
-        );
-        append_menu_item(
-            restaurant_menu,
-            CMD_RESTAURANT_ANTELL_ROUND,
-            "Antell Round",
-            state.settings.restaurant_code == "antell-round",
-        );
-        append_menu_item(
-            restaurant_menu,
-            CMD_RESTAURANT_ANTELL_HIGHWAY,
-            "Antell Highway",
-            state.settings.restaurant_code == "antell-highway",
-        );
+        let builtin = [
+            (CMD_RESTAURANT_0437, "Snellmania", "0437", Provider::Compass),
+            (
+                CMD_RESTAURANT_SNELLARI_RSS,
+                "Snellari",
+                "snellari-rss",
+                Provider::CompassRss,
+            ),
+            (CMD_RESTAURANT_0436, "Canthia", "0436", Provider::Compass),
+            (
+                CMD_RESTAURANT_0439,
+                "Tietoteknia",
+                "0439",
+                Provider::Compass,
+            ),
+            (
+                CMD_RESTAURANT_HUOMEN_BIOTEKNIA,
+                "Hyvä Huomen",
+                "huomen-bioteknia",
+                Provider::HuomenJson,
+            ),
+            (
+                CMD_RESTAURANT_ANTELL_ROUND,
+                "Antell Round",
+                "antell-round",
+                Provider::Antell,
+            ),
+            (
+                CMD_RESTAURANT_ANTELL_HIGHWAY,
+                "Antell Highway",
+                "antell-highway",
+                Provider::Antell,
+            ),
+        ];
+        for (id, label, code, provider) in builtin {
+            if provider_disabled(&state.settings, provider) {
+                continue;
+            }
+            append_menu_item(
+                restaurant_menu,
+                id,
+                &restaurant_label(&state.settings, code, label),
+                state.settings.restaurant_code == code,
+            );
+        }
+        let custom = custom_restaurants();
+        if custom
+            .iter()
+            .any(|r| !provider_disabled(&state.settings, r.provider))
+        {
+            let _ = AppendMenuW(restaurant_menu, MF_SEPARATOR, 0, PCWSTR::null());
+        }
+        for (offset, restaurant) in custom.iter().enumerate() {
+            let id = CMD_RESTAURANT_CUSTOM_FIRST as usize + offset;
+            if id > CMD_RESTAURANT_CUSTOM_LAST as usize {
+                break;
+            }
+            if provider_disabled(&state.settings, restaurant.provider) {
+                continue;
+            }
+            append_menu_item(
+                restaurant_menu,
+                id as u16,
+                &restaurant_label(&state.settings, &restaurant.code, &restaurant.name),
+                state.settings.restaurant_code == restaurant.code,
+            );
+        }
         let _ = AppendMenuW(
             menu,
             MF_POPUP,
             restaurant_menu.0 as usize,
-            PCWSTR(to_wstring("Restaurant").as_ptr()),
+            PCWSTR(to_wstring(&t("menuRestaurant")).as_ptr()),
         );
 
         let language_menu = CreatePopupMenu().expect("CreatePopupMenu");
+        let auto_label = t("menuLanguageAuto").replace(
+            "{}",
+            &t(match state.settings.language.as_str() {
+                "fi" => "languageNameFi",
+                "sv" => "languageNameSv",
+                _ => "languageNameEn",
+            }),
+        );
+        append_menu_item(
+            language_menu,
+            CMD_LANGUAGE_AUTO,
+            &auto_label,
+            state.settings.auto_language,
+        );
         append_menu_item(
             language_menu,
             CMD_LANGUAGE_FI,
             "Suomi",
-            state.settings.language == "fi",
+            !state.settings.auto_language && state.settings.language == "fi",
+        );
+        append_menu_item(
+            language_menu,
+            CMD_LANGUAGE_SV,
+            "Svenska",
+            !state.settings.auto_language && state.settings.language == "sv",
         );
         append_menu_item(
             language_menu,
             CMD_LANGUAGE_EN,
             "English",
-            state.settings.language == "en",
+            !state.settings.auto_language && state.settings.language == "en",
         );
         let _ = AppendMenuW(
             menu,
             MF_POPUP,
             language_menu.0 as usize,
-            PCWSTR(to_wstring("Language").as_ptr()),
+            PCWSTR(to_wstring(&t("menuLanguage")).as_ptr()),
         );
 
         let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
@@ -275,44 +634,70 @@ fn build_context_menu(state: &AppState) -> HMENU {
         append_menu_toggle(
             menu,
             CMD_TOGGLE_SHOW_PRICES,
-            "Show prices",
+            &t("menuShowPrices"),
             state.settings.show_prices,
         );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_PRICE_COLUMN,
+            &t("menuPriceColumn"),
+            state.settings.price_column,
+        );
         let price_menu = CreatePopupMenu().expect("CreatePopupMenu");
         append_menu_toggle(
             price_menu,
             CMD_TOGGLE_SHOW_STUDENT_PRICE,
-            "Student",
+            &t("menuPriceStudent"),
             state.settings.show_student_price,
         );
         append_menu_toggle(
             price_menu,
             CMD_TOGGLE_SHOW_STAFF_PRICE,
-            "Staff",
+            &t("menuPriceStaff"),
             state.settings.show_staff_price,
         );
         append_menu_toggle(
             price_menu,
             CMD_TOGGLE_SHOW_GUEST_PRICE,
-            "Guest",
+            &t("menuPriceGuest"),
             state.settings.show_guest_price,
         );
         let _ = AppendMenuW(
             menu,
             MF_POPUP,
             price_menu.0 as usize,
-            PCWSTR(to_wstring("Price groups").as_ptr()),
+            PCWSTR(to_wstring(&t("menuPriceGroups")).as_ptr()),
         );
-        append_menu_toggle(
+        let threshold_menu = CreatePopupMenu().expect("CreatePopupMenu");
+        append_menu_item(
+            threshold_menu,
+            CMD_THRESHOLD_OFF,
+            &t("menuThresholdOff"),
+            !state.settings.hide_expensive_student_meals,
+        );
+        for (id, value) in [
+            (CMD_THRESHOLD_350, 3.5),
+            (CMD_THRESHOLD_400, 4.0),
+            (CMD_THRESHOLD_500, 5.0),
+        ] {
+            append_menu_item(
+                threshold_menu,
+                id,
+                &t("menuThresholdOver").replace("{}", &format_eur(value, &state.settings.language)),
+                state.settings.hide_expensive_student_meals
+                    && state.settings.expensive_student_threshold_eur == value,
+            );
+        }
+        let _ = AppendMenuW(
             menu,
-            CMD_TOGGLE_HIDE_EXPENSIVE_STUDENT,
-            "Hide expensive student meals",
-            state.settings.hide_expensive_student_meals,
+            MF_POPUP,
+            threshold_menu.0 as usize,
+            PCWSTR(to_wstring(&t("menuHideExpensive")).as_ptr()),
         );
         append_menu_toggle(
             menu,
             CMD_TOGGLE_SHOW_ALLERGENS,
-            "Show allergens",
+            &t("menuShowAllergens"),
             state.settings.show_allergens,
         );
         let highlight_menu = CreatePopupMenu().expect("CreatePopupMenu");
@@ -341,31 +726,188 @@ fn build_context_menu(state: &AppState) -> HMENU {
             menu,
             MF_POPUP,
             highlight_menu.0 as usize,
-            PCWSTR(to_wstring("Highlight allergens").as_ptr()),
+            PCWSTR(to_wstring(&t("menuHighlightAllergens")).as_ptr()),
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_SHOW_WEEK_NUMBER,
+            &t("menuShowWeekNumber"),
+            state.settings.show_week_number,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_SHOW_STALE_MARKER,
+            &t("menuShowStale"),
+            state.settings.show_stale_marker,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_REVERSE_GROUP_ORDER,
+            &t("menuReverseOrder"),
+            state.settings.reverse_group_order,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_HEADING_POSITION,
+            &t("menuHeadingsBelow"),
+            state.settings.heading_position == "below",
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_GROW_DIRECTION,
+            &t("menuGrowDown"),
+            state.settings.grow_direction == "down",
+        );
+        let anchor_menu = CreatePopupMenu().expect("CreatePopupMenu");
+        append_menu_item(
+            anchor_menu,
+            CMD_ANCHOR_TRAY,
+            &t("menuAnchorTray"),
+            state.settings.popup_anchor == "tray",
+        );
+        append_menu_item(
+            anchor_menu,
+            CMD_ANCHOR_CURSOR,
+            &t("menuAnchorCursor"),
+            state.settings.popup_anchor == "cursor",
+        );
+        append_menu_item(
+            anchor_menu,
+            CMD_ANCHOR_TOP_RIGHT,
+            &t("menuAnchorTopRight"),
+            state.settings.popup_anchor == "fixed_top_right",
+        );
+        append_menu_item(
+            anchor_menu,
+            CMD_ANCHOR_BOTTOM_RIGHT,
+            &t("menuAnchorBottomRight"),
+            state.settings.popup_anchor == "fixed_bottom_right",
+        );
+        let _ = AppendMenuW(
+            menu,
+            MF_POPUP,
+            anchor_menu.0 as usize,
+            PCWSTR(to_wstring(&t("menuPopupAnchor")).as_ptr()),
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_FLAG_REPEAT_MENUS,
+            &t("menuFlagRepeated"),
+            state.settings.flag_repeat_menus,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_OPEN_ON_HOVER,
+            &t("menuOpenOnHover"),
+            state.settings.open_on_hover,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_NOTIFY,
+            &t("menuDailyNotification"),
+            state.settings.notify_daily_menu,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_LUNCH_COUNTDOWN,
+            &t("menuLunchCountdown"),
+            state.settings.lunch_countdown,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_REFRESH_ON_CLICK_OPEN,
+            &t("menuRefreshOnClick"),
+            state.settings.refresh_on_click_open,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_REFRESH_ON_HOVER_OPEN,
+            &t("menuRefreshOnHover"),
+            state.settings.refresh_on_hover_open,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_ALTERNATE_NAMES,
+            &t("menuAlternateNames"),
+            state.settings.show_alternate_names,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_SHOW_ENERGY,
+            &t("menuShowEnergy"),
+            state.settings.show_energy,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_HIGHLIGHT_FAVORITES,
+            &t("menuHighlightFavorites"),
+            state.settings.highlight_favorites,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_MICRO_MODE,
+            &t("menuMicroMode"),
+            state.settings.micro_mode,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_SHOW_ALLERGEN_LEGEND,
+            &t("menuAllergenLegend"),
+            state.settings.show_allergen_legend,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_ALLERGEN_ICONS,
+            &t("menuAllergenIcons"),
+            state.settings.allergen_icons,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_SWAP_CLICK_BUTTONS,
+            &t("menuSwapClicks"),
+            state.settings.swap_click_buttons,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_INVERT_WHEEL,
+            &t("menuInvertWheel"),
+            state.settings.invert_wheel,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_CLOSE_ON_OPEN_URL,
+            &t("menuCloseOnOpenUrl"),
+            state.settings.close_popup_on_open_url,
+        );
+        append_menu_toggle(
+            menu,
+            CMD_TOGGLE_PIN,
+            &t("menuPinPopup"),
+            state.settings.pin_popup,
         );
         let theme_menu = CreatePopupMenu().expect("CreatePopupMenu");
         append_menu_item(
             theme_menu,
             CMD_THEME_LIGHT,
-            "Light",
+            &t("menuThemeLight"),
             state.settings.theme == "light",
         );
         append_menu_item(
             theme_menu,
             CMD_THEME_DARK,
-            "Dark",
+            &t("menuThemeDark"),
             state.settings.theme == "dark",
         );
         append_menu_item(
             theme_menu,
             CMD_THEME_BLUE,
-            "Blue",
+            &t("menuThemeBlue"),
             state.settings.theme == "blue",
         );
         append_menu_item(
             theme_menu,
             CMD_THEME_GREEN,
-            "Green",
+            &t("menuThemeGreen"),
             state.settings.theme == "green",
         );
         append_menu_item(
@@ -380,76 +922,109 @@ fn build_context_menu(state: &AppState) -> HMENU {
             "Teletext 2",
             state.settings.theme == "teletext2",
         );
+        if state.settings.custom_theme.is_some() {
+            append_menu_item(
+                theme_menu,
+                CMD_THEME_CUSTOM,
+                &t("menuThemeCustom"),
+                state.settings.theme == "custom",
+            );
+        }
         let _ = AppendMenuW(
             menu,
             MF_POPUP,
             theme_menu.0 as usize,
-            PCWSTR(to_wstring("Theme").as_ptr()),
+            PCWSTR(to_wstring(&t("menuTheme")).as_ptr()),
         );
         append_menu_toggle(
             menu,
             CMD_TOGGLE_STARTUP,
-            "Run at startup",
+            &t("menuRunAtStartup"),
             crate::startup::is_enabled(),
         );
         let developer_menu = CreatePopupMenu().expect("CreatePopupMenu");
         append_menu_toggle(
             developer_menu,
             CMD_TOGGLE_LOGGING,
-            "Enable logging",
+            &t("menuEnableLogging"),
             state.settings.enable_logging,
         );
         append_menu_item(
             developer_menu,
             CMD_OPEN_APPDATA_DIR,
-            "Open app data folder",
+            &t("menuOpenAppData"),
+            false,
+        );
+        append_menu_item(
+            developer_menu,
+            CMD_COPY_RAW_PAYLOAD,
+            &t("menuCopyRawPayload"),
+            false,
+        );
+        append_menu_item(
+            developer_menu,
+            CMD_TELETEXT_FULLSCREEN,
+            &t("menuTeletextFullscreen"),
             false,
         );
         let _ = AppendMenuW(
             menu,
             MF_POPUP,
             developer_menu.0 as usize,
-            PCWSTR(to_wstring("Developer").as_ptr()),
+            PCWSTR(to_wstring(&t("menuDeveloper")).as_ptr()),
         );
 
         let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
 
-        append_menu_item(menu, CMD_REFRESH_NOW, "Refresh now", false);
+        append_menu_item(menu, CMD_REFRESH_NOW, &t("menuRefreshNow"), false);
 
         let refresh_menu = CreatePopupMenu().expect("CreatePopupMenu");
         append_menu_item(
             refresh_menu,
             CMD_REFRESH_OFF,
-            "Off",
+            &t("menuRefreshOff"),
             state.settings.refresh_minutes == 0,
         );
         append_menu_item(
             refresh_menu,
             CMD_REFRESH_60,
-            "60 minutes",
+            &t("menuMinutes").replace("{}", "60"),
             state.settings.refresh_minutes == 60,
         );
         append_menu_item(
             refresh_menu,
             CMD_REFRESH_240,
-            "240 minutes",
+            &t("menuMinutes").replace("{}", "240"),
             state.settings.refresh_minutes == 240,
         );
         append_menu_item(
             refresh_menu,
             CMD_REFRESH_1440,
-            "1440 minutes",
+            &t("menuMinutes").replace("{}", "1440"),
             state.settings.refresh_minutes == 1440,
         );
+        let custom = !REFRESH_PRESETS.contains(&state.settings.refresh_minutes);
+        let custom_value = if custom {
+            t("menuMinutesShort").replace("{}", &state.settings.refresh_minutes.to_string())
+        } else {
+            "settings.json".to_string()
+        };
+        append_menu_item(
+            refresh_menu,
+            CMD_REFRESH_CUSTOM,
+            &t("menuRefreshCustom").replace("{}", &custom_value),
+            custom,
+        );
         let _ = AppendMenuW(
             menu,
             MF_POPUP,
             refresh_menu.0 as usize,
-            PCWSTR(to_wstring("Auto refresh").as_ptr()),
+            PCWSTR(to_wstring(&t("menuAutoRefresh")).as_ptr()),
         );
 
         let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
-        append_menu_item(menu, CMD_QUIT, "Quit", false);
+        disabled_menu_item(menu, concat!("compass-lunch v", env!("CARGO_PKG_VERSION")));
+        append_menu_item(menu, CMD_QUIT, &t("menuQuit"), false);
 
         menu
     }
@@ -500,3 +1075,38 @@ pub fn disabled_menu_item(menu: HMENU, label: &str) {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{copy_truncated, fit_tooltip};
+
+    #[test]
+    fn tooltip_fits_sz_tip() {
+        assert_eq!(
+            fit_tooltip("Snellmania · Lohikeitto"),
+            "Snellmania · Lohikeitto"
+        );
+        let long = format!("Snellmania · {}", "kasvis".repeat(30));
+        let fitted = fit_tooltip(&long);
+        assert_eq!(fitted.encode_utf16().count(), 127);
+        assert!(fitted.ends_with('…'));
+        let emoji = "\u{1F331}".repeat(100);
+        assert_eq!(fit_tooltip(&emoji).encode_utf16().count(), 127);
+    }
+
+    #[test]
+    fn fixed_buffer_copy_keeps_surrogate_pairs_whole() {
+        let mut tip = [0xFFFFu16; 128];
+        let value = format!("{}\u{1F331}", "a".repeat(126));
+        copy_truncated(&mut tip, &value);
+        assert_eq!(String::from_utf16(&tip[..126]).unwrap(), "a".repeat(126));
+        assert_eq!(tip[126], 0);
+
+        let mut tip = [0xFFFFu16; 128];
+        let value = format!("{}\u{1F331}", "a".repeat(125));
+        copy_truncated(&mut tip, &value);
+        let end = tip.iter().position(|&unit| unit == 0).unwrap();
+        assert_eq!(end, 127);
+        assert_eq!(String::from_utf16(&tip[..end]).unwrap(), value);
+    }
+}
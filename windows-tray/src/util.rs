@@ -1,6 +1,81 @@
+use crate::log::log_line;
 use std::ffi::OsStr;
+use std::fs;
 use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use time::{Date, OffsetDateTime, UtcOffset};
 
 pub fn to_wstring(value: &str) -> Vec<u16> {
     OsStr::new(value).encode_wide().chain(Some(0)).collect()
 }
+
+/// Current local time. When the local offset can't be read (it can fail once other threads are
+/// running), reuses the last offset that could be read, then falls back to UTC.
+pub fn local_now() -> OffsetDateTime {
+    OffsetDateTime::now_utc().to_offset(local_offset())
+}
+
+fn local_offset() -> UtcOffset {
+    static LAST_OFFSET: Mutex<Option<UtcOffset>> = Mutex::new(None);
+    static FALLBACK_LOGGED: AtomicBool = AtomicBool::new(false);
+    if let Ok(offset) = UtcOffset::current_local_offset() {
+        *LAST_OFFSET.lock().unwrap() = Some(offset);
+        return offset;
+    }
+    let cached = *LAST_OFFSET.lock().unwrap();
+    if !FALLBACK_LOGGED.swap(true, Ordering::Relaxed) {
+        log_line(match cached {
+            Some(_) => "local offset unavailable, using last known offset",
+            None => "local offset unavailable, using UTC",
+        });
+    }
+    cached.unwrap_or(UtcOffset::UTC)
+}
+
+pub fn date_key(date: Date) -> String {
+    format!(
+        "{:04}-{:02}-{:02}",
+        date.year(),
+        date.month() as u8,
+        date.day()
+    )
+}
+
+pub fn local_date_key() -> String {
+    date_key(local_now().date())
+}
+
+pub fn local_datetime_from_epoch_ms(ms: i64) -> Option<OffsetDateTime> {
+    if ms <= 0 {
+        return None;
+    }
+    let secs = ms / 1000;
+    let nanos = ((ms % 1000) * 1_000_000) as u32;
+    let dt = OffsetDateTime::from_unix_timestamp(secs).ok()?;
+    Some(dt.replace_nanosecond(nanos).ok()?.to_offset(local_offset()))
+}
+
+pub fn date_key_from_epoch_ms(ms: i64) -> Option<String> {
+    local_datetime_from_epoch_ms(ms).map(|dt| date_key(dt.date()))
+}
+
+/// Writes `contents` to `<name>.<pid>.<n>.tmp` next to `path` and renames it into place, so a
+/// crash or a racing writer never leaves `path` truncated. The rename replaces an existing file
+/// atomically; each call gets its own temp file, so concurrent writers don't share one.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp = path.with_file_name(tmp_name);
+    if let Err(err) = fs::write(&tmp, contents).and_then(|_| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(err);
+    }
+    Ok(())
+}
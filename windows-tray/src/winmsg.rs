@@ -1,18 +1,29 @@
-use crate::app::{App, FetchApplyOutcome, FetchMessage};
+use crate::app::{App, FetchApplyOutcome, FetchMessage, FetchStatus};
+use crate::format::{parse_clock_minutes, text_for};
+use crate::keys::{PopupAction, MOD_CTRL, MOD_SHIFT};
 use crate::log::log_line;
 use crate::popup;
+use crate::restaurant::custom_restaurants;
+use crate::settings::{parse_hotkey, DEFAULT_HOTKEY};
 use crate::tray;
-use crate::util::to_wstring;
+use crate::util::{local_now, to_wstring};
+use std::sync::atomic::{AtomicU32, Ordering};
 use time::{OffsetDateTime, Time};
 use windows::core::PCWSTR;
 use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetKeyState, RegisterHotKey, TrackMouseEvent, UnregisterHotKey, TME_LEAVE, TRACKMOUSEEVENT,
+    VIRTUAL_KEY, VK_CONTROL, VK_SHIFT,
+};
 use windows::Win32::UI::WindowsAndMessaging::{
     DefWindowProcW, DestroyWindow, GetCursorPos, GetWindowLongPtrW, GetWindowRect, KillTimer,
-    LoadCursorW, PostQuitMessage, RegisterClassExW, SetForegroundWindow, SetTimer,
-    SetWindowLongPtrW, CREATESTRUCTW, CS_HREDRAW, CS_VREDRAW, GWLP_USERDATA, IDC_ARROW,
-    WM_ACTIVATE, WM_APP, WM_COMMAND, WM_CONTEXTMENU, WM_DESTROY, WM_KEYDOWN, WM_LBUTTONUP,
-    WM_MBUTTONUP, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_NCCREATE, WM_PAINT, WM_RBUTTONUP, WM_TIMER,
-    WNDCLASSEXW,
+    LoadCursorW, PostQuitMessage, RegisterClassExW, RegisterWindowMessageW, SetForegroundWindow,
+    SetTimer, SetWindowLongPtrW, CREATESTRUCTW, CS_HREDRAW, CS_VREDRAW, GWLP_USERDATA, IDC_ARROW,
+    SIZE_RESTORED, SWP_SHOWWINDOW, WINDOWPOS, WM_ACTIVATE, WM_APP, WM_CAPTURECHANGED, WM_COMMAND,
+    WM_CONTEXTMENU, WM_DESTROY, WM_HOTKEY, WM_KEYDOWN, WM_KEYFIRST, WM_KEYLAST, WM_KEYUP,
+    WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONUP, WM_MOUSEFIRST, WM_MOUSELAST, WM_MOUSEMOVE,
+    WM_MOUSEWHEEL, WM_NCCREATE, WM_PAINT, WM_RBUTTONUP, WM_SETTINGCHANGE, WM_SIZE, WM_TIMER,
+    WM_WINDOWPOSCHANGED, WNDCLASSEXW,
 };
 
 pub const TRAY_WND_CLASS: &str = "CompassLunchTrayWindow";
@@ -20,12 +31,25 @@ pub const POPUP_WND_CLASS: &str = "CompassLunchPopupWindow";
 
 pub const WM_TRAY_CALLBACK: u32 = WM_APP + 1;
 pub const WM_APP_FETCH_COMPLETE: u32 = WM_APP + 2;
+pub const WM_APP_POPUP_RENDER: u32 = WM_APP + 3;
+// Lives in Win32_UI_Controls, which is not otherwise needed.
+const WM_MOUSELEAVE: u32 = 0x02A3;
 
 pub const TIMER_REFRESH: usize = 1;
 pub const TIMER_MIDNIGHT: usize = 2;
 pub const TIMER_HOVER_CHECK: usize = 3;
 pub const TIMER_STALE_CHECK: usize = 4;
 pub const TIMER_RETRY_FETCH: usize = 5;
+pub const TIMER_COUNTDOWN: usize = 6;
+pub const TIMER_LUNCH_REMINDER: usize = 7;
+// Popup window timer; popup.rs owns ids from 100 for its animation.
+const POPUP_ROTATE_TIMER_ID: usize = 101;
+const AUTO_ROTATE_IDLE_MS: u32 = 60 * 1000;
+const POPUP_AUTO_HIDE_TIMER_ID: usize = 102;
+const AUTO_HIDE_RETRY_MS: u32 = 1000;
+const HOTKEY_TOGGLE_POPUP: i32 = 1;
+/// Broadcast when Explorer (re)creates the taskbar; registered in `register_window_classes`.
+static TASKBAR_CREATED: AtomicU32 = AtomicU32::new(0);
 
 pub fn register_window_classes(
     hinstance: windows::Win32::Foundation::HINSTANCE,
@@ -56,6 +80,9 @@ pub fn register_window_classes(
         if RegisterClassExW(&popup_class) == 0 {
             return Err(anyhow::anyhow!("RegisterClassExW for popup failed"));
         }
+
+        let taskbar_created = RegisterWindowMessageW(PCWSTR(to_wstring("TaskbarCreated").as_ptr()));
+        TASKBAR_CREATED.store(taskbar_created, Ordering::Relaxed);
     }
     Ok(())
 }
@@ -87,39 +114,29 @@ pub unsafe extern "system" fn tray_wndproc(
                 ));
             }
             match event {
-                WM_MOUSEMOVE => {}
-                WM_LBUTTONUP => {
-                    log_line("tray left click toggle popup");
-                    let popup_hwnd = app.hwnd_popup();
-                    if popup_is_visible(popup_hwnd) {
-                        app.persist_settings();
-                        let state = app.snapshot();
-                        popup::begin_close_animation(popup_hwnd, &state);
+                WM_MOUSEMOVE
+                    if app.hover_open_delay_ms().is_some()
+                        && !popup_is_visible(app.hwnd_popup()) =>
+                {
+                    if let Some(pt) = cursor_point() {
+                        app.set_hover_point(pt.x, pt.y);
+                        start_hover_timer(hwnd);
+                    }
+                }
+                WM_LBUTTONUP | WM_CONTEXTMENU => {
+                    let swap = app.snapshot().settings.swap_click_buttons;
+                    if (event == WM_LBUTTONUP) != swap {
+                        log_line("tray click toggle popup");
+                        toggle_popup_from_tray(hwnd, app);
                     } else {
-                        let state = app.snapshot();
-                        if let Some(rect) = tray::tray_icon_rect(hwnd) {
-                            popup::show_popup_for_tray_icon(popup_hwnd, &state, rect);
-                        } else if let Some(cursor_point) = cursor_point() {
-                            popup::show_popup_at(popup_hwnd, &state, cursor_point);
-                        } else {
-                            popup::show_popup(popup_hwnd, &state);
-                        }
-                        let _ = SetForegroundWindow(popup_hwnd);
+                        log_line("tray context menu");
+                        show_tray_context_menu(hwnd, app);
                     }
                 }
                 WM_RBUTTONUP => {}
-                WM_CONTEXTMENU => {
-                    log_line("tray context menu");
-                    app.persist_settings();
-                    let state = app.snapshot();
-                    popup::begin_close_animation(app.hwnd_popup(), &state);
-                    app.set_context_menu_open(true);
-                    tray::show_context_menu(hwnd, &state);
-                    app.set_context_menu_open(false);
-                }
                 WM_MBUTTONUP => {
                     log_line("tray middle click");
-                    app.open_current_url();
+                    open_url_from_popup(app.hwnd_popup(), app);
                 }
                 WM_MOUSEWHEEL => {}
                 _ => {}
@@ -127,6 +144,35 @@ pub unsafe extern "system" fn tray_wndproc(
             LRESULT(0)
         }
         WM_MOUSEWHEEL => LRESULT(0),
+        WM_HOTKEY => {
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() && wparam.0 as i32 == HOTKEY_TOGGLE_POPUP {
+                let app = &*(app);
+                log_line("hotkey toggle popup");
+                if app.no_tray {
+                    popup::toggle_popup(app.hwnd_popup(), &app.snapshot());
+                } else {
+                    toggle_popup_from_tray(hwnd, app);
+                }
+            }
+            LRESULT(0)
+        }
+        WM_SETTINGCHANGE => {
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() {
+                let app = &*(app);
+                if app.refresh_auto_language() {
+                    let _ = app.load_cache_for_current();
+                    app.check_stale_date_and_refresh();
+                    app.maybe_refresh_on_selection();
+                    if popup_is_visible(app.hwnd_popup()) {
+                        let state = app.snapshot();
+                        popup::resize_popup_keep_position(app.hwnd_popup(), &state);
+                    }
+                }
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
         WM_COMMAND => {
             let app = app_from_hwnd(hwnd);
             if app.is_null() {
@@ -135,6 +181,7 @@ pub unsafe extern "system" fn tray_wndproc(
             let app = &*(app);
             let cmd = (wparam.0 & 0xffff) as u16;
             handle_command(hwnd, app, cmd);
+            refresh_tray_tooltip(app);
             LRESULT(0)
         }
         WM_TIMER => {
@@ -145,11 +192,16 @@ pub unsafe extern "system" fn tray_wndproc(
             let app = &*(app);
             match wparam.0 as usize {
                 TIMER_REFRESH => {
+                    cancel_retry_timer(hwnd);
+                    app.reset_retry_backoff();
                     app.start_refresh();
                 }
                 TIMER_MIDNIGHT => {
                     app.start_refresh();
                     schedule_midnight_timer(hwnd);
+                    if !app.no_tray {
+                        tray::update_tray_icon(hwnd, &app.snapshot().settings);
+                    }
                 }
                 TIMER_HOVER_CHECK => {
                     handle_hover_check(hwnd, app);
@@ -157,6 +209,25 @@ pub unsafe extern "system" fn tray_wndproc(
                 TIMER_STALE_CHECK => {
                     handle_stale_check(hwnd, app);
                 }
+                TIMER_COUNTDOWN => {
+                    let state = app.snapshot();
+                    if state.settings.lunch_countdown && popup_is_visible(app.hwnd_popup()) {
+                        popup::resize_popup_keep_position(app.hwnd_popup(), &state);
+                    }
+                }
+                TIMER_LUNCH_REMINDER => {
+                    if !app.no_tray {
+                        if let Some((title, text)) = app.lunch_reminder() {
+                            tray::show_balloon(hwnd, &title, &text);
+                        } else {
+                            log_line("lunch reminder skipped; no menu for today");
+                        }
+                    }
+                    schedule_lunch_reminder_timer(
+                        hwnd,
+                        &app.snapshot().settings.lunch_reminder_time,
+                    );
+                }
                 TIMER_RETRY_FETCH => {
                     let _ = KillTimer(hwnd, TIMER_RETRY_FETCH);
                     app.start_refresh_retry();
@@ -180,6 +251,12 @@ pub unsafe extern "system" fn tray_wndproc(
                         cancel_retry_timer(hwnd);
                         app.reset_retry_backoff();
                         app.prefetch_enabled_restaurants();
+                        if !app.no_tray {
+                            if let Some((title, text)) = app.take_menu_notification() {
+                                tray::show_balloon(hwnd, &title, &text);
+                            }
+                        }
+                        refresh_tray_tooltip(app);
                         let state = app.snapshot();
                         if popup_is_visible(app.hwnd_popup()) {
                             popup::resize_popup_keep_position(app.hwnd_popup(), &state);
@@ -188,9 +265,14 @@ pub unsafe extern "system" fn tray_wndproc(
                         }
                     }
                     FetchApplyOutcome::CurrentFailure => {
-                        let delay = app.next_retry_delay_ms();
-                        schedule_retry_timer(hwnd, delay);
+                        refresh_tray_tooltip(app);
                         let state = app.snapshot();
+                        if state.error_kind.is_retryable() {
+                            match app.next_retry_delay_ms() {
+                                Some(delay) => schedule_retry_timer(hwnd, delay),
+                                None => log_line("retry limit reached; waiting for next refresh"),
+                            }
+                        }
                         if popup_is_visible(app.hwnd_popup()) {
                             popup::resize_popup_keep_position(app.hwnd_popup(), &state);
                         }
@@ -206,14 +288,28 @@ pub unsafe extern "system" fn tray_wndproc(
             if !app.is_null() {
                 let app_ref = &*(app);
                 app_ref.persist_settings();
+                app_ref.stop_menu_server();
                 tray::remove_tray_icon(hwnd);
                 let _ = DestroyWindow(app_ref.hwnd_popup());
                 drop(Box::from_raw(app));
             }
             cancel_retry_timer(hwnd);
+            let _ = UnregisterHotKey(hwnd, HOTKEY_TOGGLE_POPUP);
             PostQuitMessage(0);
             LRESULT(0)
         }
+        _ if msg != 0 && msg == TASKBAR_CREATED.load(Ordering::Relaxed) => {
+            // Explorer restarted and dropped the icon; add it back.
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() && !(*app).no_tray {
+                let app = &*(app);
+                match tray::add_tray_icon(hwnd, WM_TRAY_CALLBACK, &app.snapshot().settings) {
+                    Ok(()) => refresh_tray_tooltip(app),
+                    Err(err) => log_line(&format!("tray icon re-add failed: {}", err)),
+                }
+            }
+            LRESULT(0)
+        }
         _ => DefWindowProcW(hwnd, msg, wparam, lparam),
     }
 }
@@ -224,6 +320,12 @@ pub unsafe extern "system" fn popup_wndproc(
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
+    if (WM_KEYFIRST..=WM_KEYLAST).contains(&msg) || (WM_MOUSEFIRST..=WM_MOUSELAST).contains(&msg) {
+        let app = app_from_hwnd(hwnd);
+        if !app.is_null() {
+            schedule_auto_hide_timer(hwnd, &*(app));
+        }
+    }
     match msg {
         WM_NCCREATE => {
             let cs = &*(lparam.0 as *const CREATESTRUCTW);
@@ -239,11 +341,21 @@ pub unsafe extern "system" fn popup_wndproc(
             }
             LRESULT(0)
         }
+        WM_APP_POPUP_RENDER => {
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() {
+                popup::render_layered(hwnd, &(*app).snapshot());
+            }
+            LRESULT(0)
+        }
         WM_ACTIVATE => {
             let app = app_from_hwnd(hwnd);
             if wparam.0 == 0 {
                 if !app.is_null() {
                     let app = &*(app);
+                    if popup_pinned(app) {
+                        return LRESULT(0);
+                    }
                     app.persist_settings();
                     let state = app.snapshot();
                     popup::begin_close_animation(hwnd, &state);
@@ -259,29 +371,100 @@ pub unsafe extern "system" fn popup_wndproc(
                 return LRESULT(0);
             }
             let app = &*(app);
+            schedule_auto_rotate_timer(hwnd, app, true);
             let key = wparam.0 as u32;
-            match key {
-                0x1B => {
+            if popup::teletext_active() {
+                if key == 0x1B {
+                    popup::hide_popup(hwnd);
+                }
+                return LRESULT(0);
+            }
+            match app.popup_action(key, held_modifiers(key)) {
+                Some(PopupAction::Close) => {
                     app.persist_settings();
                     let state = app.snapshot();
                     popup::begin_close_animation(hwnd, &state);
                 }
-                0x25 | 0x41 => {
-                    cycle_popup_restaurant(hwnd, app, -1);
+                Some(PopupAction::CycleRestaurant(direction)) => {
+                    cycle_popup_restaurant(hwnd, app, direction);
                 }
-                0x27 | 0x44 => {
-                    cycle_popup_restaurant(hwnd, app, 1);
+                Some(PopupAction::SelectRestaurant(index)) => {
+                    select_popup_restaurant(hwnd, app, index);
                 }
-                _ => {}
+                Some(PopupAction::CycleDay(direction)) => {
+                    cycle_popup_day(hwnd, app, direction);
+                }
+                Some(PopupAction::PeekNextDay) => {
+                    let state = app.snapshot();
+                    popup::set_day_peek(hwnd, &state, 1);
+                }
+                Some(PopupAction::OpenUrl) => {
+                    open_url_from_popup(hwnd, app);
+                }
+                Some(PopupAction::CopyMenu) => {
+                    let state = app.snapshot();
+                    if popup::copy_menu_to_clipboard(hwnd, &state) {
+                        log_line("copied menu to clipboard");
+                    }
+                }
+                Some(PopupAction::StarDish) => {
+                    let dish = cursor_point().and_then(|pt| popup::dish_at_screen_point(hwnd, pt));
+                    if let Some(dish) = dish {
+                        app.toggle_starred_dish(&dish);
+                        let state = app.snapshot();
+                        popup::resize_popup_keep_position(hwnd, &state);
+                    }
+                }
+                Some(PopupAction::TogglePrices) => {
+                    app.toggle_show_prices();
+                    let state = app.snapshot();
+                    popup::resize_popup_keep_position(hwnd, &state);
+                }
+                Some(PopupAction::TogglePin) => {
+                    app.toggle_pin_popup();
+                    let state = app.snapshot();
+                    popup::resize_popup_keep_position(hwnd, &state);
+                }
+                None => {}
+            }
+            LRESULT(0)
+        }
+        WM_KEYUP => {
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() && (*app).is_peek_key(wparam.0 as u32) {
+                popup::set_day_peek(hwnd, &(*app).snapshot(), 0);
+            }
+            LRESULT(0)
+        }
+        WM_LBUTTONDOWN => {
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() {
+                let x = (lparam.0 as u32 & 0xFFFF) as i16 as i32;
+                let y = ((lparam.0 as u32 >> 16) & 0xFFFF) as i16 as i32;
+                popup::begin_header_drag(hwnd, &(*app).snapshot(), x, y);
             }
             LRESULT(0)
         }
+        WM_CAPTURECHANGED => {
+            popup::end_header_drag();
+            LRESULT(0)
+        }
         WM_LBUTTONUP => {
+            if popup::teletext_active() {
+                popup::hide_popup(hwnd);
+                return LRESULT(0);
+            }
+            if popup::end_header_drag() {
+                return LRESULT(0);
+            }
             let app = app_from_hwnd(hwnd);
             if app.is_null() {
                 return LRESULT(0);
             }
             let app = &*(app);
+            if expand_micro_popup(hwnd, app) {
+                return LRESULT(0);
+            }
             let x = (lparam.0 as u32 & 0xFFFF) as i16 as i32;
             let y = ((lparam.0 as u32 >> 16) & 0xFFFF) as i16 as i32;
             if let Some(action) = popup::header_button_at(hwnd, x, y) {
@@ -292,6 +475,12 @@ pub unsafe extern "system" fn popup_wndproc(
                     popup::HeaderButtonAction::Next => {
                         cycle_popup_restaurant(hwnd, app, 1);
                     }
+                    popup::HeaderButtonAction::PrevDay => {
+                        cycle_popup_day(hwnd, app, -1);
+                    }
+                    popup::HeaderButtonAction::NextDay => {
+                        cycle_popup_day(hwnd, app, 1);
+                    }
                     popup::HeaderButtonAction::Close => {
                         app.persist_settings();
                         let state = app.snapshot();
@@ -308,23 +497,71 @@ pub unsafe extern "system" fn popup_wndproc(
                 let app = &*(app);
                 app.persist_settings();
                 let state = app.snapshot();
-                popup::begin_close_animation(hwnd, &state);
+                if !state.settings.pin_popup {
+                    popup::begin_close_animation(hwnd, &state);
+                }
                 let state = app.snapshot();
                 tray::show_context_menu(app.hwnd_tray(), &state);
             }
             LRESULT(0)
         }
+        WM_MOUSEMOVE => {
+            if popup::drag_popup(hwnd) {
+                return LRESULT(0);
+            }
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() {
+                expand_micro_popup(hwnd, &*(app));
+            }
+            LRESULT(0)
+        }
+        WM_SIZE => {
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() && wparam.0 as u32 == SIZE_RESTORED {
+                let app = &*(app);
+                // lparam carries the client width; place_popup sizes the outer window, which
+                // is wider by the frame in the --no-tray captioned window.
+                let mut rect = RECT::default();
+                let width = if GetWindowRect(hwnd, &mut rect).is_ok() {
+                    rect.right - rect.left
+                } else {
+                    (lparam.0 as u32 & 0xFFFF) as i32
+                };
+                if let Some(width) = popup::user_resized_width(&app.snapshot(), width) {
+                    app.set_popup_width(width);
+                }
+            }
+            LRESULT(0)
+        }
+        WM_MOUSELEAVE => {
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() {
+                let state = (*app).snapshot();
+                popup::set_micro_expanded(hwnd, &state, false);
+            }
+            LRESULT(0)
+        }
         WM_MOUSEWHEEL => {
             let app = app_from_hwnd(hwnd);
             if app.is_null() {
                 return LRESULT(0);
             }
             let app = &*(app);
+            schedule_auto_rotate_timer(hwnd, app, true);
             let delta = ((wparam.0 >> 16) & 0xFFFF) as i16 as i32;
+            if popup::scroll_popup(hwnd, delta) {
+                return LRESULT(0);
+            }
+            // Only restaurant cycling follows invert_wheel; content scrolling above never flips.
+            let direction = if app.snapshot().settings.invert_wheel {
+                1
+            } else {
+                -1
+            };
             if delta > 0 {
-                cycle_popup_restaurant(hwnd, app, -1);
+                cycle_popup_restaurant(hwnd, app, direction);
             } else if delta < 0 {
-                cycle_popup_restaurant(hwnd, app, 1);
+                cycle_popup_restaurant(hwnd, app, -direction);
             } else {
                 return LRESULT(0);
             }
@@ -335,13 +572,128 @@ pub unsafe extern "system" fn popup_wndproc(
                 popup::tick_animation(hwnd);
                 return LRESULT(0);
             }
+            if wparam.0 == POPUP_AUTO_HIDE_TIMER_ID {
+                let app = app_from_hwnd(hwnd);
+                if !app.is_null() {
+                    handle_auto_hide(hwnd, &*(app));
+                }
+                return LRESULT(0);
+            }
+            if wparam.0 == POPUP_ROTATE_TIMER_ID {
+                let app = app_from_hwnd(hwnd);
+                if !app.is_null() {
+                    let app = &*(app);
+                    cycle_popup_restaurant(hwnd, app, 1);
+                    schedule_auto_rotate_timer(hwnd, app, false);
+                }
+            }
             LRESULT(0)
         }
+        WM_WINDOWPOSCHANGED => {
+            let pos = &*(lparam.0 as *const WINDOWPOS);
+            let app = app_from_hwnd(hwnd);
+            if !app.is_null() && (pos.flags & SWP_SHOWWINDOW).0 != 0 {
+                schedule_auto_hide_timer(hwnd, &*(app));
+            }
+            // DefWindowProcW still has to turn this into WM_SIZE and WM_MOVE.
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
         WM_DESTROY => LRESULT(0),
         _ => DefWindowProcW(hwnd, msg, wparam, lparam),
     }
 }
 
+/// `MOD_*` bits for the modifiers held with `key`, leaving out `key` itself so a bare Shift
+/// press looks up as plain Shift.
+fn held_modifiers(key: u32) -> u32 {
+    let held = |vk: VIRTUAL_KEY| vk.0 as u32 != key && unsafe { GetKeyState(vk.0 as i32) } < 0;
+    let mut modifiers = 0;
+    if held(VK_CONTROL) {
+        modifiers |= MOD_CTRL;
+    }
+    if held(VK_SHIFT) {
+        modifiers |= MOD_SHIFT;
+    }
+    modifiers
+}
+
+/// Expands a collapsed micro-mode popup and asks for WM_MOUSELEAVE so it collapses again.
+fn expand_micro_popup(hwnd: HWND, app: &App) -> bool {
+    if popup::micro_expanded() {
+        return false;
+    }
+    let state = app.snapshot();
+    if !popup::set_micro_expanded(hwnd, &state, true) {
+        return false;
+    }
+    let mut track = TRACKMOUSEEVENT {
+        cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+        dwFlags: TME_LEAVE,
+        hwndTrack: hwnd,
+        dwHoverTime: 0,
+    };
+    unsafe {
+        let _ = TrackMouseEvent(&mut track);
+    }
+    true
+}
+
+/// A pinned popup only closes through Esc or its close button.
+fn popup_pinned(app: &App) -> bool {
+    app.snapshot().settings.pin_popup
+}
+
+fn toggle_popup_from_tray(hwnd: HWND, app: &App) {
+    stop_hover_timer(hwnd);
+    app.clear_hover_point();
+    let popup_hwnd = app.hwnd_popup();
+    if popup_is_visible(popup_hwnd) && popup_pinned(app) {
+        unsafe {
+            let _ = SetForegroundWindow(popup_hwnd);
+        }
+    } else if popup_is_visible(popup_hwnd) {
+        app.persist_settings();
+        let state = app.snapshot();
+        popup::begin_close_animation(popup_hwnd, &state);
+    } else {
+        show_popup_from_tray(hwnd, app, false);
+    }
+}
+
+fn show_popup_from_tray(hwnd: HWND, app: &App, opened_by_hover: bool) {
+    let popup_hwnd = app.hwnd_popup();
+    let state = app.snapshot();
+    if let Some(rect) = tray::tray_icon_rect(hwnd) {
+        popup::show_popup_for_tray_icon(popup_hwnd, &state, rect);
+    } else if let Some(cursor_point) = cursor_point() {
+        popup::show_popup_at(popup_hwnd, &state, cursor_point);
+    } else {
+        popup::show_popup(popup_hwnd, &state);
+    }
+    unsafe {
+        let _ = SetForegroundWindow(popup_hwnd);
+    }
+    let refresh = if opened_by_hover {
+        state.settings.refresh_on_hover_open
+    } else {
+        state.settings.refresh_on_click_open
+    };
+    if refresh {
+        app.maybe_refresh_on_selection();
+    }
+}
+
+fn show_tray_context_menu(hwnd: HWND, app: &App) {
+    app.persist_settings();
+    let state = app.snapshot();
+    if !state.settings.pin_popup {
+        popup::begin_close_animation(app.hwnd_popup(), &state);
+    }
+    app.set_context_menu_open(true);
+    tray::show_context_menu(hwnd, &state);
+    app.set_context_menu_open(false);
+}
+
 fn cycle_popup_restaurant(hwnd: HWND, app: &App, direction: i32) {
     let old_state = app.snapshot();
     app.cycle_restaurant(direction);
@@ -351,6 +703,100 @@ fn cycle_popup_restaurant(hwnd: HWND, app: &App, direction: i32) {
     let new_state = app.snapshot();
     popup::resize_popup_keep_position(hwnd, &new_state);
     popup::begin_switch_animation(hwnd, &old_state, &new_state, direction);
+    refresh_tray_tooltip(app);
+}
+
+fn select_popup_restaurant(hwnd: HWND, app: &App, index: usize) {
+    let old_state = app.snapshot();
+    let Some(old_index) = app.set_restaurant_by_index(index) else {
+        return;
+    };
+    let _ = app.load_cache_for_current();
+    app.check_stale_date_and_refresh();
+    app.maybe_refresh_on_selection();
+    let new_state = app.snapshot();
+    let direction = if index < old_index { -1 } else { 1 };
+    popup::resize_popup_keep_position(hwnd, &new_state);
+    popup::begin_switch_animation(hwnd, &old_state, &new_state, direction);
+    refresh_tray_tooltip(app);
+}
+
+/// Advances the `--no-tray` popup through restaurants every `auto_rotate_seconds`. After user
+/// input the next step waits at least `AUTO_ROTATE_IDLE_MS` so rotation resumes once idle.
+pub fn schedule_auto_rotate_timer(hwnd: HWND, app: &App, after_interaction: bool) {
+    let seconds = app.auto_rotate_seconds();
+    unsafe {
+        let _ = KillTimer(hwnd, POPUP_ROTATE_TIMER_ID);
+        if !app.no_tray || seconds == 0 {
+            return;
+        }
+        let interval = seconds.saturating_mul(1000);
+        let delay = if after_interaction {
+            interval.max(AUTO_ROTATE_IDLE_MS)
+        } else {
+            interval
+        };
+        let _ = SetTimer(hwnd, POPUP_ROTATE_TIMER_ID, delay, None);
+    }
+}
+
+/// (Re)starts the `auto_hide_seconds` countdown; called when the popup shows and on any key or
+/// mouse input in it.
+fn schedule_auto_hide_timer(hwnd: HWND, app: &App) {
+    let seconds = app.auto_hide_seconds();
+    unsafe {
+        let _ = KillTimer(hwnd, POPUP_AUTO_HIDE_TIMER_ID);
+        if seconds > 0 {
+            let _ = SetTimer(
+                hwnd,
+                POPUP_AUTO_HIDE_TIMER_ID,
+                seconds.saturating_mul(1000),
+                None,
+            );
+        }
+    }
+}
+
+/// Hides an idle popup, pinned or not, unless it is animating or a fetch is still loading; then
+/// it checks again shortly.
+fn handle_auto_hide(hwnd: HWND, app: &App) {
+    unsafe {
+        let _ = KillTimer(hwnd, POPUP_AUTO_HIDE_TIMER_ID);
+    }
+    if !popup_is_visible(hwnd) {
+        return;
+    }
+    let state = app.snapshot();
+    if popup::animation_active(hwnd) || state.status == FetchStatus::Loading {
+        unsafe {
+            let _ = SetTimer(hwnd, POPUP_AUTO_HIDE_TIMER_ID, AUTO_HIDE_RETRY_MS, None);
+        }
+        return;
+    }
+    app.persist_settings();
+    popup::begin_close_animation(hwnd, &state);
+}
+
+/// Opens the restaurant page and, with `close_popup_on_open_url`, hides a visible popup.
+fn open_url_from_popup(hwnd_popup: HWND, app: &App) {
+    if app.open_current_url()
+        && app.snapshot().settings.close_popup_on_open_url
+        && !popup_pinned(app)
+        && popup_is_visible(hwnd_popup)
+    {
+        app.persist_settings();
+        popup::hide_popup(hwnd_popup);
+    }
+}
+
+fn cycle_popup_day(hwnd: HWND, app: &App, direction: i32) {
+    popup::clear_day_peek();
+    if app.cycle_day(direction) {
+        popup::reset_scroll();
+        let state = app.snapshot();
+        popup::resize_popup_keep_position(hwnd, &state);
+        refresh_tray_tooltip(app);
+    }
 }
 
 fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
@@ -397,14 +843,39 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
             app.check_stale_date_and_refresh();
             app.maybe_refresh_on_selection();
         }
+        tray::CMD_RESTAURANT_CUSTOM_FIRST..=tray::CMD_RESTAURANT_CUSTOM_LAST => {
+            let offset = (cmd - tray::CMD_RESTAURANT_CUSTOM_FIRST) as usize;
+            if let Some(restaurant) = custom_restaurants().get(offset) {
+                app.set_restaurant(&restaurant.code);
+                let _ = app.load_cache_for_current();
+                app.check_stale_date_and_refresh();
+                app.maybe_refresh_on_selection();
+            }
+        }
         tray::CMD_LANGUAGE_FI => {
             app.set_language("fi");
+            refresh_tray_icon(hwnd, app);
+            let _ = app.load_cache_for_current();
+            app.check_stale_date_and_refresh();
+            app.maybe_refresh_on_selection();
+        }
+        tray::CMD_LANGUAGE_SV => {
+            app.set_language("sv");
+            refresh_tray_icon(hwnd, app);
             let _ = app.load_cache_for_current();
             app.check_stale_date_and_refresh();
             app.maybe_refresh_on_selection();
         }
         tray::CMD_LANGUAGE_EN => {
             app.set_language("en");
+            refresh_tray_icon(hwnd, app);
+            let _ = app.load_cache_for_current();
+            app.check_stale_date_and_refresh();
+            app.maybe_refresh_on_selection();
+        }
+        tray::CMD_LANGUAGE_AUTO => {
+            app.set_auto_language();
+            refresh_tray_icon(hwnd, app);
             let _ = app.load_cache_for_current();
             app.check_stale_date_and_refresh();
             app.maybe_refresh_on_selection();
@@ -412,6 +883,13 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
         tray::CMD_TOGGLE_SHOW_PRICES => {
             app.toggle_show_prices();
         }
+        tray::CMD_TOGGLE_PRICE_COLUMN => {
+            app.toggle_price_column();
+            if popup_is_visible(app.hwnd_popup()) {
+                let state = app.snapshot();
+                popup::resize_popup_keep_position(app.hwnd_popup(), &state);
+            }
+        }
         tray::CMD_TOGGLE_SHOW_ALLERGENS => {
             app.toggle_show_allergens();
             if popup_is_visible(app.hwnd_popup()) {
@@ -428,6 +906,73 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
         tray::CMD_TOGGLE_HIGHLIGHT_L => {
             app.toggle_highlight_lactose_free();
         }
+        tray::CMD_TOGGLE_SHOW_WEEK_NUMBER => {
+            app.toggle_show_week_number();
+        }
+        tray::CMD_TOGGLE_SHOW_STALE_MARKER => {
+            app.toggle_show_stale_marker();
+        }
+        tray::CMD_TOGGLE_REVERSE_GROUP_ORDER => {
+            app.toggle_reverse_group_order();
+        }
+        tray::CMD_TOGGLE_HEADING_POSITION => {
+            app.toggle_heading_position();
+        }
+        tray::CMD_TOGGLE_GROW_DIRECTION => {
+            app.toggle_grow_direction();
+        }
+        tray::CMD_TOGGLE_FLAG_REPEAT_MENUS => {
+            app.toggle_flag_repeat_menus();
+        }
+        tray::CMD_TOGGLE_OPEN_ON_HOVER => {
+            app.toggle_open_on_hover();
+        }
+        tray::CMD_TOGGLE_NOTIFY => {
+            app.toggle_notify_daily_menu();
+        }
+        tray::CMD_TOGGLE_LUNCH_COUNTDOWN => {
+            app.toggle_lunch_countdown();
+        }
+        tray::CMD_TOGGLE_REFRESH_ON_CLICK_OPEN => {
+            app.toggle_refresh_on_click_open();
+        }
+        tray::CMD_TOGGLE_REFRESH_ON_HOVER_OPEN => {
+            app.toggle_refresh_on_hover_open();
+        }
+        tray::CMD_TOGGLE_ALTERNATE_NAMES => {
+            app.toggle_show_alternate_names();
+        }
+        tray::CMD_TOGGLE_SHOW_ENERGY => {
+            app.toggle_show_energy();
+        }
+        tray::CMD_TOGGLE_HIGHLIGHT_FAVORITES => {
+            app.toggle_highlight_favorites();
+        }
+        tray::CMD_TOGGLE_MICRO_MODE => {
+            app.toggle_micro_mode();
+        }
+        tray::CMD_SHOW_ALLERGEN_LEGEND => {
+            app.toggle_show_allergen_legend();
+        }
+        tray::CMD_TOGGLE_ALLERGEN_ICONS => {
+            app.toggle_allergen_icons();
+        }
+        tray::CMD_TOGGLE_SWAP_CLICK_BUTTONS => {
+            app.toggle_swap_click_buttons();
+        }
+        tray::CMD_TOGGLE_INVERT_WHEEL => {
+            app.toggle_invert_wheel();
+        }
+        tray::CMD_TOGGLE_CLOSE_ON_OPEN_URL => {
+            app.toggle_close_popup_on_open_url();
+        }
+        tray::CMD_TOGGLE_PIN => {
+            app.toggle_pin_popup();
+            if popup_is_visible(app.hwnd_popup()) {
+                let state = app.snapshot();
+                popup::resize_popup_keep_position(app.hwnd_popup(), &state);
+            }
+        }
         tray::CMD_TOGGLE_SHOW_STUDENT_PRICE => {
             app.toggle_show_student_price();
         }
@@ -437,11 +982,33 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
         tray::CMD_TOGGLE_SHOW_GUEST_PRICE => {
             app.toggle_show_guest_price();
         }
-        tray::CMD_TOGGLE_HIDE_EXPENSIVE_STUDENT => {
-            app.toggle_hide_expensive_student_meals();
+        tray::CMD_THRESHOLD_OFF => {
+            app.set_expensive_student_threshold(None);
+        }
+        tray::CMD_THRESHOLD_350 => {
+            app.set_expensive_student_threshold(Some(3.5));
+        }
+        tray::CMD_THRESHOLD_400 => {
+            app.set_expensive_student_threshold(Some(4.0));
+        }
+        tray::CMD_THRESHOLD_500 => {
+            app.set_expensive_student_threshold(Some(5.0));
+        }
+        tray::CMD_ANCHOR_CURSOR => {
+            app.set_popup_anchor("cursor");
+        }
+        tray::CMD_ANCHOR_TRAY => {
+            app.set_popup_anchor("tray");
+        }
+        tray::CMD_ANCHOR_TOP_RIGHT => {
+            app.set_popup_anchor("fixed_top_right");
+        }
+        tray::CMD_ANCHOR_BOTTOM_RIGHT => {
+            app.set_popup_anchor("fixed_bottom_right");
         }
         tray::CMD_THEME_LIGHT => {
             app.set_theme("light");
+            refresh_tray_icon(hwnd, app);
             if popup_is_visible(app.hwnd_popup()) {
                 let state = app.snapshot();
                 popup::resize_popup_keep_position(app.hwnd_popup(), &state);
@@ -449,6 +1016,7 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
         }
         tray::CMD_THEME_DARK => {
             app.set_theme("dark");
+            refresh_tray_icon(hwnd, app);
             if popup_is_visible(app.hwnd_popup()) {
                 let state = app.snapshot();
                 popup::resize_popup_keep_position(app.hwnd_popup(), &state);
@@ -456,6 +1024,7 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
         }
         tray::CMD_THEME_BLUE => {
             app.set_theme("blue");
+            refresh_tray_icon(hwnd, app);
             if popup_is_visible(app.hwnd_popup()) {
                 let state = app.snapshot();
                 popup::resize_popup_keep_position(app.hwnd_popup(), &state);
@@ -463,6 +1032,7 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
         }
         tray::CMD_THEME_GREEN => {
             app.set_theme("green");
+            refresh_tray_icon(hwnd, app);
             if popup_is_visible(app.hwnd_popup()) {
                 let state = app.snapshot();
                 popup::resize_popup_keep_position(app.hwnd_popup(), &state);
@@ -470,6 +1040,7 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
         }
         tray::CMD_THEME_TELETEXT1 => {
             app.set_theme("teletext1");
+            refresh_tray_icon(hwnd, app);
             if popup_is_visible(app.hwnd_popup()) {
                 let state = app.snapshot();
                 popup::resize_popup_keep_position(app.hwnd_popup(), &state);
@@ -477,6 +1048,15 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
         }
         tray::CMD_THEME_TELETEXT2 => {
             app.set_theme("teletext2");
+            refresh_tray_icon(hwnd, app);
+            if popup_is_visible(app.hwnd_popup()) {
+                let state = app.snapshot();
+                popup::resize_popup_keep_position(app.hwnd_popup(), &state);
+            }
+        }
+        tray::CMD_THEME_CUSTOM => {
+            app.set_theme("custom");
+            refresh_tray_icon(hwnd, app);
             if popup_is_visible(app.hwnd_popup()) {
                 let state = app.snapshot();
                 popup::resize_popup_keep_position(app.hwnd_popup(), &state);
@@ -494,8 +1074,40 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
         tray::CMD_OPEN_APPDATA_DIR => {
             app.open_appdata_dir();
         }
+        tray::CMD_COPY_RAW_PAYLOAD => {
+            let state = app.snapshot();
+            if state.raw_payload.is_empty() {
+                log_line("copy raw payload: nothing cached yet");
+                tray::show_balloon(hwnd, "Compass Lunch", "No raw payload cached yet.");
+            } else if popup::copy_text_to_clipboard(hwnd, &state.raw_payload) {
+                log_line(&format!(
+                    "copied raw payload ({} bytes)",
+                    state.raw_payload.len()
+                ));
+            }
+        }
+        tray::CMD_TELETEXT_FULLSCREEN => {
+            let popup_hwnd = app.hwnd_popup();
+            popup::show_teletext_fullscreen(popup_hwnd);
+            unsafe {
+                let _ = SetForegroundWindow(popup_hwnd);
+            }
+        }
         tray::CMD_REFRESH_NOW => {
-            app.start_refresh();
+            if app.start_manual_refresh() {
+                cancel_retry_timer(hwnd);
+                app.reset_retry_backoff();
+            } else {
+                log_line("manual refresh ignored: just refreshed");
+                if !app.no_tray {
+                    let language = app.snapshot().settings.language;
+                    tray::show_balloon(
+                        hwnd,
+                        "Compass Lunch",
+                        &text_for(&language, "justRefreshed"),
+                    );
+                }
+            }
         }
         tray::CMD_REFRESH_OFF => {
             app.set_refresh_minutes(0);
@@ -513,6 +1125,9 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
             app.set_refresh_minutes(1440);
             schedule_refresh_timer(hwnd, 1440);
         }
+        tray::CMD_REFRESH_CUSTOM if app.reload_settings() => {
+            apply_reloaded_settings(hwnd, app);
+        }
         tray::CMD_QUIT => unsafe {
             let _ = DestroyWindow(hwnd);
         },
@@ -524,26 +1139,63 @@ fn handle_command(hwnd: HWND, app: &App, cmd: u16) {
     }
 }
 
+/// Re-applies what settings.json controls outside `AppState` after `App::reload_settings`.
+/// `serve_port` still needs a restart.
+fn apply_reloaded_settings(hwnd: HWND, app: &App) {
+    let settings = app.snapshot().settings;
+    schedule_refresh_timer(hwnd, settings.refresh_minutes);
+    schedule_lunch_reminder_timer(hwnd, &settings.lunch_reminder_time);
+    unsafe {
+        let _ = UnregisterHotKey(hwnd, HOTKEY_TOGGLE_POPUP);
+    }
+    register_hotkey(hwnd, settings.hotkey.as_deref());
+    popup::apply_opacity_settings(app.hwnd_popup(), &settings);
+    if !app.no_tray {
+        tray::update_tray_icon(hwnd, &settings);
+    }
+    let _ = app.load_cache_for_current();
+    app.check_stale_date_and_refresh();
+    app.maybe_refresh_on_selection();
+}
+
 fn schedule_refresh_timer(hwnd: HWND, minutes: u32) {
     unsafe {
         let _ = KillTimer(hwnd, TIMER_REFRESH);
         if minutes > 0 {
-            let interval = minutes * 60 * 1000;
+            let interval = minutes.saturating_mul(60 * 1000);
             let _ = SetTimer(hwnd, TIMER_REFRESH, interval, None);
         }
     }
 }
 
+/// Registers `settings.hotkey` (default Win+Shift+L; an empty string turns it off). A combo that
+/// is invalid or already taken is logged and skipped.
+pub fn register_hotkey(hwnd: HWND, hotkey: Option<&str>) {
+    let combo = hotkey.unwrap_or(DEFAULT_HOTKEY).trim();
+    if combo.is_empty() {
+        return;
+    }
+    let Some((modifiers, key)) = parse_hotkey(combo) else {
+        log_line(&format!("hotkey {:?} ignored: not a valid combo", combo));
+        return;
+    };
+    match unsafe { RegisterHotKey(hwnd, HOTKEY_TOGGLE_POPUP, modifiers, key) } {
+        Ok(()) => log_line(&format!("hotkey {} registered", combo)),
+        Err(err) => log_line(&format!("hotkey {} registration failed: {}", combo, err)),
+    }
+}
+
 pub fn schedule_timers(hwnd: HWND, minutes: u32) {
     schedule_refresh_timer(hwnd, minutes);
     schedule_midnight_timer(hwnd);
     schedule_stale_timer(hwnd);
+    schedule_countdown_timer(hwnd);
 }
 
 fn schedule_midnight_timer(hwnd: HWND) {
     unsafe {
         let _ = KillTimer(hwnd, TIMER_MIDNIGHT);
-        let now = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        let now = local_now();
         let date = now.date();
         let next_date = date.next_day().unwrap_or(date);
         let next_midnight = OffsetDateTime::new_in_offset(next_date, Time::MIDNIGHT, now.offset());
@@ -553,6 +1205,30 @@ fn schedule_midnight_timer(hwnd: HWND) {
     }
 }
 
+/// Fires once at the configured local HH:MM, today if still ahead, otherwise tomorrow.
+/// An empty or unparseable time turns the reminder off.
+pub fn schedule_lunch_reminder_timer(hwnd: HWND, reminder_time: &str) {
+    unsafe {
+        let _ = KillTimer(hwnd, TIMER_LUNCH_REMINDER);
+    }
+    let Some(minutes) = parse_clock_minutes(reminder_time) else {
+        return;
+    };
+    let Ok(time) = Time::from_hms((minutes / 60) as u8, (minutes % 60) as u8, 0) else {
+        return;
+    };
+    let now = local_now();
+    let mut next = OffsetDateTime::new_in_offset(now.date(), time, now.offset());
+    if next <= now {
+        let date = now.date().next_day().unwrap_or(now.date());
+        next = OffsetDateTime::new_in_offset(date, time, now.offset());
+    }
+    let millis = (next - now).whole_milliseconds().max(1000) as u32;
+    unsafe {
+        let _ = SetTimer(hwnd, TIMER_LUNCH_REMINDER, millis, None);
+    }
+}
+
 fn schedule_stale_timer(hwnd: HWND) {
     unsafe {
         let _ = KillTimer(hwnd, TIMER_STALE_CHECK);
@@ -561,6 +1237,13 @@ fn schedule_stale_timer(hwnd: HWND) {
     }
 }
 
+fn schedule_countdown_timer(hwnd: HWND) {
+    unsafe {
+        let _ = KillTimer(hwnd, TIMER_COUNTDOWN);
+        let _ = SetTimer(hwnd, TIMER_COUNTDOWN, 30 * 1000, None);
+    }
+}
+
 fn schedule_retry_timer(hwnd: HWND, delay_ms: u32) {
     unsafe {
         let _ = KillTimer(hwnd, TIMER_RETRY_FETCH);
@@ -575,6 +1258,20 @@ fn cancel_retry_timer(hwnd: HWND) {
     }
 }
 
+/// Redraws the weekday badge after a language or theme change.
+fn refresh_tray_icon(hwnd: HWND, app: &App) {
+    if !app.no_tray {
+        tray::update_tray_icon(hwnd, &app.snapshot().settings);
+    }
+}
+
+/// Brings the tray tooltip in line with the shown restaurant and fetch state.
+pub fn refresh_tray_tooltip(app: &App) {
+    if !app.no_tray {
+        tray::update_tooltip(app.hwnd_tray(), &app.snapshot());
+    }
+}
+
 fn popup_is_visible(hwnd: HWND) -> bool {
     unsafe { windows::Win32::UI::WindowsAndMessaging::IsWindowVisible(hwnd).as_bool() }
 }
@@ -598,7 +1295,19 @@ fn stop_hover_timer(hwnd: HWND) {
 fn handle_hover_check(hwnd: HWND, app: &App) {
     let popup_hwnd = app.hwnd_popup();
     if !popup_is_visible(popup_hwnd) {
+        if !open_popup_after_hover(hwnd, app) {
+            stop_hover_timer(hwnd);
+            app.clear_hover_point();
+        }
+        return;
+    }
+    // Dragging can carry the cursor away from the tray; leave the popup open meanwhile.
+    if popup::header_drag_active() {
+        return;
+    }
+    if popup_pinned(app) {
         stop_hover_timer(hwnd);
+        app.clear_hover_point();
         return;
     }
 
@@ -615,6 +1324,37 @@ fn handle_hover_check(hwnd: HWND, app: &App) {
     let in_popup = unsafe { GetWindowRect(popup_hwnd, &mut rect).is_ok() }
         && point_in_rect(&rect, cursor.x, cursor.y);
 
+    if in_popup || cursor_near_tray(app, cursor) {
+        app.hover_returned();
+    } else if app.hover_close_grace_elapsed() {
+        popup::hide_popup(popup_hwnd);
+        stop_hover_timer(hwnd);
+        app.clear_hover_point();
+    }
+}
+
+/// Returns false once the hover should be abandoned. The timer keeps running after opening so
+/// the hover-out check can close the popup again; a popup closed some other way is not reopened.
+fn open_popup_after_hover(hwnd: HWND, app: &App) -> bool {
+    let (Some(delay_ms), Some(elapsed_ms)) = (app.hover_open_delay_ms(), app.hover_elapsed_ms())
+    else {
+        return false;
+    };
+    let Some(cursor) = cursor_point() else {
+        return false;
+    };
+    if !cursor_near_tray(app, cursor) {
+        return false;
+    }
+    if elapsed_ms >= delay_ms as u128 {
+        log_line("tray hover open popup");
+        app.finish_hover_open();
+        show_popup_from_tray(hwnd, app, true);
+    }
+    true
+}
+
+fn cursor_near_tray(app: &App, cursor: POINT) -> bool {
     let in_tray_rect = tray::tray_icon_rect(app.hwnd_tray())
         .map(|rect| point_near_rect(&rect, cursor.x, cursor.y, 12))
         .unwrap_or(false);
@@ -622,13 +1362,7 @@ fn handle_hover_check(hwnd: HWND, app: &App) {
         .hover_point()
         .map(|(x, y)| (cursor.x - x).abs() <= 32 && (cursor.y - y).abs() <= 32)
         .unwrap_or(false);
-    let in_tray = in_tray_rect || in_tray_hover;
-
-    if !(in_popup || in_tray) {
-        popup::hide_popup(popup_hwnd);
-        stop_hover_timer(hwnd);
-        app.clear_hover_point();
-    }
+    in_tray_rect || in_tray_hover
 }
 
 fn handle_stale_check(hwnd: HWND, app: &App) {